@@ -143,6 +143,15 @@ export function bindImport(model) {
     });
 }
 
+export function bindRecommend(model) {
+    const recommendButton = document.querySelector("button.bookmark#recommend");
+
+    recommendButton.addEventListener("click", () => {
+        const eventIds = model.exportEventIdsAsText();
+        window.location.href = `/recommendations?ids=${encodeURIComponent(eventIds)}`;
+    });
+}
+
 export function bindHide() {
     const showBookmarkedButtons = document.querySelectorAll("button.only-show-bookmarked");
     const showAllButtons = document.querySelectorAll("button.show-all");
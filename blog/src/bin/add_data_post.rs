@@ -2,9 +2,18 @@ use std::path::PathBuf;
 
 use chrono::Utc;
 use clap::Parser;
-use shared::{env::load_secret, inmemory_openai::InMemoryOpenAIQueryable, summary::load_summary};
+use shared::{
+    cli::EmbeddingProviderArgs,
+    inmemory_openai::InMemoryOpenAIQueryable,
+    manifest::ManifestPolicy,
+    nearest::IndexConfig,
+    summary::{load_summary, DataSummary},
+};
 use tracing::info;
 
+/// how many of the largest tracks of the latest year to list
+const LARGEST_TRACKS: usize = 5;
+
 #[derive(Parser, Debug)]
 #[command(author, version, about = "Create a blog post summarizing current data")]
 struct Args {
@@ -15,6 +24,14 @@ struct Args {
     /// Path to blog posts directory
     #[arg(short, long)]
     blog_content_dir: PathBuf,
+
+    /// Markdown of what changed in the schedule since the last import, as written by
+    /// `diff_events --markdown`
+    #[arg(long)]
+    schedule_changes: Option<PathBuf>,
+
+    #[command(flatten)]
+    embedding: EmbeddingProviderArgs,
 }
 
 #[tokio::main]
@@ -23,14 +40,34 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let args = Args::parse();
 
-    let openai_api_key = load_secret("OPENAI_API_KEY")?;
-    let queryable = InMemoryOpenAIQueryable::connect(&args.model_dir, &openai_api_key).await?;
+    let embedding_provider = args.embedding.config(None).build()?;
+    let queryable = InMemoryOpenAIQueryable::connect(
+        &args.model_dir,
+        embedding_provider,
+        IndexConfig::BruteForce,
+        ManifestPolicy::Strict,
+    )
+    .await?;
 
     let summary = load_summary(&queryable).await?;
 
     let today = Utc::now().format("%Y-%m-%d").to_string();
     let post_path = args.blog_content_dir.join(format!("{}.md", today));
 
+    let schedule_changes = args
+        .schedule_changes
+        .as_ref()
+        .map(std::fs::read_to_string)
+        .transpose()?;
+
+    let content = data_post(&summary, schedule_changes.as_deref());
+    std::fs::write(&post_path, content)?;
+    info!("Created post: {}", post_path.display());
+
+    Ok(())
+}
+
+fn data_post(summary: &DataSummary, schedule_changes: Option<&str>) -> String {
     let mut content = String::new();
     content.push_str("---\n");
     content.push_str("title: Data Update\n");
@@ -38,18 +75,108 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     content.push_str("  - data\n");
     content.push_str("---\n\n");
     content.push_str("Updated event data.\n\n");
-    content.push_str("| Year | Events | Presenters | Rooms | Tracks | Videos | Slides | Links |\n");
-    content.push_str("|------|--------|--------|-------|--------|--------|--------|-------|\n");
+    content.push_str("| Year | Events | Presenters | Rooms | Tracks | Videos | Slides | Links | Average length |\n");
+    content.push_str(
+        "|------|--------|--------|-------|--------|--------|--------|-------|-------|\n",
+    );
     for (year, s) in &summary.by_year {
         let video_hours = s.video_duration.num_hours();
+        let returning = |count: usize| {
+            s.change
+                .as_ref()
+                .map_or(String::new(), |_| format!(" ({} returning)", count))
+        };
         content.push_str(&format!(
-            "| {} | {} | {} | {} | {} | {} (~{}h) | {} | {} |\n",
-            year, s.events, s.people, s.rooms, s.tracks, s.videos, video_hours, s.slides, s.links
+            "| {} | {} | {}{} | {} | {}{} | {} ({:.0}%, ~{}h) | {} ({:.0}% of events) | {} | {} min |\n",
+            year,
+            s.events,
+            s.people,
+            returning(s.returning_people),
+            s.rooms,
+            s.tracks,
+            returning(s.returning_tracks),
+            s.videos,
+            s.video_share() * 100.0,
+            video_hours,
+            s.slides,
+            s.slides_share() * 100.0,
+            s.links,
+            s.average_duration().num_minutes()
         ));
     }
 
-    std::fs::write(&post_path, content)?;
-    info!("Created post: {}", post_path.display());
+    if let Some(schedule_changes) = schedule_changes {
+        content.push_str("\n## Schedule changes\n\n");
+        content.push_str(schedule_changes);
+    }
 
-    Ok(())
+    let Some((year, latest)) = summary.by_year.last_key_value() else {
+        return content;
+    };
+    if let Some(change) = &latest.change {
+        content.push_str(&format!(
+            "\n## What changed in {} since {}\n\n",
+            year, change.previous_year
+        ));
+        content.push_str(&format!(
+            "* {:+} events ({:+.0}%), {:+} presenters, {:+} rooms and {:+} tracks\n",
+            change.events,
+            change.events_growth * 100.0,
+            change.people,
+            change.rooms,
+            change.tracks
+        ));
+        content.push_str(&format!(
+            "* {:+} videos, {:+} slides and {:+} links\n",
+            change.videos, change.slides, change.links
+        ));
+        content.push_str(&format!(
+            "* {} of {} presenters, and {} of {} tracks, were also there in {}\n",
+            latest.returning_people,
+            latest.people,
+            latest.returning_tracks,
+            latest.tracks,
+            change.previous_year
+        ));
+        for (heading, tracks) in [
+            ("new tracks", &change.new_tracks),
+            ("tracks not running again", &change.ended_tracks),
+        ] {
+            if !tracks.is_empty() {
+                content.push_str(&format!("* {}:\n", heading));
+                for track in tracks {
+                    content.push_str(&format!("    * {}\n", track));
+                }
+            }
+        }
+    }
+
+    content.push_str(&format!("\n## {} by day\n\n", year));
+    content.push_str("| Day | Events | With video | With slides | Average length |\n");
+    content.push_str("|-----|--------|------------|-------------|----------------|\n");
+    for (day, b) in &latest.by_day {
+        content.push_str(&format!(
+            "| {} | {} | {:.0}% | {:.0}% | {} min |\n",
+            day.format("%a %-d %b"),
+            b.events,
+            b.video_share() * 100.0,
+            b.slides_share() * 100.0,
+            b.average_duration().num_minutes()
+        ));
+    }
+
+    content.push_str(&format!("\n## Largest tracks in {}\n\n", year));
+    content.push_str("| Track | Events | With video | With slides |\n");
+    content.push_str("|-------|--------|------------|-------------|\n");
+    for (track, b) in latest.largest_tracks().into_iter().take(LARGEST_TRACKS) {
+        content.push_str(&format!(
+            "| {} | {} | {:.0}% | {:.0}% |\n",
+            track,
+            b.events,
+            b.video_share() * 100.0,
+            b.slides_share() * 100.0
+        ));
+    }
+
+    content
 }
@@ -0,0 +1,316 @@
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use futures::stream::{self, StreamExt};
+use indicatif::ProgressBar;
+use shared::embedding::EmbeddingProvider;
+use shared::error::EmbeddingRequestError;
+use shared::model::OpenAIVector;
+use shared::nearest::XorShift;
+use tokio::time::{sleep_until, Instant};
+use tracing::{info, warn};
+
+use crate::embedding_cache::EmbeddingCache;
+
+/// OpenAI allows up to 2048 inputs in a request, but also limits the total number of tokens, and
+/// each input can be up to ~8k tokens
+pub const DEFAULT_BATCH_SIZE: usize = 16;
+pub const DEFAULT_CONCURRENCY: usize = 4;
+pub const DEFAULT_CHECKPOINT_EVERY: usize = 20;
+
+/// Exponential backoff between attempts at a request
+#[derive(Debug, Clone, Copy)]
+pub struct Backoff {
+    pub max_retries: u32,
+    pub initial: Duration,
+    pub max: Duration,
+}
+
+impl Backoff {
+    pub fn new(max_retries: u32) -> Backoff {
+        Backoff {
+            max_retries,
+            initial: Duration::from_secs(1),
+            max: Duration::from_secs(60),
+        }
+    }
+
+    /// `initial` doubled for each retry so far, up to `max`. `jitter` (0.0 to 1.0) then picks
+    /// somewhere in the top half of that, so that requests which failed together don't all retry
+    /// together.
+    pub fn delay(&self, retries: u32, jitter: f64) -> Duration {
+        let delay = self
+            .initial
+            .saturating_mul(2u32.saturating_pow(retries))
+            .min(self.max);
+        delay.mul_f64(0.5 + 0.5 * jitter.clamp(0.0, 1.0))
+    }
+}
+
+/// Wraps a provider to retry requests which were rate limited or failed on the server, waiting
+/// for as long as the server asked in `Retry-After`, or backing off if it didn't say. Requests
+/// which fail for other reasons, such as a bad key, aren't retried.
+pub struct RetryingEmbedder<'a, P> {
+    provider: &'a P,
+    backoff: Backoff,
+    /// once any request is rate limited, no request is made until this, as the limit is shared
+    paused_until: Mutex<Instant>,
+    rng: Mutex<XorShift>,
+}
+
+impl<'a, P: EmbeddingProvider> RetryingEmbedder<'a, P> {
+    pub fn new(provider: &'a P, backoff: Backoff) -> RetryingEmbedder<'a, P> {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(1, |since| since.as_nanos() as u64)
+            | 1;
+        RetryingEmbedder {
+            provider,
+            backoff,
+            paused_until: Mutex::new(Instant::now()),
+            rng: Mutex::new(XorShift::new(seed)),
+        }
+    }
+
+    pub async fn embed_batch(
+        &self,
+        inputs: &[String],
+    ) -> Result<Vec<OpenAIVector>, Box<dyn std::error::Error>> {
+        let mut retries = 0;
+        loop {
+            let paused_until = *self.paused_until.lock().unwrap();
+            sleep_until(paused_until).await;
+            let error = match self.provider.embed_batch(inputs).await {
+                Ok(embeddings) => return Ok(embeddings),
+                Err(error) => error,
+            };
+            let request_error = error.downcast_ref::<EmbeddingRequestError>();
+            if let Some(EmbeddingRequestError::Failed { .. }) = request_error {
+                return Err(error);
+            }
+            if retries >= self.backoff.max_retries {
+                return Err(format!(
+                    "error: '{}', even after {} retries (max: {})",
+                    error, retries, self.backoff.max_retries
+                )
+                .into());
+            }
+            let retry_after = match request_error {
+                Some(EmbeddingRequestError::Retryable { retry_after, .. }) => *retry_after,
+                _ => None,
+            };
+            let delay = retry_after.unwrap_or_else(|| {
+                let jitter = self.rng.lock().unwrap().next_f64();
+                self.backoff.delay(retries, jitter)
+            });
+            retries += 1;
+            warn!(
+                "error: '{}', will retry in {:?} (retry count = {})",
+                error, delay, retries
+            );
+            let retry_at = Instant::now() + delay;
+            if request_error.is_some_and(|e| e.is_rate_limited()) {
+                let mut paused_until = self.paused_until.lock().unwrap();
+                *paused_until = (*paused_until).max(retry_at);
+            }
+            sleep_until(retry_at).await;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Batching {
+    /// inputs per request
+    pub batch_size: usize,
+    /// requests in flight at once
+    pub concurrency: usize,
+    /// save the cache after this many requests
+    pub checkpoint_every: usize,
+}
+
+/// Embed each of `pending`, a cache key and the input to embed for it, adding the embeddings to
+/// `cache` as each batch comes back. `cache` is saved to `checkpoint` every so often, and before
+/// giving up if a batch fails, so that a run which stops part way through can carry on from there
+/// rather than starting again.
+pub async fn embed_into_cache<P: EmbeddingProvider>(
+    embedder: &RetryingEmbedder<'_, P>,
+    cache: &mut EmbeddingCache,
+    pending: Vec<(String, String)>,
+    batching: &Batching,
+    checkpoint: &Path,
+    progress: &ProgressBar,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let batches: Vec<Vec<(String, String)>> = pending
+        .chunks(batching.batch_size.max(1))
+        .map(|batch| batch.to_vec())
+        .collect();
+    let mut results = stream::iter(batches)
+        .map(|batch| async move {
+            let inputs: Vec<String> = batch.iter().map(|(_, input)| input.clone()).collect();
+            let embeddings = embedder.embed_batch(&inputs).await;
+            (batch, embeddings)
+        })
+        .buffer_unordered(batching.concurrency.max(1));
+
+    let mut completed = 0;
+    while let Some((batch, embeddings)) = results.next().await {
+        let embeddings = match embeddings {
+            Ok(embeddings) => embeddings,
+            Err(error) => {
+                warn!(
+                    "Saving {} embeddings to {:?} before giving up",
+                    cache.len(),
+                    checkpoint
+                );
+                cache.save(checkpoint)?;
+                return Err(error);
+            }
+        };
+        progress.inc(batch.len() as u64);
+        for ((key, _), embedding) in batch.into_iter().zip(embeddings) {
+            cache.insert(key, embedding);
+        }
+        completed += 1;
+        if completed % batching.checkpoint_every.max(1) == 0 {
+            info!(
+                "Checkpointing {} embeddings to {:?}",
+                cache.len(),
+                checkpoint
+            );
+            cache.save(checkpoint)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::{Cell, RefCell};
+    use std::collections::VecDeque;
+
+    use shared::offline::OfflineEmbeddingProvider;
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::embedding_cache::EMBEDDING_CACHE_FILE;
+
+    /// fails with each of `failures` in turn, and then embeds like `OfflineEmbeddingProvider`
+    struct FlakyProvider {
+        failures: RefCell<VecDeque<EmbeddingRequestError>>,
+        requests: Cell<usize>,
+    }
+
+    impl FlakyProvider {
+        fn new(failures: Vec<EmbeddingRequestError>) -> FlakyProvider {
+            FlakyProvider {
+                failures: RefCell::new(failures.into()),
+                requests: Cell::new(0),
+            }
+        }
+    }
+
+    impl EmbeddingProvider for FlakyProvider {
+        fn model(&self) -> &str {
+            "flaky"
+        }
+
+        async fn embed(&self, input: &str) -> Result<OpenAIVector, Box<dyn std::error::Error>> {
+            self.requests.set(self.requests.get() + 1);
+            match self.failures.borrow_mut().pop_front() {
+                Some(failure) => Err(failure.into()),
+                None => Ok(OfflineEmbeddingProvider::default().embed_sync(input)),
+            }
+        }
+    }
+
+    fn rate_limited() -> EmbeddingRequestError {
+        EmbeddingRequestError::Retryable {
+            status: Some(429),
+            retry_after: Some(Duration::from_millis(1)),
+            message: "slow down".to_string(),
+        }
+    }
+
+    fn backoff(max_retries: u32) -> Backoff {
+        Backoff {
+            max_retries,
+            initial: Duration::from_millis(1),
+            max: Duration::from_millis(4),
+        }
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_the_max_with_jitter() {
+        let backoff = Backoff::new(5);
+
+        assert_eq!(Duration::from_secs(1), backoff.delay(0, 1.0));
+        assert_eq!(Duration::from_millis(500), backoff.delay(0, 0.0));
+        assert_eq!(Duration::from_secs(8), backoff.delay(3, 1.0));
+        assert_eq!(Duration::from_secs(60), backoff.delay(10, 1.0));
+        assert_eq!(Duration::from_secs(60), backoff.delay(100, 1.0));
+    }
+
+    #[tokio::test]
+    async fn test_retries_only_what_may_succeed_later() {
+        let server_error = EmbeddingRequestError::Retryable {
+            status: Some(503),
+            retry_after: None,
+            message: "busy".to_string(),
+        };
+        let provider = FlakyProvider::new(vec![rate_limited(), server_error]);
+        let embedder = RetryingEmbedder::new(&provider, backoff(2));
+        assert!(embedder.embed_batch(&["rust".to_string()]).await.is_ok());
+        assert_eq!(3, provider.requests.get());
+
+        let provider = FlakyProvider::new(vec![rate_limited(), rate_limited(), rate_limited()]);
+        let embedder = RetryingEmbedder::new(&provider, backoff(2));
+        assert!(embedder.embed_batch(&["rust".to_string()]).await.is_err());
+        assert_eq!(3, provider.requests.get());
+
+        let bad_key = EmbeddingRequestError::Failed {
+            status: 401,
+            message: "bad key".to_string(),
+        };
+        let provider = FlakyProvider::new(vec![bad_key]);
+        let embedder = RetryingEmbedder::new(&provider, backoff(2));
+        assert!(embedder.embed_batch(&["rust".to_string()]).await.is_err());
+        assert_eq!(1, provider.requests.get());
+    }
+
+    #[tokio::test]
+    async fn test_every_batch_ends_up_in_the_cache() {
+        let dir = TempDir::new().unwrap();
+        let checkpoint = dir.path().join(EMBEDDING_CACHE_FILE);
+        let provider = FlakyProvider::new(vec![rate_limited()]);
+        let embedder = RetryingEmbedder::new(&provider, backoff(2));
+        let pending: Vec<(String, String)> = (0..6)
+            .map(|i| (format!("key {}", i), format!("input {}", i)))
+            .collect();
+        let batching = Batching {
+            batch_size: 2,
+            concurrency: 2,
+            checkpoint_every: 2,
+        };
+
+        let mut cache = EmbeddingCache::default();
+        embed_into_cache(
+            &embedder,
+            &mut cache,
+            pending,
+            &batching,
+            &checkpoint,
+            &ProgressBar::hidden(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(6, cache.len());
+        assert_eq!(
+            Some(&OfflineEmbeddingProvider::default().embed_sync("input 3")),
+            cache.embedding("key 3")
+        );
+        // the checkpoint after the second of the three batches
+        assert_eq!(4, EmbeddingCache::load(&checkpoint).unwrap().len());
+    }
+}
@@ -0,0 +1,53 @@
+use std::{fs::File, io::BufReader, path::PathBuf};
+
+use clap::Parser;
+use content::model_dir::record_checksums;
+use flate2::read::GzDecoder;
+use shared::binary_index::{write_binary_index, VectorEncoding};
+use shared::manifest::sha256_file;
+use shared::model::{EventId, OpenAIEmbedding, OpenAIVector};
+use tracing::info;
+
+/// Convert embeddings.json.gz into embeddings.bin, which the webapp maps into memory rather than
+/// parsing. As this is the last step in building a model dir, it also records the checksums of
+/// the finished files in manifest.json.
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// model area, where embeddings.json.gz is, and where embeddings.bin will be written
+    #[arg(long)]
+    model_dir: PathBuf,
+
+    /// how to store each vector
+    #[arg(long, value_enum, default_value = "f32")]
+    encoding: VectorEncoding,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    tracing_subscriber::fmt::init();
+
+    let args = Args::parse();
+    info!("args: {:?}", args);
+
+    let json_path = args.model_dir.join("embeddings").with_extension("json.gz");
+    info!("Reading embeddings from {:?}", json_path);
+    let reader = BufReader::new(GzDecoder::new(File::open(&json_path)?));
+    let embeddings: Vec<OpenAIEmbedding> = serde_json::from_reader(reader)?;
+
+    let binary_path = args.model_dir.join("embeddings").with_extension("bin");
+    info!(
+        "Writing {} embeddings as {:?} to {:?}",
+        embeddings.len(),
+        args.encoding,
+        binary_path
+    );
+    let entries: Vec<(EventId, &OpenAIVector)> = embeddings
+        .iter()
+        .map(|e| (e.event_id, &e.embedding))
+        .collect();
+    // so that the webapp can tell if embeddings.json.gz has been replaced since
+    let source = sha256_file(&json_path)?;
+    write_binary_index(&binary_path, args.encoding, &source, &entries)?;
+
+    record_checksums(&args.model_dir)
+}
@@ -0,0 +1,118 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+use content::clustering::{distinctive_terms, k_means};
+use content::model_dir::{load_embedded_events, record_checksums};
+use shared::lexical::tokenize;
+use shared::model::{Event, OpenAIVector};
+use shared::topics::{Topic, TopicId, TopicIndex, TopicMember};
+use tracing::info;
+
+/// how many terms to name a topic with
+const TERMS_IN_NAME: usize = 3;
+
+/// how many distinctive terms to keep for each topic
+const TERMS_PER_TOPIC: usize = 10;
+
+/// Cluster the events of each year, and of all years together, by their embeddings, naming each
+/// cluster from its most distinctive terms, and write them to topics.json
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// model area, where events.json and embeddings.json.gz are, and where topics.json will be
+    /// written
+    #[arg(long)]
+    model_dir: PathBuf,
+
+    /// roughly how many events each topic should have, which sets how many topics there are
+    #[arg(long, default_value = "30")]
+    events_per_topic: usize,
+
+    /// stop clustering after this many iterations, even if events are still moving between topics
+    #[arg(long, default_value = "100")]
+    max_iterations: usize,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    tracing_subscriber::fmt::init();
+
+    let args = Args::parse();
+    info!("args: {:?}", args);
+
+    let embedded = load_embedded_events(&args.model_dir)?;
+
+    let mut years: Vec<u32> = embedded.iter().map(|(event, _)| event.year).collect();
+    years.sort();
+    years.dedup();
+    let mut topics = vec![];
+    for year in years.into_iter().map(Some).chain([None]) {
+        let in_scope: Vec<(&Event, &OpenAIVector)> = embedded
+            .iter()
+            .filter(|(event, _)| year.is_none() || Some(event.year) == year)
+            .map(|(event, embedding)| (event, embedding))
+            .collect();
+        let year_topics = cluster(&in_scope, year, &args);
+        info!(
+            "Clustered {} events of {} into {} topics",
+            in_scope.len(),
+            year.map_or("all years".to_string(), |year| year.to_string()),
+            year_topics.len()
+        );
+        topics.extend(year_topics);
+    }
+
+    info!("Writing {} topics to {:?}", topics.len(), args.model_dir);
+    TopicIndex::new(topics).save(&args.model_dir)?;
+
+    record_checksums(&args.model_dir)
+}
+
+/// Topics for `events`, largest first
+fn cluster(events: &[(&Event, &OpenAIVector)], year: Option<u32>, args: &Args) -> Vec<Topic> {
+    let vectors: Vec<&OpenAIVector> = events.iter().map(|(_, embedding)| *embedding).collect();
+    let k = (events.len() / args.events_per_topic.max(1)).max(1);
+    let clusters = k_means(&vectors, k, args.max_iterations);
+
+    let documents: Vec<Vec<String>> = events
+        .iter()
+        .map(|(event, _)| tokenize(&format!("{} {}", event.title, event.r#abstract)))
+        .collect();
+    let terms = distinctive_terms(
+        &documents,
+        &clusters.assignments,
+        clusters.centres.len(),
+        TERMS_PER_TOPIC,
+    );
+
+    let mut members: Vec<Vec<TopicMember>> = vec![vec![]; clusters.centres.len()];
+    for ((event, embedding), cluster) in events.iter().zip(&clusters.assignments) {
+        members[*cluster].push(TopicMember {
+            event_id: event.id,
+            distance: embedding.metric_distance(&clusters.centres[*cluster]),
+        });
+    }
+    let mut clustered: Vec<(Vec<TopicMember>, Vec<String>)> = members
+        .into_iter()
+        .zip(terms)
+        .filter(|(members, _)| !members.is_empty())
+        .collect();
+    clustered.sort_by_key(|(members, _)| std::cmp::Reverse(members.len()));
+    clustered
+        .into_iter()
+        .map(|(mut members, terms)| {
+            members.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+            Topic {
+                id: TopicId::new(year, members[0].event_id),
+                year,
+                name: terms
+                    .iter()
+                    .take(TERMS_IN_NAME)
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                terms,
+                members,
+            }
+        })
+        .collect()
+}
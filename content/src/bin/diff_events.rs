@@ -0,0 +1,61 @@
+use std::path::{Path, PathBuf};
+
+use clap::Parser;
+use content::schedule_diff::diff_events;
+use shared::model::Event;
+use tracing::info;
+
+/// Compare two imports of the schedule, reporting which events were added, removed, retimed,
+/// moved to another room, retitled or had their abstract changed
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// events.json from the earlier import
+    #[arg(long)]
+    before: PathBuf,
+
+    /// events.json from the later import
+    #[arg(long)]
+    after: PathBuf,
+
+    /// write the plain text report here, rather than to stdout
+    #[arg(long)]
+    report: Option<PathBuf>,
+
+    /// also write the changes as markdown here, for `add_data_post --schedule-changes`
+    #[arg(long)]
+    markdown: Option<PathBuf>,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    tracing_subscriber::fmt::init();
+
+    let args = Args::parse();
+
+    let before = load_events(&args.before)?;
+    let after = load_events(&args.after)?;
+    let diff = diff_events(&before, &after);
+    info!("{}", diff.counts());
+
+    let report = diff.report();
+    match &args.report {
+        Some(path) => {
+            std::fs::write(path, report)?;
+            info!("Wrote report to {:?}", path);
+        }
+        None => print!("{}", report),
+    }
+    if let Some(path) = &args.markdown {
+        std::fs::write(path, diff.markdown())?;
+        info!("Wrote markdown to {:?}", path);
+    }
+
+    Ok(())
+}
+
+fn load_events(path: &Path) -> Result<Vec<Event>, Box<dyn std::error::Error>> {
+    info!("Loading events from {:?}", path);
+    let file = std::fs::File::open(path)?;
+    let reader = std::io::BufReader::new(file);
+    Ok(serde_json::from_reader(reader)?)
+}
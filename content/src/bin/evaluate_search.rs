@@ -0,0 +1,191 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use clap::Parser;
+use serde::Serialize;
+use shared::cli::{EmbeddingProviderArgs, NearestNeighbourArgs};
+use shared::evaluation::{
+    evaluate, load_golden_queries, Evaluation, GoldenQuery, Scores, DEFAULT_CUTOFF,
+};
+use shared::inmemory_openai::InMemoryOpenAIQueryable;
+use shared::manifest::ManifestPolicy;
+use shared::queryable::SearchMode;
+use shared::rerank::DEFAULT_MMR_LAMBDA;
+use tracing::info;
+
+/// scores which differ by less than this are treated as the same
+const SIGNIFICANT_CHANGE: f64 = 1e-6;
+
+/// Measure search quality against a golden set of queries, as recall@k, MRR and nDCG@k, and
+/// optionally compare it to another build of the model
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// model area to evaluate
+    #[arg(long)]
+    model_dir: PathBuf,
+
+    /// another model area, e.g. the current build, to compare against
+    #[arg(long)]
+    baseline_model_dir: Option<PathBuf>,
+
+    /// queries, and the events each should find
+    #[arg(long, default_value = "./test_shared/data/golden_queries.json")]
+    golden_queries: PathBuf,
+
+    /// how many results of each search to score
+    #[arg(long, default_value_t = DEFAULT_CUTOFF)]
+    k: u8,
+
+    /// search modes to evaluate, all of them if not given
+    #[arg(long, value_enum)]
+    mode: Vec<SearchMode>,
+
+    /// balance of relevance against diversity, see `/search?lambda=`
+    #[arg(long, default_value_t = DEFAULT_MMR_LAMBDA)]
+    lambda: f64,
+
+    /// write the scores of every query as json to this path
+    #[arg(long)]
+    report: Option<PathBuf>,
+
+    #[command(flatten)]
+    embedding: EmbeddingProviderArgs,
+
+    #[command(flatten)]
+    nearest: NearestNeighbourArgs,
+}
+
+#[derive(Serialize)]
+struct Report {
+    candidate: Vec<Evaluation>,
+    baseline: Option<Vec<Evaluation>>,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    tracing_subscriber::fmt::init();
+
+    let args = Args::parse();
+    info!("args: {:?}", args);
+
+    let golden = load_golden_queries(&args.golden_queries)?;
+    info!(
+        "Loaded {} golden queries from {:?}",
+        golden.len(),
+        args.golden_queries
+    );
+    let modes = if args.mode.is_empty() {
+        SearchMode::ALL.to_vec()
+    } else {
+        args.mode.clone()
+    };
+
+    let candidate = evaluate_model(&args.model_dir, &golden, &modes, &args).await?;
+    let baseline = match &args.baseline_model_dir {
+        Some(baseline_model_dir) => {
+            Some(evaluate_model(baseline_model_dir, &golden, &modes, &args).await?)
+        }
+        None => None,
+    };
+
+    for (i, evaluation) in candidate.iter().enumerate() {
+        print_evaluation(evaluation, baseline.as_ref().map(|baseline| &baseline[i]));
+    }
+
+    if let Some(report) = &args.report {
+        info!("Writing report to {:?}", report);
+        let writer = BufWriter::new(File::create(report)?);
+        serde_json::to_writer_pretty(
+            writer,
+            &Report {
+                candidate,
+                baseline,
+            },
+        )?;
+    }
+
+    Ok(())
+}
+
+async fn evaluate_model(
+    model_dir: &Path,
+    golden: &[GoldenQuery],
+    modes: &[SearchMode],
+    args: &Args,
+) -> Result<Vec<Evaluation>, Box<dyn std::error::Error>> {
+    let queryable = InMemoryOpenAIQueryable::connect(
+        model_dir,
+        args.embedding.config(None).build()?,
+        args.nearest.config(),
+        ManifestPolicy::Strict,
+    )
+    .await?;
+    let mut evaluations = vec![];
+    for mode in modes {
+        info!("Evaluating {} search of {:?}", mode, model_dir);
+        evaluations.push(evaluate(&queryable, golden, args.k, *mode, args.lambda).await?);
+    }
+    Ok(evaluations)
+}
+
+fn print_evaluation(candidate: &Evaluation, baseline: Option<&Evaluation>) {
+    let k = candidate.k;
+    println!(
+        "\n{} search, {} queries\n{:<40} {:>9} {:>9} {:>9}",
+        candidate.mode,
+        candidate.queries.len(),
+        "",
+        format!("recall@{}", k),
+        "MRR",
+        format!("nDCG@{}", k)
+    );
+    let Some(baseline) = baseline else {
+        for query in &candidate.queries {
+            print_scores(&query.query, &query.scores);
+        }
+        print_scores("mean", &candidate.mean);
+        return;
+    };
+    // only the queries which changed, as the rest are the same in both
+    for (query, baseline_query) in candidate.queries.iter().zip(&baseline.queries) {
+        let change = query.scores.change_from(&baseline_query.scores);
+        if significant(&change) {
+            print_change(&query.query, &change);
+        }
+    }
+    print_scores("baseline mean", &baseline.mean);
+    print_scores("mean", &candidate.mean);
+    print_change("change", &candidate.mean.change_from(&baseline.mean));
+}
+
+fn significant(change: &Scores) -> bool {
+    [change.recall, change.reciprocal_rank, change.ndcg]
+        .iter()
+        .any(|c| c.abs() > SIGNIFICANT_CHANGE)
+}
+
+fn print_scores(label: &str, scores: &Scores) {
+    println!(
+        "{:<40} {:>9.3} {:>9.3} {:>9.3}",
+        truncate(label),
+        scores.recall,
+        scores.reciprocal_rank,
+        scores.ndcg
+    );
+}
+
+fn print_change(label: &str, change: &Scores) {
+    println!(
+        "{:<40} {:>+9.3} {:>+9.3} {:>+9.3}",
+        truncate(label),
+        change.recall,
+        change.reciprocal_rank,
+        change.ndcg
+    );
+}
+
+fn truncate(label: &str) -> String {
+    label.chars().take(40).collect()
+}
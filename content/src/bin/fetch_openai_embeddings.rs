@@ -1,24 +1,29 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
-use std::io::{BufReader, BufWriter, Read, Write};
-use std::path::PathBuf;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
+use chrono::Utc;
 use clap::Parser;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::Serialize;
 
-use content::video_index::VideoIndex;
-use openai_dive::v1::api::Client;
-
-use openai_dive::v1::resources::embedding::{
-    EmbeddingInput, EmbeddingParameters, EmbeddingResponse,
+use content::batch_embedding::{
+    embed_into_cache, Backoff, Batching, RetryingEmbedder, DEFAULT_BATCH_SIZE,
+    DEFAULT_CHECKPOINT_EVERY, DEFAULT_CONCURRENCY,
 };
+use content::embedding_cache::{cache_key, EmbeddingCache, EMBEDDING_CACHE_FILE};
+use content::passages::slide_passages;
+use content::slide_content::load_slide_content;
+use content::video_index::VideoIndex;
 
-use reqwest::ClientBuilder;
-use shared::cli::progress_bar;
-use shared::env::load_secret;
-use shared::model::{self, Event, OpenAIEmbedding};
-use subtp::vtt::VttBlock;
-use tracing::{debug, info, warn};
+use shared::cli::{progress_bar, EmbeddingProviderArgs};
+use shared::embedding::EmbeddingProvider;
+use shared::manifest::{ContentSource, Manifest};
+use shared::model::{self, Event, EventType, OpenAIEmbedding, PassageEmbedding};
+use tracing::{debug, info};
 
 /// Fetch Embeddings
 #[derive(Parser, Debug)]
@@ -36,6 +41,18 @@ struct Args {
     #[arg(long, default_value = "5")]
     retries: u32,
 
+    /// number of texts to embed in each request
+    #[arg(long, default_value_t = DEFAULT_BATCH_SIZE)]
+    batch_size: usize,
+
+    /// number of embedding requests to have in flight at once
+    #[arg(long, default_value_t = DEFAULT_CONCURRENCY)]
+    concurrency: usize,
+
+    /// save the embedding cache after this many requests, so that an interrupted run can resume
+    #[arg(long, default_value_t = DEFAULT_CHECKPOINT_EVERY)]
+    checkpoint_every: usize,
+
     /// include slide content at path
     #[arg(long)]
     include_slide_content: Option<PathBuf>,
@@ -43,6 +60,14 @@ struct Args {
     /// include video content at path
     #[arg(long)]
     include_video_content: Option<PathBuf>,
+
+    /// embeddings kept between runs, so that only new or changed text is embedded, by default
+    /// embedding_cache.json.gz in the model area
+    #[arg(long)]
+    embedding_cache: Option<PathBuf>,
+
+    #[command(flatten)]
+    embedding: EmbeddingProviderArgs,
 }
 
 fn parse_seconds_duration(arg: &str) -> Result<std::time::Duration, std::num::ParseIntError> {
@@ -57,15 +82,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Args::parse();
     info!("args: {:?}", args);
 
-    let openai_api_key = load_secret("OPENAI_API_KEY")?;
-
-    let reqwest_client = ClientBuilder::new().timeout(args.timeout).build()?;
-
-    let openai_client = Client {
-        api_key: openai_api_key,
-        http_client: reqwest_client,
-        ..Default::default()
-    };
+    let embedding_provider = args.embedding.config(Some(args.timeout)).build()?;
+    info!("Embedding with model '{}'", embedding_provider.model());
 
     let events_path = args.model_dir.join("events").with_extension("json");
 
@@ -73,22 +91,29 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let reader = BufReader::new(File::open(events_path)?);
     let events: Vec<Event> = serde_json::from_reader(reader)?;
 
-    let mut slide_content_for_event: HashMap<model::EventId, String> = HashMap::new();
-    if let Some(base_path) = args.include_slide_content {
-        info!("Fetching slide content from {:?} ... ", base_path);
-        let mut slide_content_count = 0;
-        for event in events.iter() {
-            let slide_content_path = base_path.join(event.id.to_string()).with_extension("txt");
-            if slide_content_path.exists() {
-                let mut file = File::open(slide_content_path)?;
-                let mut slide_content = String::new();
-                file.read_to_string(&mut slide_content)?;
-                slide_content_for_event.insert(event.id, slide_content);
-                slide_content_count += 1;
-            }
-        }
-        info!("Read {} events with slide content ", slide_content_count);
+    let mut sources = vec![
+        ContentSource::Title,
+        ContentSource::Track,
+        ContentSource::Type,
+        ContentSource::Language,
+        ContentSource::Abstract,
+        ContentSource::Presenters,
+    ];
+    if args.include_slide_content.is_some() {
+        sources.push(ContentSource::Slides);
     }
+    if args.include_video_content.is_some() {
+        sources.push(ContentSource::Video);
+    }
+    let mut years: Vec<u32> = events.iter().map(|e| e.year).collect();
+    years.sort();
+    years.dedup();
+
+    let slide_content_for_event = if let Some(base_path) = args.include_slide_content {
+        load_slide_content(&base_path, &events)?
+    } else {
+        HashMap::new()
+    };
 
     let video_index = if let Some(base_path) = args.include_video_content {
         VideoIndex::from_content_area(&base_path)?
@@ -96,46 +121,157 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         VideoIndex::empty_index()
     };
 
-    let embedding_path = args.model_dir.join("embeddings").with_extension("json");
-
+    let cache_path = args
+        .embedding_cache
+        .clone()
+        .unwrap_or_else(|| args.model_dir.join(EMBEDDING_CACHE_FILE));
+    let mut cache = EmbeddingCache::load(&cache_path)?;
     info!(
-        "Looking up and writing embeddings to {} ... ",
-        embedding_path.to_str().unwrap()
+        "Loaded {} cached embeddings from {}",
+        cache.len(),
+        cache_path.to_str().unwrap()
     );
-    let mut embeddings = vec![];
-    let progress = progress_bar(events.len() as u64);
-    for event in events.into_iter() {
-        let response = get_embedding(
-            &openai_client,
-            args.retries,
-            &event,
+
+    let mut event_inputs = vec![];
+    let mut passages = vec![];
+    for event in &events {
+        event_inputs.push(embedding_input(
+            event,
             &slide_content_for_event,
             &video_index,
-        )
-        .await?;
-        let embedding = OpenAIEmbedding {
-            title: event.title,
-            embedding: OpenAIEmbedding::embedding_from_response(&response)?,
-        };
-        embeddings.push(embedding);
-        progress.inc(1);
+        )?);
+        if let Some(slide_content) = slide_content_for_event.get(&event.id) {
+            passages.extend(slide_passages(event.id, slide_content));
+        }
+        passages.extend(video_index.passages_for_event_id(event.id));
+    }
+
+    // only what isn't in the cache is sent to the provider, and identical texts only once
+    let model = embedding_provider.model();
+    let event_keys: Vec<String> = event_inputs
+        .iter()
+        .map(|input| cache_key(model, input))
+        .collect();
+    let passage_keys: Vec<String> = passages
+        .iter()
+        .map(|passage| cache_key(model, &passage.text))
+        .collect();
+    let mut pending = vec![];
+    let mut seen = HashSet::new();
+    for (key, input) in event_keys.iter().zip(event_inputs).chain(
+        passage_keys
+            .iter()
+            .zip(passages.iter().map(|p| p.text.clone())),
+    ) {
+        // a text which appears more than once is only looked up, and counted, the first time
+        if seen.insert(key.clone()) && cache.get(key).is_none() {
+            pending.push((key.clone(), input));
+        }
+    }
+
+    info!(
+        "Embedding {} texts for {} events and {} passages ... ",
+        pending.len(),
+        events.len(),
+        passages.len()
+    );
+    let embedder = RetryingEmbedder::new(&embedding_provider, Backoff::new(args.retries));
+    let batching = Batching {
+        batch_size: args.batch_size,
+        concurrency: args.concurrency,
+        checkpoint_every: args.checkpoint_every,
+    };
+    let progress = progress_bar(pending.len() as u64);
+    embed_into_cache(
+        &embedder,
+        &mut cache,
+        pending,
+        &batching,
+        &cache_path,
+        &progress,
+    )
+    .await?;
+    progress.finish();
+
+    info!("Embedding cache: {}", cache.stats());
+    info!(
+        "Writing embedding cache to {} ... ",
+        cache_path.to_str().unwrap()
+    );
+    cache.save(&cache_path)?;
+
+    let embedding_path = args.model_dir.join("embeddings").with_extension("json.gz");
+    info!(
+        "Writing embeddings to {} ... ",
+        embedding_path.to_str().unwrap()
+    );
+    let embeddings: Vec<OpenAIEmbedding> = events
+        .iter()
+        .zip(&event_keys)
+        .map(|(event, key)| OpenAIEmbedding {
+            event_id: event.id,
+            embedding: cache.embedding(key).unwrap().clone(),
+        })
+        .collect();
+    write_json_gz(&embedding_path, &embeddings)?;
+
+    let passages_path = args.model_dir.join("passages").with_extension("json.gz");
+    info!(
+        "Writing embeddings for {} passages to {} ... ",
+        passages.len(),
+        passages_path.to_str().unwrap()
+    );
+    let passage_embeddings: Vec<PassageEmbedding> = passages
+        .into_iter()
+        .zip(&passage_keys)
+        .map(|(passage, key)| PassageEmbedding {
+            passage,
+            embedding: cache.embedding(key).unwrap().clone(),
+        })
+        .collect();
+    write_json_gz(&passages_path, &passage_embeddings)?;
+
+    // built from the embeddings which have just been replaced, so remove it rather than leave
+    // it to be served, until build_binary_index is run again
+    let binary_path = args.model_dir.join("embeddings").with_extension("bin");
+    if binary_path.exists() {
+        info!("Removing out of date {}", binary_path.to_str().unwrap());
+        std::fs::remove_file(&binary_path)?;
     }
 
-    let embedding_file = File::create(embedding_path)?;
-    let mut writer = BufWriter::new(embedding_file);
-    serde_json::to_writer(&mut writer, &embeddings)?;
-    writer.flush()?;
+    let mut manifest = Manifest {
+        model: embedding_provider.model().to_string(),
+        dimensions: embeddings.first().map_or(0, |e| e.embedding.len()),
+        years,
+        sources,
+        checksums: Default::default(),
+        built_at: Utc::now(),
+    };
+    manifest.record_checksums(&args.model_dir)?;
+    info!("Writing manifest: {:?}", manifest);
+    manifest.save(&args.model_dir)?;
+
+    Ok(())
+}
 
+/// Write `value` as gzipped JSON, as the model dir keeps embeddings and passages
+fn write_json_gz<T: Serialize>(path: &Path, value: &T) -> Result<(), Box<dyn std::error::Error>> {
+    let mut writer = BufWriter::new(GzEncoder::new(File::create(path)?, Compression::best()));
+    serde_json::to_writer(&mut writer, value)?;
+    writer
+        .into_inner()
+        .map_err(|e| e.into_error())?
+        .finish()?
+        .flush()?;
     Ok(())
 }
 
-async fn get_embedding(
-    client: &Client,
-    max_retries: u32,
+/// The text which is embedded for `event`
+fn embedding_input(
     event: &Event,
     slide_content_for_event: &HashMap<model::EventId, String>,
     video_index: &VideoIndex,
-) -> Result<EmbeddingResponse, Box<dyn std::error::Error>> {
+) -> Result<String, Box<dyn std::error::Error>> {
     let mut preferred_input = String::new();
     use std::fmt::Write;
 
@@ -143,58 +279,28 @@ async fn get_embedding(
     if let Some(slide_content) = slide_content_for_event.get(&event.id) {
         writeln!(preferred_input, "Slides:{}", slide_content)?;
     }
-    if let Some(video_content) = video_index.webvtt_for_event_id(event.id) {
-        let mut block_content: Vec<_> = video_content
-            .blocks
-            .iter()
-            .map(|b| match b {
-                VttBlock::Que(cue) => cue.payload.join("\n"),
-                _ => "".into(),
-            })
-            .collect();
-        block_content.dedup();
-        debug!("[{}] blocks: {:?}", event.id, block_content);
-        writeln!(preferred_input, "Subtitles:{}", block_content.join("\n"))?;
+    if let Some(transcript) = video_index.transcript_for_event_id(event.id) {
+        debug!("[{}] transcript: {:?}", event.id, transcript);
+        writeln!(preferred_input, "Subtitles:{}", transcript)?;
     }
 
-    let trimmed_input = trim_input(&preferred_input);
-
-    let parameters = EmbeddingParameters {
-        model: "text-embedding-ada-002".to_string(),
-        input: EmbeddingInput::String(trimmed_input),
-        encoding_format: None,
-        user: None,
-        dimensions: None,
-    };
-
-    let mut retries = 0;
-    loop {
-        match client.embeddings().create(parameters.clone()).await {
-            Ok(response) => return Ok(response),
-            Err(e) => {
-                retries += 1;
-                if retries > max_retries {
-                    return Err(format!(
-                        "[{}] error: \'{}\', even after {} retries (max: {})",
-                        event.id, e, retries, max_retries
-                    )
-                    .into());
-                } else {
-                    warn!(
-                        "[{}] error: \'{}\', will retry (retry count = {})",
-                        event.id, e, retries
-                    );
-                }
-            }
-        }
-    }
+    Ok(trim_input(&preferred_input))
 }
 
 fn format_basic_input(event: &Event) -> String {
-    let lines: Vec<String> = vec![
+    let mut lines: Vec<String> = vec![
         format!("FOSDEM Conference Event {}", event.year),
         format!("Title: {}", event.title),
         format!("Track: {}", event.track),
+    ];
+    // only when known, so that events imported without them embed as they did before
+    if event.event_type != EventType::Other {
+        lines.push(format!("Type: {}", event.event_type.label()));
+    }
+    if let Some(language) = &event.language {
+        lines.push(format!("Language: {}", language));
+    }
+    lines.extend([
         format!("Abstract: {}", event.r#abstract),
         format!(
             "Presenter: {}",
@@ -205,7 +311,7 @@ fn format_basic_input(event: &Event) -> String {
                 .collect::<Vec<_>>()
                 .join(", ")
         ),
-    ];
+    ]);
     lines.join("\n")
 }
 
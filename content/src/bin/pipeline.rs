@@ -0,0 +1,302 @@
+use std::path::{Path, PathBuf};
+
+use clap::{Parser, ValueEnum};
+use content::embedding_cache::EMBEDDING_CACHE_FILE;
+use content::pipeline::{run, Pipeline, RunManifest, Stage, StageStatus, Step, RUN_MANIFEST_FILE};
+use content::schedule::ScheduleFormat;
+use shared::cli::EmbeddingProviderArgs;
+use shared::manifest::MANIFEST_FILE;
+use tracing::info;
+
+/// Build the model area from the schedules, running only the stages whose inputs have
+/// changed since they last ran, and recording what happened in pipeline_run.json
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// model area, where everything is built
+    #[arg(long)]
+    model_dir: PathBuf,
+
+    /// dir where schedule files, named by year, are located
+    #[arg(long, alias = "pentabarf-dir")]
+    schedule_dir: PathBuf,
+
+    /// format of the schedule files, see `import_events`
+    #[arg(long, value_enum, default_value_t)]
+    format: ScheduleFormat,
+
+    /// years to import
+    #[arg(long, value_delimiter = ' ', required = true)]
+    years: Vec<u32>,
+
+    /// JSON file of year-scoped person ids to global person ids, see `import_events`
+    #[arg(long)]
+    people_overrides: Option<PathBuf>,
+
+    /// fail the import if anything is wrong with the schedules, see `import_events --strict`
+    #[arg(long)]
+    strict_schedules: bool,
+
+    /// fetch slide content into, and include it from, this dir
+    #[arg(long)]
+    slides: Option<PathBuf>,
+
+    /// fetch video transcripts into, and include them from, this dir, downloading videos and
+    /// audio into `--video-dir` and `--audio-dir`
+    #[arg(long, requires_all = ["video_dir", "audio_dir"])]
+    webvtt_dir: Option<PathBuf>,
+
+    /// where to download videos
+    #[arg(long)]
+    video_dir: Option<PathBuf>,
+
+    /// where to store converted audio
+    #[arg(long)]
+    audio_dir: Option<PathBuf>,
+
+    /// write a data post into this dir
+    #[arg(long)]
+    blog_content_dir: Option<PathBuf>,
+
+    /// include this markdown, from `diff_events`, in the data post
+    #[arg(long, requires = "blog_content_dir")]
+    schedule_changes: Option<PathBuf>,
+
+    /// start from this stage, rather than the first
+    #[arg(long)]
+    from: Option<String>,
+
+    /// stop after this stage, rather than the last
+    #[arg(long)]
+    to: Option<String>,
+
+    /// run the selected stages even if nothing they use has changed
+    #[arg(long)]
+    force: bool,
+
+    /// list the stages, in the order they run, and exit
+    #[arg(long)]
+    list: bool,
+
+    #[command(flatten)]
+    embedding: EmbeddingProviderArgs,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    tracing_subscriber::fmt::init();
+
+    let args = Args::parse();
+    info!("args: {:?}", args);
+
+    let pipeline = Pipeline::new(stages(&args))?;
+    if args.list {
+        for stage in pipeline.stages() {
+            println!("{} (after: {})", stage.name, stage.after.join(", "));
+        }
+        return Ok(());
+    }
+    let selected = pipeline.select(args.from.as_deref(), args.to.as_deref())?;
+    info!("Running stages: {}", selected.join(", "));
+
+    let manifest_path = args.model_dir.join(RUN_MANIFEST_FILE);
+    let previous = RunManifest::load(&manifest_path)?;
+    let current_exe = std::env::current_exe()?;
+    let bin_dir = current_exe
+        .parent()
+        .ok_or("no dir for the current binary")?;
+
+    std::fs::create_dir_all(&args.model_dir)?;
+    let manifest = run(&pipeline, &selected, previous.as_ref(), bin_dir, args.force);
+    info!("Writing run manifest to {:?}", manifest_path);
+    manifest.save(&manifest_path)?;
+
+    println!("{:<24} {:<12} {:>9}", "stage", "status", "seconds");
+    for stage in &manifest.stages {
+        if stage.status == StageStatus::NotSelected {
+            continue;
+        }
+        println!(
+            "{:<24} {:<12} {:>9}",
+            stage.name,
+            format!("{:?}", stage.status),
+            stage.seconds.map_or(String::new(), |s| format!("{:.1}", s))
+        );
+    }
+
+    let failures = manifest.failures();
+    if !failures.is_empty() {
+        let names: Vec<_> = failures.iter().map(|f| f.name.as_str()).collect();
+        return Err(format!("failed: {}", names.join(", ")).into());
+    }
+    Ok(())
+}
+
+fn stages(args: &Args) -> Vec<Stage> {
+    let model_dir = &args.model_dir;
+    let model_file = |name: &str| model_dir.join(name);
+    let events = model_file("events.json");
+    let embeddings = model_file("embeddings.json.gz");
+    let passages = model_file("passages.json.gz");
+    let years = args.years.iter().map(|y| y.to_string()).collect::<Vec<_>>();
+
+    let mut import_args = vec![
+        "--schedule-dir".to_string(),
+        path_arg(&args.schedule_dir),
+        "--format".to_string(),
+        args.format
+            .to_possible_value()
+            .map_or(String::new(), |v| v.get_name().to_string()),
+        "--years".to_string(),
+        years.join(" "),
+        "--model-dir".to_string(),
+        path_arg(model_dir),
+    ];
+    let mut import_inputs: Vec<PathBuf> = args
+        .years
+        .iter()
+        .map(|year| args.schedule_dir.join(args.format.file_name(*year)))
+        .collect();
+    if let Some(people_overrides) = &args.people_overrides {
+        import_args.extend(["--people-overrides".to_string(), path_arg(people_overrides)]);
+        import_inputs.push(people_overrides.clone());
+    }
+    if args.strict_schedules {
+        import_args.push("--strict".to_string());
+    }
+    let mut stages = vec![Stage {
+        name: "import_events".to_string(),
+        after: vec![],
+        inputs: import_inputs,
+        outputs: vec![events.clone()],
+        steps: vec![run_binary("import_events", import_args)],
+    }];
+
+    let mut embedding_args = vec!["--model-dir".to_string(), path_arg(model_dir)];
+    let mut embedding_inputs = vec![events.clone()];
+    let mut embedding_after = vec!["import_events".to_string()];
+    if let Some(slides) = &args.slides {
+        stages.push(Stage {
+            name: "fetch_slide_content".to_string(),
+            after: vec!["import_events".to_string()],
+            inputs: vec![events.clone()],
+            outputs: vec![slides.clone()],
+            steps: vec![run_binary(
+                "fetch_slide_content",
+                vec![
+                    "--model-dir".to_string(),
+                    path_arg(model_dir),
+                    "--slides".to_string(),
+                    path_arg(slides),
+                ],
+            )],
+        });
+        embedding_args.extend(["--include-slide-content".to_string(), path_arg(slides)]);
+        embedding_inputs.push(slides.clone());
+        embedding_after.push("fetch_slide_content".to_string());
+    }
+    if let (Some(webvtt_dir), Some(video_dir), Some(audio_dir)) =
+        (&args.webvtt_dir, &args.video_dir, &args.audio_dir)
+    {
+        stages.push(Stage {
+            name: "fetch_video_content".to_string(),
+            after: vec!["import_events".to_string()],
+            inputs: vec![events.clone()],
+            outputs: vec![webvtt_dir.clone()],
+            steps: vec![run_binary(
+                "fetch_video_content_chained",
+                vec![
+                    "--model-dir".to_string(),
+                    path_arg(model_dir),
+                    "--video-dir".to_string(),
+                    path_arg(video_dir),
+                    "--audio-dir".to_string(),
+                    path_arg(audio_dir),
+                    "--webvtt-dir".to_string(),
+                    path_arg(webvtt_dir),
+                    "--hide-progress".to_string(),
+                ],
+            )],
+        });
+        embedding_args.extend(["--include-video-content".to_string(), path_arg(webvtt_dir)]);
+        embedding_inputs.push(webvtt_dir.clone());
+        embedding_after.push("fetch_video_content".to_string());
+    }
+    embedding_args.extend(args.embedding.to_args());
+
+    stages.push(Stage {
+        name: "fetch_openai_embeddings".to_string(),
+        after: embedding_after,
+        inputs: embedding_inputs,
+        outputs: vec![
+            embeddings.clone(),
+            passages.clone(),
+            model_file(MANIFEST_FILE),
+            model_file(EMBEDDING_CACHE_FILE),
+        ],
+        steps: vec![run_binary("fetch_openai_embeddings", embedding_args)],
+    });
+    stages.push(Stage {
+        name: "build_binary_index".to_string(),
+        after: vec!["fetch_openai_embeddings".to_string()],
+        inputs: vec![embeddings.clone()],
+        outputs: vec![model_file("embeddings.bin")],
+        steps: vec![run_binary(
+            "build_binary_index",
+            vec!["--model-dir".to_string(), path_arg(model_dir)],
+        )],
+    });
+    for (name, output) in [
+        ("cluster_topics", "topics.json"),
+        ("project_map", "map.json"),
+    ] {
+        stages.push(Stage {
+            name: name.to_string(),
+            after: vec!["fetch_openai_embeddings".to_string()],
+            inputs: vec![events.clone(), embeddings.clone()],
+            outputs: vec![model_file(output)],
+            steps: vec![run_binary(
+                name,
+                vec!["--model-dir".to_string(), path_arg(model_dir)],
+            )],
+        });
+    }
+
+    if let Some(blog_content_dir) = &args.blog_content_dir {
+        let mut post_args = vec![
+            "--model-dir".to_string(),
+            path_arg(model_dir),
+            "--blog-content-dir".to_string(),
+            path_arg(blog_content_dir),
+        ];
+        let mut post_inputs = vec![events.clone(), embeddings.clone(), passages.clone()];
+        if let Some(schedule_changes) = &args.schedule_changes {
+            post_args.extend(["--schedule-changes".to_string(), path_arg(schedule_changes)]);
+            post_inputs.push(schedule_changes.clone());
+        }
+        post_args.extend(args.embedding.to_args());
+        stages.push(Stage {
+            name: "add_data_post".to_string(),
+            // after everything which records its checksums in the manifest, which is checked
+            after: vec![
+                "build_binary_index".to_string(),
+                "cluster_topics".to_string(),
+                "project_map".to_string(),
+            ],
+            inputs: post_inputs,
+            outputs: vec![blog_content_dir.clone()],
+            steps: vec![run_binary("add_data_post", post_args)],
+        });
+    }
+    stages
+}
+
+fn run_binary(binary: &str, args: Vec<String>) -> Step {
+    Step::Run {
+        binary: binary.to_string(),
+        args,
+    }
+}
+
+fn path_arg(path: &Path) -> String {
+    path.to_string_lossy().to_string()
+}
@@ -0,0 +1,79 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+use content::model_dir::{load_embedded_events, record_checksums};
+use content::projection::{normalise, pca, tsne_sampled};
+use shared::map::{EventMap, MapPoint};
+use shared::model::OpenAIVector;
+use tracing::info;
+
+/// Place every event on a 2D map, where nearby events have similar embeddings, by reducing the
+/// embeddings with PCA and then t-SNE, and write the map to map.json
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// model area, where events.json and embeddings.json.gz are, and where map.json will be
+    /// written
+    #[arg(long)]
+    model_dir: PathBuf,
+
+    /// how many dimensions PCA reduces the embeddings to before t-SNE
+    #[arg(long, default_value = "50")]
+    pca_dimensions: usize,
+
+    /// roughly how many neighbours t-SNE keeps close to each event
+    #[arg(long, default_value = "30.0")]
+    perplexity: f64,
+
+    /// how many iterations of t-SNE to run
+    #[arg(long, default_value = "1000")]
+    iterations: usize,
+
+    /// at most how many events t-SNE places, since it compares every pair of them, so that
+    /// memory and time grow with the square of this; any others are sampled out, spread evenly
+    /// through the events, and placed in the middle of their nearest placed events
+    #[arg(long, default_value = "1500")]
+    max_tsne_events: usize,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    tracing_subscriber::fmt::init();
+
+    let args = Args::parse();
+    info!("args: {:?}", args);
+
+    let embedded = load_embedded_events(&args.model_dir)?;
+    let vectors: Vec<&OpenAIVector> = embedded.iter().map(|(_, embedding)| embedding).collect();
+
+    info!(
+        "Reducing {} embeddings to {} dimensions",
+        vectors.len(),
+        args.pca_dimensions
+    );
+    let reduced = pca(&vectors, args.pca_dimensions);
+
+    info!(
+        "Placing at most {} events with {} iterations of t-SNE",
+        args.max_tsne_events, args.iterations
+    );
+    let placed = normalise(&tsne_sampled(
+        &reduced,
+        args.max_tsne_events,
+        args.perplexity,
+        args.iterations,
+    ));
+
+    let points = embedded
+        .iter()
+        .zip(placed)
+        .map(|((event, _), (x, y))| MapPoint {
+            event_id: event.id,
+            x,
+            y,
+        })
+        .collect();
+    info!("Writing map to {:?}", args.model_dir);
+    EventMap::new(points).save(&args.model_dir)?;
+
+    record_checksums(&args.model_dir)
+}
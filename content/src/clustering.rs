@@ -0,0 +1,191 @@
+use std::collections::{HashMap, HashSet};
+
+use shared::model::OpenAIVector;
+use shared::nearest::XorShift;
+
+/// a fixed seed, so the same vectors always give the same clusters
+const SEED: u64 = 0x9e3779b97f4a7c15;
+
+/// terms shorter than this are mostly noise, like "a", "of" or version numbers
+const MIN_TERM_LENGTH: usize = 3;
+
+/// a term has to be in at least this many events of a cluster to describe it, unless the
+/// cluster has fewer events than this
+const MIN_TERM_EVENTS: usize = 2;
+
+#[derive(Debug)]
+pub struct Clusters {
+    /// the cluster of each vector
+    pub assignments: Vec<usize>,
+    pub centres: Vec<OpenAIVector>,
+}
+
+/// k-means, seeded with k-means++, stopping when no vector changes cluster or after
+/// `max_iterations`. There may be fewer than `k` clusters if there are fewer distinct vectors.
+pub fn k_means(vectors: &[&OpenAIVector], k: usize, max_iterations: usize) -> Clusters {
+    if vectors.is_empty() || k == 0 {
+        return Clusters {
+            assignments: vec![0; vectors.len()],
+            centres: vec![],
+        };
+    }
+    let mut centres = k_means_plus_plus(vectors, k);
+    let mut assignments: Vec<usize> = vec![];
+    for _ in 0..max_iterations {
+        let next: Vec<usize> = vectors.iter().map(|v| nearest(v, &centres)).collect();
+        if next == assignments {
+            break;
+        }
+        assignments = next;
+        // a centre left with no members stays where it is
+        let mut sums = vec![OpenAIVector::zeros(vectors[0].len()); centres.len()];
+        let mut counts = vec![0; centres.len()];
+        for (v, cluster) in vectors.iter().zip(&assignments) {
+            sums[*cluster] += *v;
+            counts[*cluster] += 1;
+        }
+        for (cluster, (sum, count)) in sums.into_iter().zip(counts).enumerate() {
+            if count > 0 {
+                centres[cluster] = sum / count as f64;
+            }
+        }
+    }
+    Clusters {
+        assignments,
+        centres,
+    }
+}
+
+/// Each next centre is picked with a probability proportional to its squared distance from the
+/// closest centre so far, which spreads the starting centres out
+fn k_means_plus_plus(vectors: &[&OpenAIVector], k: usize) -> Vec<OpenAIVector> {
+    let mut rng = XorShift::new(SEED);
+    let first = ((rng.next_f64() * vectors.len() as f64) as usize).min(vectors.len() - 1);
+    let mut centres = vec![vectors[first].clone()];
+    let mut closest: Vec<f64> = vectors
+        .iter()
+        .map(|v| v.metric_distance(&centres[0]).powi(2))
+        .collect();
+    while centres.len() < k {
+        let total: f64 = closest.iter().sum();
+        if total == 0.0 {
+            break;
+        }
+        let mut target = rng.next_f64() * total;
+        let next = closest
+            .iter()
+            .position(|d| {
+                target -= d;
+                target <= 0.0
+            })
+            .unwrap_or(vectors.len() - 1);
+        centres.push(vectors[next].clone());
+        for (v, d) in vectors.iter().zip(closest.iter_mut()) {
+            *d = d.min(v.metric_distance(&centres[centres.len() - 1]).powi(2));
+        }
+    }
+    centres
+}
+
+fn nearest(v: &OpenAIVector, centres: &[OpenAIVector]) -> usize {
+    centres
+        .iter()
+        .map(|centre| v.metric_distance(centre))
+        .enumerate()
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(cluster, _)| cluster)
+        .unwrap()
+}
+
+/// For each cluster, up to `count` terms which tell its documents apart from the rest: those in
+/// many of the cluster's documents, and in many more of them than of documents overall, which
+/// rules out words which are common everywhere. `documents` are lists of terms, e.g. from
+/// `shared::lexical::tokenize`.
+pub fn distinctive_terms(
+    documents: &[Vec<String>],
+    assignments: &[usize],
+    clusters: usize,
+    count: usize,
+) -> Vec<Vec<String>> {
+    let mut document_frequency: HashMap<&str, usize> = HashMap::new();
+    let mut cluster_frequency: Vec<HashMap<&str, usize>> = vec![HashMap::new(); clusters];
+    let mut cluster_sizes = vec![0; clusters];
+    for (terms, cluster) in documents.iter().zip(assignments) {
+        cluster_sizes[*cluster] += 1;
+        let unique: HashSet<&str> = terms
+            .iter()
+            .map(|term| term.as_str())
+            .filter(|term| {
+                term.chars().count() >= MIN_TERM_LENGTH && !term.chars().all(|c| c.is_numeric())
+            })
+            .collect();
+        for term in unique {
+            *document_frequency.entry(term).or_default() += 1;
+            *cluster_frequency[*cluster].entry(term).or_default() += 1;
+        }
+    }
+    let total = documents.len() as f64;
+    cluster_frequency
+        .into_iter()
+        .zip(cluster_sizes)
+        .map(|(frequency, size)| {
+            let min_events = MIN_TERM_EVENTS.min(size);
+            let mut scored: Vec<(&str, f64)> = frequency
+                .into_iter()
+                .filter(|(_, in_cluster)| *in_cluster >= min_events)
+                .map(|(term, in_cluster)| {
+                    let in_cluster = in_cluster as f64 / size as f64;
+                    let overall = document_frequency[term] as f64 / total;
+                    (term, in_cluster * (in_cluster / overall).ln())
+                })
+                .collect();
+            scored.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(b.0)));
+            scored
+                .into_iter()
+                .take(count)
+                .map(|(term, _)| term.to_string())
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_k_means_separates_distinct_groups() {
+        let vectors: Vec<OpenAIVector> =
+            [[0.0, 0.1], [0.1, 0.0], [5.0, 5.1], [5.1, 5.0], [0.0, 0.0]]
+                .iter()
+                .map(|v| OpenAIVector::from_row_slice(v))
+                .collect();
+        let references: Vec<&OpenAIVector> = vectors.iter().collect();
+
+        let clusters = k_means(&references, 2, 10);
+
+        let [a, b, c, d, e] = clusters.assignments[..] else {
+            panic!("expected 5 assignments");
+        };
+        assert_eq!((a, a), (b, e));
+        assert_eq!(c, d);
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn test_distinctive_terms_are_common_in_cluster_and_rare_outside() {
+        let documents: Vec<Vec<String>> = [
+            "the rust compiler",
+            "the rust borrow checker",
+            "the postgres planner",
+            "the postgres vacuum",
+        ]
+        .iter()
+        .map(|text| text.split(' ').map(|term| term.to_string()).collect())
+        .collect();
+
+        let terms = distinctive_terms(&documents, &[0, 0, 1, 1], 2, 1);
+
+        assert_eq!(vec![vec!["rust"], vec!["postgres"]], terms);
+    }
+}
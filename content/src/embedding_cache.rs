@@ -0,0 +1,226 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use shared::model::OpenAIVector;
+
+use crate::temp_file::TempFile;
+
+pub const EMBEDDING_CACHE_FILE: &str = "embedding_cache.json.gz";
+
+/// Identifies an embedding by exactly what went into it: the model, and the text it was given
+pub fn cache_key(model: &str, input: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(model.as_bytes());
+    hasher.update([0]);
+    hasher.update(input.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+#[derive(Deserialize, Serialize)]
+struct CacheEntry {
+    key: String,
+    embedding: OpenAIVector,
+}
+
+/// Embeddings from previous runs, so that only new or changed text needs to be sent to the
+/// embedding provider
+#[derive(Default)]
+pub struct EmbeddingCache {
+    embeddings: HashMap<String, OpenAIVector>,
+    used: HashSet<String>,
+    hits: usize,
+    misses: usize,
+}
+
+/// How a run used the cache. Stale entries weren't asked for, and are dropped when it's saved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: usize,
+    pub misses: usize,
+    pub stale: usize,
+}
+
+impl Display for CacheStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} hits, {} misses, {} stale",
+            self.hits, self.misses, self.stale
+        )
+    }
+}
+
+impl EmbeddingCache {
+    /// Read a cache written by `save`, or start an empty one if there isn't one at `path`
+    pub fn load(path: &Path) -> io::Result<EmbeddingCache> {
+        if !path.exists() {
+            return Ok(EmbeddingCache::default());
+        }
+        let reader = BufReader::new(GzDecoder::new(File::open(path)?));
+        let entries: Vec<CacheEntry> = serde_json::from_reader(reader)?;
+        Ok(EmbeddingCache {
+            embeddings: entries
+                .into_iter()
+                .map(|entry| (entry.key, entry.embedding))
+                .collect(),
+            ..Default::default()
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.embeddings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.embeddings.is_empty()
+    }
+
+    /// The embedding for `key`, counted as a hit if there is one and a miss if not
+    pub fn get(&mut self, key: &str) -> Option<OpenAIVector> {
+        match self.embeddings.get(key) {
+            Some(embedding) => {
+                self.hits += 1;
+                self.used.insert(key.to_string());
+                Some(embedding.clone())
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Whether there is an embedding for `key`, without counting it as a hit or a miss
+    pub fn contains(&self, key: &str) -> bool {
+        self.embeddings.contains_key(key)
+    }
+
+    /// The embedding for `key`, without counting it as a hit or a miss
+    pub fn embedding(&self, key: &str) -> Option<&OpenAIVector> {
+        self.embeddings.get(key)
+    }
+
+    pub fn insert(&mut self, key: String, embedding: OpenAIVector) {
+        self.used.insert(key.clone());
+        self.embeddings.insert(key, embedding);
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits,
+            misses: self.misses,
+            stale: self.embeddings.len() - self.used.len(),
+        }
+    }
+
+    /// Write out the embeddings which were used or added, leaving out stale ones so that the
+    /// cache doesn't keep growing as events change. This is also used as a checkpoint part way
+    /// through a run, so it's written to a temporary file first and then moved into place, and
+    /// is never left half-written.
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let mut keys: Vec<&String> = self.used.iter().collect();
+        keys.sort();
+        let entries: Vec<CacheEntry> = keys
+            .into_iter()
+            .map(|key| CacheEntry {
+                key: key.clone(),
+                embedding: self.embeddings[key].clone(),
+            })
+            .collect();
+        let tmp_path = path.with_extension("tmp");
+        let tmp_file = TempFile::replacing(path.to_path_buf(), tmp_path.clone())?;
+        match write_entries(&tmp_path, &entries) {
+            Ok(()) => tmp_file.commit(),
+            Err(e) => {
+                tmp_file.abort()?;
+                Err(e.into())
+            }
+        }
+    }
+}
+
+fn write_entries(path: &Path, entries: &[CacheEntry]) -> io::Result<()> {
+    let mut writer = BufWriter::new(GzEncoder::new(File::create(path)?, Compression::default()));
+    serde_json::to_writer(&mut writer, entries)?;
+    writer
+        .into_inner()
+        .map_err(|e| e.into_error())?
+        .finish()?
+        .flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn test_key_depends_on_model_and_text() {
+        let key = cache_key("some-model", "Title: Rust");
+
+        assert_eq!(key, cache_key("some-model", "Title: Rust"));
+        assert_ne!(key, cache_key("other-model", "Title: Rust"));
+        assert_ne!(key, cache_key("some-model", "Title: Rust!"));
+        assert_ne!(cache_key("a", "bc"), cache_key("ab", "c"));
+    }
+
+    #[test]
+    fn test_only_entries_used_in_a_run_are_kept() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(EMBEDDING_CACHE_FILE);
+        let mut cache = EmbeddingCache::load(&path).unwrap();
+        assert!(cache.is_empty());
+        cache.insert("unchanged".into(), OpenAIVector::from_vec(vec![1.0, 0.0]));
+        cache.insert("changed".into(), OpenAIVector::from_vec(vec![0.0, 1.0]));
+        cache.save(&path).unwrap();
+
+        let mut cache = EmbeddingCache::load(&path).unwrap();
+        assert_eq!(2, cache.len());
+        assert_eq!(
+            Some(OpenAIVector::from_vec(vec![1.0, 0.0])),
+            cache.get("unchanged")
+        );
+        assert_eq!(None, cache.get("new"));
+        cache.insert("new".into(), OpenAIVector::from_vec(vec![1.0, 1.0]));
+        assert_eq!(
+            CacheStats {
+                hits: 1,
+                misses: 1,
+                stale: 1
+            },
+            cache.stats()
+        );
+        cache.save(&path).unwrap();
+
+        let mut cache = EmbeddingCache::load(&path).unwrap();
+        assert_eq!(2, cache.len());
+        assert_eq!(None, cache.get("changed"));
+        assert!(cache.get("new").is_some());
+    }
+
+    #[test]
+    fn test_contains_is_not_counted() {
+        let mut cache = EmbeddingCache::default();
+        cache.insert("known".into(), OpenAIVector::from_vec(vec![1.0, 0.0]));
+
+        assert!(cache.contains("known"));
+        assert!(!cache.contains("unknown"));
+        assert_eq!(
+            CacheStats {
+                hits: 0,
+                misses: 0,
+                stale: 0
+            },
+            cache.stats()
+        );
+    }
+}
@@ -0,0 +1,256 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use shared::model::{self, EventId, PersonId};
+use tracing::info;
+
+use crate::schedule::{
+    conference_days, event_type, non_empty, parse_date, parse_duration, parse_links, parse_start,
+    parse_url, ImportedSchedule, ScheduleSource,
+};
+use crate::schedule_lint::{Check, Issue};
+
+/// A frab `schedule.json`, as also exported by Pretalx, see
+/// https://c3voc.de/wiki/schedule#schedule_json
+#[derive(Deserialize, Debug)]
+pub struct ScheduleFile {
+    pub schedule: Schedule,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Schedule {
+    pub version: Option<String>,
+    pub conference: Conference,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Conference {
+    pub acronym: String,
+    pub title: String,
+    pub start: Option<String>,
+    pub end: Option<String>,
+    #[serde(rename = "daysCount")]
+    pub days_count: Option<u32>,
+    pub time_zone_name: Option<String>,
+    /// frab leaves these out, and only gives the rooms under each day
+    #[serde(default)]
+    pub rooms: Vec<Room>,
+    #[serde(default)]
+    pub tracks: Vec<Track>,
+    pub days: Vec<Day>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Room {
+    pub name: String,
+    pub slug: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Track {
+    pub name: String,
+    pub slug: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Day {
+    pub index: u32,
+    pub date: String,
+    /// events by room name
+    pub rooms: BTreeMap<String, Vec<Event>>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Event {
+    pub id: u32,
+    pub guid: String,
+    pub start: String,
+    pub duration: String,
+    pub room: String,
+    pub slug: String,
+    pub url: String,
+    pub title: String,
+    pub subtitle: Option<String>,
+    pub track: Option<String>,
+    pub r#type: Option<String>,
+    pub language: Option<String>,
+    pub r#abstract: Option<String>,
+    pub description: Option<String>,
+    pub feedback_url: Option<String>,
+    #[serde(default)]
+    pub persons: Vec<Person>,
+    #[serde(default)]
+    pub links: Vec<Link>,
+    #[serde(default)]
+    pub attachments: Vec<Link>,
+}
+
+/// frab identifies people by a number, and Pretalx by a code, like `ZNAQXK`
+#[derive(Deserialize, Debug)]
+pub struct Person {
+    pub id: Option<u32>,
+    pub code: Option<String>,
+    pub public_name: Option<String>,
+    pub name: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Link {
+    pub url: String,
+    pub title: String,
+    pub r#type: Option<String>,
+}
+
+impl ScheduleSource for ScheduleFile {
+    fn import(self, year: u32) -> Result<ImportedSchedule, Box<dyn std::error::Error>> {
+        let conference = self.schedule.conference;
+        info!(
+            "{} ({}), {} days from {} ({})",
+            conference.title,
+            conference.acronym,
+            conference
+                .days_count
+                .unwrap_or(conference.days.len() as u32),
+            conference.start.as_deref().unwrap_or("?"),
+            conference.time_zone_name.as_deref().unwrap_or("?")
+        );
+        let mut imported = ImportedSchedule {
+            days: conference_days(conference.start.as_deref(), conference.end.as_deref()),
+            // frab doesn't list the rooms, so there's nothing to check them against
+            rooms: (!conference.rooms.is_empty())
+                .then(|| conference.rooms.iter().map(|r| r.name.clone()).collect()),
+            ..Default::default()
+        };
+        let room_slugs: HashMap<String, String> = conference
+            .rooms
+            .into_iter()
+            .filter_map(|r| Some((r.name, non_empty(r.slug?)?)))
+            .collect();
+        let track_slugs: HashMap<String, String> = conference
+            .tracks
+            .into_iter()
+            .filter_map(|t| Some((t.name, non_empty(t.slug?)?)))
+            .collect();
+        for day in conference.days {
+            for event in day.rooms.into_values().flatten() {
+                let id = EventId::new(year, event.id);
+                let issues = &mut imported.issues;
+                match model_event(id, &day.date, event, &room_slugs, &track_slugs, issues) {
+                    Ok(event) => imported.events.push(event),
+                    Err(message) => {
+                        imported
+                            .issues
+                            .push(Issue::new(Check::Unreadable, id, message))
+                    }
+                }
+            }
+        }
+        // rooms are keyed by name, so put events back in the order they happen
+        imported
+            .events
+            .sort_by(|a, b| (a.date, &a.room, a.start).cmp(&(b.date, &b.room, b.start)));
+        Ok(imported)
+    }
+}
+
+/// The event, or why it can't be read, given the slugs of rooms and tracks by name
+fn model_event(
+    id: EventId,
+    date: &str,
+    event: Event,
+    room_slugs: &HashMap<String, String>,
+    track_slugs: &HashMap<String, String>,
+    issues: &mut Vec<Issue>,
+) -> Result<model::Event, String> {
+    let date = parse_date(date)?;
+    let start = parse_start(&event.start)?;
+    let duration = parse_duration(&event.duration)?;
+    let url = parse_url(&event.url)?;
+    let presenters = event
+        .persons
+        .into_iter()
+        .map(|p| presenter(id.year(), p))
+        .collect::<Result<_, _>>()?;
+
+    let track = event.track.unwrap_or_default();
+    let (slides, links): (Vec<Link>, Vec<Link>) = event
+        .attachments
+        .into_iter()
+        .chain(event.links)
+        .partition(|l| l.r#type.as_deref() == Some("slides"));
+    let feedback_url = event.feedback_url.and_then(non_empty);
+    Ok(model::Event {
+        id,
+        year: id.year(),
+        guid: event.guid,
+        date,
+        start,
+        duration,
+        room_slug: room_slugs.get(&event.room).cloned(),
+        room: event.room,
+        track_slug: track_slugs.get(&track).cloned(),
+        track,
+        title: event.title,
+        slug: event.slug,
+        url,
+        r#abstract: event.r#abstract.unwrap_or_default(),
+        slides: parse_links(id, slides.into_iter().map(|l| (l.url, ())), issues)
+            .into_iter()
+            .map(|(url, _)| url)
+            .collect(),
+        presenters,
+        links: parse_links(id, links.into_iter().map(|l| (l.url, l.title)), issues)
+            .into_iter()
+            .map(|(url, name)| model::Link { url, name })
+            .collect(),
+        event_type: event_type(event.id, event.r#type.as_deref().unwrap_or("")),
+        language: event.language.and_then(non_empty),
+        subtitle: event.subtitle.and_then(non_empty),
+        description: event.description.and_then(non_empty),
+        feedback_url: parse_links(id, feedback_url.map(|url| (url, ())), issues)
+            .into_iter()
+            .next()
+            .map(|(url, _)| url),
+    })
+}
+
+fn presenter(year: u32, person: Person) -> Result<model::Person, String> {
+    let id = match (person.id, &person.code) {
+        (Some(id), _) => id,
+        (None, Some(code)) => code_as_id(code),
+        (None, None) => return Err("person with neither an id nor a code".to_string()),
+    };
+    Ok(model::Person {
+        id: PersonId::new(year, id),
+        name: person.public_name.or(person.name).unwrap_or_default(),
+        global_id: None,
+    })
+}
+
+/// Pretalx codes aren't numbers, so they're hashed into one, which is the same for the same code
+fn code_as_id(code: &str) -> u32 {
+    let hash = Sha256::digest(code.as_bytes());
+    u32::from_be_bytes([hash[0], hash[1], hash[2], hash[3]])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pretalx_people_are_identified_by_their_code() {
+        let person = |code: &str| Person {
+            id: None,
+            code: Some(code.to_string()),
+            public_name: None,
+            name: Some("Jane Doe".to_string()),
+        };
+
+        let jane = presenter(2026, person("ZNAQXK")).unwrap();
+
+        assert_eq!("Jane Doe", jane.name);
+        assert_eq!(jane, presenter(2026, person("ZNAQXK")).unwrap());
+        assert_ne!(jane.id, presenter(2026, person("ZNAQXL")).unwrap().id);
+    }
+}
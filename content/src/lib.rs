@@ -1,3 +1,15 @@
+pub mod batch_embedding;
+pub mod clustering;
+pub mod embedding_cache;
+pub mod frab;
+pub mod model_dir;
+pub mod passages;
 pub mod pentabarf;
+pub mod pipeline;
+pub mod projection;
+pub mod schedule;
+pub mod schedule_diff;
+pub mod schedule_lint;
+pub mod slide_content;
 pub mod temp_file;
 pub mod video_index;
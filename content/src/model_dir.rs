@@ -0,0 +1,49 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use flate2::read::GzDecoder;
+use shared::manifest::Manifest;
+use shared::model::{Event, EventId, OpenAIEmbedding, OpenAIVector};
+use tracing::{info, warn};
+
+/// Every event in `model_dir` which has an embedding, with that embedding
+pub fn load_embedded_events(
+    model_dir: &Path,
+) -> Result<Vec<(Event, OpenAIVector)>, Box<dyn std::error::Error>> {
+    let events_path = model_dir.join("events").with_extension("json");
+    let events: Vec<Event> = serde_json::from_reader(BufReader::new(File::open(&events_path)?))?;
+
+    let embeddings_path = model_dir.join("embeddings").with_extension("json.gz");
+    info!("Reading embeddings from {:?}", embeddings_path);
+    let reader = BufReader::new(GzDecoder::new(File::open(&embeddings_path)?));
+    let embeddings: Vec<OpenAIEmbedding> = serde_json::from_reader(reader)?;
+    let mut embedding_for_event: HashMap<EventId, OpenAIVector> = embeddings
+        .into_iter()
+        .map(|e| (e.event_id, e.embedding))
+        .collect();
+
+    let mut embedded = vec![];
+    for event in events {
+        match embedding_for_event.remove(&event.id) {
+            Some(embedding) => embedded.push((event, embedding)),
+            None => warn!("no embedding for {}, so it will be left out", event.id),
+        }
+    }
+    Ok(embedded)
+}
+
+/// Steps which add files to a finished model dir record their checksums in its manifest, if it
+/// has one
+pub fn record_checksums(model_dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    match Manifest::load(model_dir)? {
+        Some(mut manifest) => {
+            info!("Recording checksums in manifest");
+            manifest.record_checksums(model_dir)?;
+            manifest.save(model_dir)?;
+        }
+        None => warn!("No manifest in {:?} to record checksums in", model_dir),
+    }
+    Ok(())
+}
@@ -0,0 +1,142 @@
+use shared::model::{EventId, Passage, PassageLocation};
+use subtp::vtt::{VttBlock, VttTimestamp, WebVtt};
+
+/// Roughly how many words go into a passage: enough to be about something, but short enough that
+/// a passage is about one thing
+pub const WORDS_PER_PASSAGE: usize = 150;
+
+/// Group the cues of a transcript into passages, each starting where its first cue starts
+pub fn transcript_passages(event_id: EventId, webvtt: &WebVtt) -> Vec<Passage> {
+    let mut passages = vec![];
+    let mut current: Vec<String> = vec![];
+    let mut current_words = 0;
+    let mut current_start = 0;
+    let mut previous_payload = None;
+    for block in &webvtt.blocks {
+        let VttBlock::Que(cue) = block else {
+            continue;
+        };
+        let payload = cue.payload.join(" ");
+        // captions often repeat a line across consecutive cues as they scroll
+        if previous_payload.as_ref() == Some(&payload) {
+            continue;
+        }
+        if current.is_empty() {
+            current_start = seconds(&cue.timings.start);
+        }
+        current_words += payload.split_whitespace().count();
+        current.push(payload.clone());
+        previous_payload = Some(payload);
+        if current_words >= WORDS_PER_PASSAGE {
+            passages.push(transcript_passage(event_id, current_start, &current));
+            current.clear();
+            current_words = 0;
+        }
+    }
+    if !current.is_empty() {
+        passages.push(transcript_passage(event_id, current_start, &current));
+    }
+    passages
+}
+
+fn transcript_passage(event_id: EventId, start_seconds: u32, lines: &[String]) -> Passage {
+    Passage {
+        event_id,
+        location: PassageLocation::Transcript { start_seconds },
+        text: lines.join("\n"),
+    }
+}
+
+fn seconds(timestamp: &VttTimestamp) -> u32 {
+    timestamp.hours as u32 * 3600 + timestamp.minutes as u32 * 60 + timestamp.seconds as u32
+}
+
+/// Split slide text into passages. Pages are only known when the text has form feeds between
+/// them, as `pdftotext` and Tika produce for PDFs; otherwise passages are just runs of words.
+pub fn slide_passages(event_id: EventId, slide_content: &str) -> Vec<Passage> {
+    let pages: Vec<&str> = slide_content.split('\x0c').collect();
+    let paged = pages.len() > 1;
+    let mut passages = vec![];
+    for (index, page) in pages.into_iter().enumerate() {
+        let words: Vec<&str> = page.split_whitespace().collect();
+        for chunk in words.chunks(WORDS_PER_PASSAGE) {
+            passages.push(Passage {
+                event_id,
+                location: PassageLocation::Slides {
+                    page: paged.then_some(index as u32 + 1),
+                },
+                text: chunk.join(" "),
+            });
+        }
+    }
+    passages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EVENT_ID: EventId = EventId::new(2025, 1);
+
+    #[test]
+    fn test_transcript_passages_start_at_first_cue() {
+        let mut vtt = String::from("WEBVTT\n\n");
+        for i in 0..WORDS_PER_PASSAGE + 1 {
+            let (start, end) = (i * 2, i * 2 + 1);
+            vtt.push_str(&format!(
+                "00:{:02}:{:02}.000 --> 00:{:02}:{:02}.000\nword{}\n\n",
+                start / 60,
+                start % 60,
+                end / 60,
+                end % 60,
+                i
+            ));
+        }
+        let webvtt = WebVtt::parse(&vtt).unwrap();
+
+        let passages = transcript_passages(EVENT_ID, &webvtt);
+
+        assert_eq!(2, passages.len());
+        assert_eq!(
+            PassageLocation::Transcript { start_seconds: 0 },
+            passages[0].location
+        );
+        assert_eq!(
+            PassageLocation::Transcript {
+                start_seconds: WORDS_PER_PASSAGE as u32 * 2
+            },
+            passages[1].location
+        );
+        assert_eq!(format!("word{}", WORDS_PER_PASSAGE), passages[1].text);
+    }
+
+    #[test]
+    fn test_repeated_cues_are_skipped() {
+        let webvtt = WebVtt::parse(
+            "WEBVTT\n\n00:00:01.000 --> 00:00:02.000\nhello\n\n00:00:02.000 --> 00:00:03.000\nhello\n\n",
+        )
+        .unwrap();
+
+        let passages = transcript_passages(EVENT_ID, &webvtt);
+
+        assert_eq!("hello", passages[0].text);
+    }
+
+    #[test]
+    fn test_slide_passages_know_their_page() {
+        let passages = slide_passages(EVENT_ID, "Title slide\x0cSecond page\x0c\x0cFourth page");
+
+        assert_eq!(3, passages.len());
+        assert_eq!(
+            PassageLocation::Slides { page: Some(4) },
+            passages[2].location
+        );
+    }
+
+    #[test]
+    fn test_unpaged_slides_have_no_page() {
+        let passages = slide_passages(EVENT_ID, "just some text");
+
+        assert_eq!(PassageLocation::Slides { page: None }, passages[0].location);
+    }
+}
@@ -1,12 +1,62 @@
+use std::collections::{BTreeSet, HashMap};
+
+use shared::model::{self, EventId, PersonId};
+use tracing::info;
 use xmlserde_derives::XmlDeserialize;
 
+use crate::schedule::{
+    conference_days, event_type, non_empty, parse_date, parse_duration, parse_links, parse_start,
+    parse_url, ImportedSchedule, ScheduleSource,
+};
+use crate::schedule_lint::{Check, Issue};
+
 #[derive(XmlDeserialize, Default, Debug)]
 #[xmlserde(root = b"schedule")]
 pub struct Schedule {
+    #[xmlserde(name = b"conference", ty = "child")]
+    pub conference: Conference,
+    #[xmlserde(name = b"tracks", ty = "child")]
+    pub tracks: Tracks,
     #[xmlserde(name = b"day", ty = "child")]
     pub days: Vec<Day>,
 }
 
+#[derive(XmlDeserialize, Default, Debug)]
+pub struct Conference {
+    #[xmlserde(name = b"acronym", ty = "child")]
+    pub acronym: Text,
+    #[xmlserde(name = b"title", ty = "child")]
+    pub title: Text,
+    #[xmlserde(name = b"venue", ty = "child")]
+    pub venue: Text,
+    #[xmlserde(name = b"city", ty = "child")]
+    pub city: Text,
+    #[xmlserde(name = b"start", ty = "child")]
+    pub start: Text,
+    #[xmlserde(name = b"end", ty = "child")]
+    pub end: Text,
+    #[xmlserde(name = b"days", ty = "child")]
+    pub days: Text,
+    #[xmlserde(name = b"time_zone_name", ty = "child")]
+    pub time_zone_name: Text,
+}
+
+#[derive(XmlDeserialize, Default, Debug)]
+pub struct Tracks {
+    #[xmlserde(name = b"track", ty = "child")]
+    pub tracks: Vec<Track>,
+}
+
+/// Used both for the list of tracks, and the track of each event. Schedules before 2025 don't
+/// have slugs.
+#[derive(XmlDeserialize, Default, Debug)]
+pub struct Track {
+    #[xmlserde(name = b"slug", ty = "attr")]
+    pub slug: Option<String>,
+    #[xmlserde(ty = "text")]
+    pub name: String,
+}
+
 #[derive(XmlDeserialize, Default, Debug)]
 pub struct Day {
     #[xmlserde(name = b"date", ty = "attr")]
@@ -19,6 +69,8 @@ pub struct Day {
 pub struct Room {
     #[xmlserde(name = b"name", ty = "attr")]
     pub name: String,
+    #[xmlserde(name = b"slug", ty = "attr")]
+    pub slug: Option<String>,
     #[xmlserde(name = b"event", ty = "child")]
     pub events: Vec<crate::pentabarf::Event>,
 }
@@ -34,9 +86,15 @@ pub struct Event {
     #[xmlserde(name = b"duration", ty = "child")]
     pub duration: Text,
     #[xmlserde(name = b"track", ty = "child")]
-    pub track: Text,
+    pub track: Track,
+    #[xmlserde(name = b"type", ty = "child")]
+    pub r#type: Text,
+    #[xmlserde(name = b"language", ty = "child")]
+    pub language: OptionalText,
     #[xmlserde(name = b"title", ty = "child")]
     pub title: Text,
+    #[xmlserde(name = b"subtitle", ty = "child")]
+    pub subtitle: OptionalText,
     #[xmlserde(name = b"persons", ty = "child")]
     pub persons: Persons,
     #[xmlserde(name = b"slug", ty = "child")]
@@ -44,7 +102,11 @@ pub struct Event {
     #[xmlserde(name = b"url", ty = "child")]
     pub url: Text,
     #[xmlserde(name = b"abstract", ty = "child")]
-    pub r#abstract: Text,
+    pub r#abstract: OptionalText,
+    #[xmlserde(name = b"description", ty = "child")]
+    pub description: OptionalText,
+    #[xmlserde(name = b"feedback_url", ty = "child")]
+    pub feedback_url: OptionalText,
     #[xmlserde(name = b"attachments", ty = "child")]
     pub attachments: Attachments,
     #[xmlserde(name = b"links", ty = "child")]
@@ -57,6 +119,13 @@ pub struct Text {
     pub value: String,
 }
 
+/// For elements which are often empty, like `<subtitle></subtitle>`
+#[derive(XmlDeserialize, Default, Debug)]
+pub struct OptionalText {
+    #[xmlserde(ty = "text")]
+    pub value: Option<String>,
+}
+
 #[derive(XmlDeserialize, Default, Debug)]
 pub struct Persons {
     #[xmlserde(name = b"person", ty = "child")]
@@ -98,3 +167,204 @@ pub struct Link {
     #[xmlserde(ty = "text")]
     pub name: String,
 }
+
+impl ScheduleSource for Schedule {
+    fn import(self, year: u32) -> Result<ImportedSchedule, Box<dyn std::error::Error>> {
+        let conference = &self.conference;
+        info!(
+            "{} at {}, {}, {} days from {} ({})",
+            conference.title.value,
+            conference.venue.value,
+            conference.city.value,
+            conference.days.value,
+            conference.start.value,
+            conference.time_zone_name.value
+        );
+        let mut imported = ImportedSchedule {
+            days: conference_days(Some(&conference.start.value), Some(&conference.end.value)),
+            ..Default::default()
+        };
+        // events in older schedules don't give their track's slug, so fall back to the list
+        let track_slugs: HashMap<String, String> = self
+            .tracks
+            .tracks
+            .into_iter()
+            .filter_map(|t| Some((t.name, non_empty(t.slug?)?)))
+            .collect();
+        // there is no list of rooms, so a room counts as listed if it has a slug
+        let mut rooms = BTreeSet::new();
+        for day in self.days {
+            for room in day.rooms {
+                let room_slug = room.slug.and_then(non_empty);
+                if room_slug.is_some() {
+                    rooms.insert(room.name.clone());
+                }
+                for event in room.events {
+                    let id = EventId::new(year, event.id);
+                    let place = Place {
+                        date: &day.date,
+                        room: &room.name,
+                        room_slug: &room_slug,
+                    };
+                    match model_event(id, place, event, &track_slugs, &mut imported.issues) {
+                        Ok(event) => imported.events.push(event),
+                        Err(message) => {
+                            imported
+                                .issues
+                                .push(Issue::new(Check::Unreadable, id, message))
+                        }
+                    }
+                }
+            }
+        }
+        imported.rooms = Some(rooms);
+        Ok(imported)
+    }
+}
+
+/// Where an event is, from the day and room it's listed under
+struct Place<'a> {
+    date: &'a str,
+    room: &'a str,
+    room_slug: &'a Option<String>,
+}
+
+/// The event, or why it can't be read
+fn model_event(
+    id: EventId,
+    place: Place,
+    event: Event,
+    track_slugs: &HashMap<String, String>,
+    issues: &mut Vec<Issue>,
+) -> Result<model::Event, String> {
+    let date = parse_date(place.date)?;
+    let start = parse_start(&event.start.value)?;
+    let duration = parse_duration(&event.duration.value)?;
+    let url = parse_url(&event.url.value)?;
+
+    let track_slug = event
+        .track
+        .slug
+        .and_then(non_empty)
+        .or_else(|| track_slugs.get(&event.track.name).cloned());
+    let slides = event
+        .attachments
+        .attachments
+        .into_iter()
+        .filter(|a| a.r#type == "slides")
+        .map(|a| (a.href, ()));
+    let links = event.links.links.into_iter().map(|l| (l.href, l.name));
+    let feedback_url = event.feedback_url.value.and_then(non_empty);
+    Ok(model::Event {
+        id,
+        year: id.year(),
+        guid: event.guid,
+        date,
+        start,
+        duration,
+        room: place.room.to_string(),
+        track: event.track.name,
+        title: event.title.value,
+        slug: event.slug.value,
+        url,
+        r#abstract: event.r#abstract.value.unwrap_or_default(),
+        slides: parse_links(id, slides, issues)
+            .into_iter()
+            .map(|(url, _)| url)
+            .collect(),
+        presenters: presenters(id.year(), event.persons),
+        links: parse_links(id, links, issues)
+            .into_iter()
+            .map(|(url, name)| model::Link { url, name })
+            .collect(),
+        event_type: event_type(event.id, &event.r#type.value),
+        language: event.language.value.and_then(non_empty),
+        subtitle: event.subtitle.value.and_then(non_empty),
+        description: event.description.value.and_then(non_empty),
+        feedback_url: parse_links(id, feedback_url.map(|url| (url, ())), issues)
+            .into_iter()
+            .next()
+            .map(|(url, _)| url),
+        room_slug: place.room_slug.clone(),
+        track_slug,
+    })
+}
+
+fn presenters(year: u32, persons: Persons) -> Vec<model::Person> {
+    persons
+        .persons
+        .into_iter()
+        .map(|p| model::Person {
+            id: PersonId::new(year, p.id),
+            name: p.name,
+            global_id: None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use xmlserde::xml_deserialize_from_str;
+
+    use super::*;
+
+    #[test]
+    fn test_reads_conference_tracks_and_event_details() {
+        let xml = r#"<schedule>
+  <conference>
+    <acronym>fosdem-2026</acronym>
+    <title>FOSDEM 2026</title>
+    <venue>ULB</venue>
+    <city>Brussels</city>
+    <start>2026-01-31</start>
+    <end>2026-02-01</end>
+    <days>2</days>
+    <time_zone_name>Europe/Brussels</time_zone_name>
+  </conference>
+  <tracks>
+    <track online_qa="false" slug="main">Main Track</track>
+    <track online_qa="false">Lightning Talks</track>
+  </tracks>
+  <day index="1" date="2026-01-31">
+    <room name="Janson" slug="janson">
+      <event guid="guid" id="8376">
+        <start>09:30</start>
+        <duration>00:20</duration>
+        <slug>SFKNTZ-welcome_to_fosdem_2026</slug>
+        <url>https://fosdem.org/2026/schedule/event/SFKNTZ-welcome_to_fosdem_2026/</url>
+        <title>Welcome to FOSDEM 2026</title>
+        <subtitle></subtitle>
+        <track slug="main">Main Track</track>
+        <type>maintrack</type>
+        <language>en</language>
+        <abstract>Welcome</abstract>
+        <description></description>
+        <feedback_url>https://pretalx.fosdem.org/fosdem-2026/talk/SFKNTZ/feedback/</feedback_url>
+        <persons>
+          <person id="2">FOSDEM Staff</person>
+        </persons>
+        <attachments></attachments>
+        <links></links>
+      </event>
+    </room>
+  </day>
+</schedule>"#;
+
+        let schedule: Schedule = xml_deserialize_from_str(xml).unwrap();
+
+        assert_eq!("Europe/Brussels", schedule.conference.time_zone_name.value);
+        assert_eq!("2", schedule.conference.days.value);
+        let slugs: Vec<_> = schedule.tracks.tracks.iter().map(|t| &t.slug).collect();
+        assert_eq!(vec![&Some("main".to_string()), &None], slugs);
+        let room = &schedule.days[0].rooms[0];
+        assert_eq!(Some("janson".to_string()), room.slug);
+        let event = &room.events[0];
+        assert_eq!(Some("main".to_string()), event.track.slug);
+        assert_eq!("Main Track", event.track.name);
+        assert_eq!("maintrack", event.r#type.value);
+        assert_eq!(Some("en".to_string()), event.language.value);
+        assert_eq!(None, event.subtitle.value);
+        assert_eq!(None, event.description.value);
+        assert!(event.feedback_url.value.is_some());
+    }
+}
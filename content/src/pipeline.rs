@@ -0,0 +1,556 @@
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Instant;
+
+use chrono::{DateTime, Utc};
+use flate2::read::GzDecoder;
+use serde::de::IgnoredAny;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::{info, warn};
+
+/// Written to the model dir after every run, and read at the start of the next to see what
+/// needs running again
+pub const RUN_MANIFEST_FILE: &str = "pipeline_run.json";
+
+/// Something a stage does
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step {
+    /// run one of the other binaries, which are expected to be alongside `pipeline`
+    Run { binary: String, args: Vec<String> },
+}
+
+impl Display for Step {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Step::Run { binary, args } => write!(f, "{} {}", binary, args.join(" ")),
+        }
+    }
+}
+
+/// Part of the pipeline, which reads `inputs` and writes `outputs`, once the stages it comes
+/// `after` have run
+#[derive(Debug, Clone)]
+pub struct Stage {
+    pub name: String,
+    pub after: Vec<String>,
+    /// files or dirs
+    pub inputs: Vec<PathBuf>,
+    /// files or dirs
+    pub outputs: Vec<PathBuf>,
+    pub steps: Vec<Step>,
+}
+
+/// Stages ordered so that each comes after the stages it depends on
+#[derive(Debug)]
+pub struct Pipeline {
+    stages: Vec<Stage>,
+}
+
+impl Pipeline {
+    /// Orders `stages` by their dependencies, but otherwise keeps them in the order given
+    pub fn new(stages: Vec<Stage>) -> Result<Pipeline, Box<dyn std::error::Error>> {
+        let mut names = HashSet::new();
+        for stage in &stages {
+            if !names.insert(stage.name.as_str()) {
+                return Err(format!("there is more than one stage called {}", stage.name).into());
+            }
+        }
+        for stage in &stages {
+            if let Some(unknown) = stage.after.iter().find(|d| !names.contains(d.as_str())) {
+                return Err(format!(
+                    "{} comes after {}, which isn't a stage",
+                    stage.name, unknown
+                )
+                .into());
+            }
+        }
+
+        let mut remaining = stages;
+        let mut ordered: Vec<Stage> = vec![];
+        while !remaining.is_empty() {
+            let ready = remaining.iter().position(|stage| {
+                stage
+                    .after
+                    .iter()
+                    .all(|d| ordered.iter().any(|done| &done.name == d))
+            });
+            match ready {
+                Some(ready) => ordered.push(remaining.remove(ready)),
+                None => {
+                    let names: Vec<_> = remaining.iter().map(|s| s.name.as_str()).collect();
+                    return Err(format!("stages depend on each other: {}", names.join(", ")).into());
+                }
+            }
+        }
+        Ok(Pipeline { stages: ordered })
+    }
+
+    pub fn stages(&self) -> &[Stage] {
+        &self.stages
+    }
+
+    /// The names of the stages between `from` and `to`: those which `from` leads to, and which
+    /// lead to `to`, including both. Either end is open if not given.
+    pub fn select(
+        &self,
+        from: Option<&str>,
+        to: Option<&str>,
+    ) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        for name in from.iter().chain(to.iter()) {
+            if !self.stages.iter().any(|s| s.name == *name) {
+                return Err(format!("there is no stage called {}", name).into());
+            }
+        }
+        // as stages are ordered, everything a stage leads to comes after it, and vice versa
+        let mut led_to: HashSet<&str> = HashSet::new();
+        for stage in &self.stages {
+            if from.is_none_or(|from| from == stage.name)
+                || stage.after.iter().any(|d| led_to.contains(d.as_str()))
+            {
+                led_to.insert(&stage.name);
+            }
+        }
+        let mut leading: HashSet<&str> = HashSet::new();
+        for stage in self.stages.iter().rev() {
+            if to.is_none_or(|to| to == stage.name) || leading.contains(stage.name.as_str()) {
+                leading.insert(&stage.name);
+                leading.extend(stage.after.iter().map(|d| d.as_str()));
+            }
+        }
+        Ok(self
+            .stages
+            .iter()
+            .filter(|s| led_to.contains(s.name.as_str()) && leading.contains(s.name.as_str()))
+            .map(|s| s.name.clone())
+            .collect())
+    }
+}
+
+/// sha256 of what a stage does and the contents of its inputs, so that it only needs to run again
+/// when one of them changes
+pub fn fingerprint(stage: &Stage) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    for step in &stage.steps {
+        hasher.update(step.to_string().as_bytes());
+        hasher.update([0]);
+    }
+    for input in &stage.inputs {
+        hasher.update(input.to_string_lossy().as_bytes());
+        hash_path(&mut hasher, input)?;
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn hash_path(hasher: &mut Sha256, path: &Path) -> io::Result<()> {
+    if path.is_dir() {
+        let mut entries = fs::read_dir(path)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<io::Result<Vec<_>>>()?;
+        entries.sort();
+        hasher.update(b"dir");
+        for entry in entries {
+            hasher.update(entry.file_name().unwrap_or_default().as_encoded_bytes());
+            hash_path(hasher, &entry)?;
+        }
+    } else if path.is_file() {
+        hasher.update(b"file");
+        io::copy(&mut BufReader::new(File::open(path)?), hasher)?;
+    } else {
+        hasher.update(b"missing");
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StageStatus {
+    Ran,
+    /// its inputs hadn't changed since it last ran
+    Skipped,
+    Failed,
+    /// a stage it comes after failed, or was itself blocked
+    Blocked,
+    /// outside of `--from` and `--to`
+    NotSelected,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutputSummary {
+    pub path: PathBuf,
+    pub bytes: u64,
+    /// entries in a JSON array, or files in a dir
+    pub count: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StageRun {
+    pub name: String,
+    pub status: StageStatus,
+    /// of the stage when it last succeeded, which may have been in an earlier run
+    pub fingerprint: Option<String>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub seconds: Option<f64>,
+    #[serde(default)]
+    pub outputs: Vec<OutputSummary>,
+    pub error: Option<String>,
+}
+
+/// What happened to each stage in a run of the pipeline
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunManifest {
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub stages: Vec<StageRun>,
+}
+
+impl RunManifest {
+    pub fn load(path: &Path) -> io::Result<Option<RunManifest>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let manifest = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+        Ok(Some(manifest))
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.flush()
+    }
+
+    pub fn stage(&self, name: &str) -> Option<&StageRun> {
+        self.stages.iter().find(|s| s.name == name)
+    }
+
+    pub fn failures(&self) -> Vec<&StageRun> {
+        self.stages
+            .iter()
+            .filter(|s| s.status == StageStatus::Failed)
+            .collect()
+    }
+}
+
+/// Run each `selected` stage of `pipeline` in order, with binaries from `bin_dir`. A stage is
+/// skipped if it has the same fingerprint as when it last succeeded, according to `previous`, and
+/// its outputs are still there, unless `force`. Stages after one which failed aren't run. Every
+/// stage is recorded, even those not selected, so that what's known about them is kept for the
+/// next run.
+pub fn run(
+    pipeline: &Pipeline,
+    selected: &[String],
+    previous: Option<&RunManifest>,
+    bin_dir: &Path,
+    force: bool,
+) -> RunManifest {
+    let started_at = Utc::now();
+    let mut unsuccessful: HashSet<&str> = HashSet::new();
+    let mut runs = vec![];
+    for stage in pipeline.stages() {
+        let last_fingerprint = previous
+            .and_then(|previous| previous.stage(&stage.name))
+            .and_then(|last| last.fingerprint.clone());
+        let mut stage_run = StageRun {
+            name: stage.name.clone(),
+            status: StageStatus::NotSelected,
+            fingerprint: last_fingerprint.clone(),
+            started_at: None,
+            seconds: None,
+            outputs: vec![],
+            error: None,
+        };
+        if !selected.contains(&stage.name) {
+            runs.push(stage_run);
+            continue;
+        }
+        if let Some(blocker) = stage
+            .after
+            .iter()
+            .find(|d| unsuccessful.contains(d.as_str()))
+        {
+            warn!(
+                "[{}] not running, as {} didn't succeed",
+                stage.name, blocker
+            );
+            stage_run.status = StageStatus::Blocked;
+            stage_run.error = Some(format!("{} didn't succeed", blocker));
+            unsuccessful.insert(&stage.name);
+            runs.push(stage_run);
+            continue;
+        }
+
+        let fingerprint = match fingerprint(stage) {
+            Ok(fingerprint) => fingerprint,
+            Err(e) => {
+                warn!("[{}] couldn't read its inputs: {}", stage.name, e);
+                stage_run.status = StageStatus::Failed;
+                stage_run.error = Some(format!("couldn't read inputs: {}", e));
+                unsuccessful.insert(&stage.name);
+                runs.push(stage_run);
+                continue;
+            }
+        };
+        if !force
+            && last_fingerprint.as_ref() == Some(&fingerprint)
+            && stage.outputs.iter().all(|output| output.exists())
+        {
+            info!("[{}] skipping, as nothing it uses has changed", stage.name);
+            stage_run.status = StageStatus::Skipped;
+            stage_run.outputs = summarise_outputs(stage);
+            runs.push(stage_run);
+            continue;
+        }
+
+        info!("[{}] running", stage.name);
+        stage_run.started_at = Some(Utc::now());
+        let started = Instant::now();
+        let result = stage
+            .steps
+            .iter()
+            .try_for_each(|step| run_step(step, bin_dir));
+        stage_run.seconds = Some(started.elapsed().as_secs_f64());
+        match result {
+            Ok(()) => {
+                info!(
+                    "[{}] finished in {:.1}s",
+                    stage.name,
+                    started.elapsed().as_secs_f64()
+                );
+                stage_run.status = StageStatus::Ran;
+                stage_run.fingerprint = Some(fingerprint);
+                stage_run.outputs = summarise_outputs(stage);
+            }
+            Err(e) => {
+                warn!("[{}] failed: {}", stage.name, e);
+                stage_run.status = StageStatus::Failed;
+                // so that it's run again next time, even if nothing has changed
+                stage_run.fingerprint = None;
+                stage_run.error = Some(e.to_string());
+                unsuccessful.insert(&stage.name);
+            }
+        }
+        runs.push(stage_run);
+    }
+    RunManifest {
+        started_at,
+        finished_at: Utc::now(),
+        stages: runs,
+    }
+}
+
+fn run_step(step: &Step, bin_dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    info!("{}", step);
+    match step {
+        Step::Run { binary, args } => {
+            let program = bin_dir.join(binary);
+            let status = Command::new(&program).args(args).status().map_err(|e| {
+                format!(
+                    "couldn't run {:?}, which may need building first: {}",
+                    program, e
+                )
+            })?;
+            if !status.success() {
+                return Err(format!("{} exited with {}", binary, status).into());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn summarise_outputs(stage: &Stage) -> Vec<OutputSummary> {
+    let mut summaries = vec![];
+    for output in &stage.outputs {
+        match summarise_output(output) {
+            Ok(summary) => summaries.push(summary),
+            Err(e) => warn!("[{}] couldn't summarise {:?}: {}", stage.name, output, e),
+        }
+    }
+    summaries
+}
+
+fn summarise_output(path: &Path) -> io::Result<OutputSummary> {
+    let (bytes, count) = if path.is_dir() {
+        let (bytes, files) = dir_size(path)?;
+        (bytes, Some(files))
+    } else {
+        let bytes = fs::metadata(path)?.len();
+        let name = path.to_string_lossy();
+        let count = if name.ends_with(".json.gz") {
+            json_array_len(GzDecoder::new(File::open(path)?))
+        } else if name.ends_with(".json") {
+            json_array_len(File::open(path)?)
+        } else {
+            None
+        };
+        (bytes, count)
+    };
+    Ok(OutputSummary {
+        path: path.to_path_buf(),
+        bytes,
+        count,
+    })
+}
+
+fn json_array_len<R: Read>(reader: R) -> Option<usize> {
+    serde_json::from_reader::<_, Vec<IgnoredAny>>(BufReader::new(reader))
+        .ok()
+        .map(|entries| entries.len())
+}
+
+/// total bytes, and number of files
+fn dir_size(path: &Path) -> io::Result<(u64, usize)> {
+    let mut bytes = 0;
+    let mut files = 0;
+    for entry in fs::read_dir(path)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            let (dir_bytes, dir_files) = dir_size(&path)?;
+            bytes += dir_bytes;
+            files += dir_files;
+        } else {
+            bytes += fs::metadata(&path)?.len();
+            files += 1;
+        }
+    }
+    Ok((bytes, files))
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn stage(name: &str, after: &[&str]) -> Stage {
+        Stage {
+            name: name.to_string(),
+            after: after.iter().map(|d| d.to_string()).collect(),
+            inputs: vec![],
+            outputs: vec![],
+            steps: vec![],
+        }
+    }
+
+    fn names(pipeline: &Pipeline) -> Vec<&str> {
+        pipeline.stages().iter().map(|s| s.name.as_str()).collect()
+    }
+
+    /// copies `input` to `output` with `cp`, which is in /bin on both Linux and macOS
+    fn copy_stage(name: &str, after: &[&str], input: &Path, output: &Path) -> Stage {
+        Stage {
+            inputs: vec![input.to_path_buf()],
+            outputs: vec![output.to_path_buf()],
+            steps: vec![Step::Run {
+                binary: "cp".to_string(),
+                args: vec![
+                    input.to_string_lossy().to_string(),
+                    output.to_string_lossy().to_string(),
+                ],
+            }],
+            ..stage(name, after)
+        }
+    }
+
+    fn statuses(manifest: &RunManifest) -> Vec<StageStatus> {
+        manifest.stages.iter().map(|s| s.status).collect()
+    }
+
+    #[test]
+    fn test_stages_come_after_what_they_depend_on() {
+        let pipeline = Pipeline::new(vec![
+            stage("embed", &["import"]),
+            stage("import", &[]),
+            stage("map", &["embed"]),
+            stage("topics", &["embed"]),
+        ])
+        .unwrap();
+
+        assert_eq!(vec!["import", "embed", "map", "topics"], names(&pipeline));
+        assert!(Pipeline::new(vec![stage("a", &["b"]), stage("b", &["a"])]).is_err());
+        assert!(Pipeline::new(vec![stage("a", &["missing"])]).is_err());
+        assert!(Pipeline::new(vec![stage("a", &[]), stage("a", &[])]).is_err());
+    }
+
+    #[test]
+    fn test_select_from_and_to() {
+        let pipeline = Pipeline::new(vec![
+            stage("import", &[]),
+            stage("slides", &["import"]),
+            stage("embed", &["import", "slides"]),
+            stage("map", &["embed"]),
+            stage("post", &["embed"]),
+        ])
+        .unwrap();
+
+        assert_eq!(5, pipeline.select(None, None).unwrap().len());
+        assert_eq!(
+            vec!["embed", "map", "post"],
+            pipeline.select(Some("embed"), None).unwrap()
+        );
+        assert_eq!(
+            vec!["import", "slides", "embed"],
+            pipeline.select(None, Some("embed")).unwrap()
+        );
+        assert_eq!(
+            vec!["slides", "embed", "map"],
+            pipeline.select(Some("slides"), Some("map")).unwrap()
+        );
+        assert!(pipeline.select(Some("missing"), None).is_err());
+    }
+
+    #[test]
+    fn test_only_stages_whose_inputs_changed_are_run_again() {
+        let dir = TempDir::new().unwrap();
+        let (events, copied, copied_again) = (
+            dir.path().join("events.json"),
+            dir.path().join("copied.json"),
+            dir.path().join("copied_again.json"),
+        );
+        fs::write(&events, "[1, 2, 3]").unwrap();
+        let pipeline = Pipeline::new(vec![
+            copy_stage("copy", &[], &events, &copied),
+            copy_stage("copy_again", &["copy"], &copied, &copied_again),
+        ])
+        .unwrap();
+        let all = pipeline.select(None, None).unwrap();
+        let bin_dir = Path::new("/bin");
+
+        let first = run(&pipeline, &all, None, bin_dir, false);
+        assert_eq!(vec![StageStatus::Ran, StageStatus::Ran], statuses(&first));
+        assert_eq!(Some(3), first.stages[1].outputs[0].count);
+
+        let second = run(&pipeline, &all, Some(&first), bin_dir, false);
+        assert_eq!(
+            vec![StageStatus::Skipped, StageStatus::Skipped],
+            statuses(&second)
+        );
+
+        fs::write(&events, "[1, 2]").unwrap();
+        let only_first = pipeline.select(None, Some("copy")).unwrap();
+        let third = run(&pipeline, &only_first, Some(&second), bin_dir, false);
+        assert_eq!(
+            vec![StageStatus::Ran, StageStatus::NotSelected],
+            statuses(&third)
+        );
+
+        let fourth = run(&pipeline, &all, Some(&third), bin_dir, false);
+        assert_eq!(
+            vec![StageStatus::Skipped, StageStatus::Ran],
+            statuses(&fourth)
+        );
+        assert_eq!(Some(2), fourth.stages[1].outputs[0].count);
+
+        fs::remove_file(&events).unwrap();
+        let fifth = run(&pipeline, &all, Some(&fourth), bin_dir, false);
+        assert_eq!(
+            vec![StageStatus::Failed, StageStatus::Blocked],
+            statuses(&fifth)
+        );
+        assert_eq!(1, fifth.failures().len());
+        assert_eq!(None, fifth.stages[0].fingerprint);
+    }
+}
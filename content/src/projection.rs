@@ -0,0 +1,322 @@
+use nalgebra::DMatrix;
+use shared::model::OpenAIVector;
+
+/// for the first iterations, attractions are exaggerated so that clusters form, and are then
+/// free to move apart
+const EARLY_EXAGGERATION: f64 = 12.0;
+const EXAGGERATION_ITERATIONS: usize = 250;
+
+/// the learning rate is scaled to the number of points, but no lower than this
+const MIN_LEARNING_RATE: f64 = 50.0;
+const MIN_GAIN: f64 = 0.01;
+
+/// how closely the perplexity of each point's neighbourhood has to match the one asked for
+const PERPLEXITY_TOLERANCE: f64 = 1e-5;
+const PERPLEXITY_SEARCH_STEPS: usize = 50;
+
+/// how many of the nearest sampled points a point left out of t-SNE is placed amongst
+const SAMPLED_NEIGHBOURS: usize = 3;
+
+/// Project `vectors` onto their first `components` principal components, which keeps most of
+/// how they differ in far fewer dimensions
+pub fn pca(vectors: &[&OpenAIVector], components: usize) -> Vec<OpenAIVector> {
+    let Some(first) = vectors.first() else {
+        return vec![];
+    };
+    let (rows, dimensions) = (vectors.len(), first.len());
+    let components = components.min(dimensions);
+    let mean = vectors
+        .iter()
+        .fold(OpenAIVector::zeros(dimensions), |sum, v| sum + *v)
+        / rows as f64;
+    let centred = DMatrix::from_fn(rows, dimensions, |r, c| vectors[r][c] - mean[c]);
+    let covariance = centred.transpose() * &centred / (rows.max(2) - 1) as f64;
+    let eigen = covariance.symmetric_eigen();
+    let mut order: Vec<usize> = (0..dimensions).collect();
+    order.sort_by(|a, b| eigen.eigenvalues[*b].total_cmp(&eigen.eigenvalues[*a]));
+    let basis = DMatrix::from_fn(dimensions, components, |r, c| {
+        eigen.eigenvectors[(r, order[c])]
+    });
+    let projected = centred * basis;
+    (0..rows).map(|r| projected.row(r).transpose()).collect()
+}
+
+/// t-SNE: place each of `points` in 2D so that points which are near each other stay near each
+/// other, with `perplexity` being roughly how many neighbours each point should keep close. This
+/// is the exact form, which compares every pair of points, so is only practical for thousands of
+/// points. Starts from the first two coordinates of each point, which after `pca` makes it
+/// repeatable.
+pub fn tsne(points: &[OpenAIVector], perplexity: f64, iterations: usize) -> Vec<(f64, f64)> {
+    let n = points.len();
+    let mut distances = vec![0.0; n * n];
+    for i in 0..n {
+        for j in (i + 1)..n {
+            let d = (&points[i] - &points[j]).norm_squared();
+            distances[i * n + j] = d;
+            distances[j * n + i] = d;
+        }
+    }
+    let p = joint_probabilities(&distances, n, perplexity);
+
+    let coordinate = |point: &OpenAIVector, i: usize| point.get(i).copied().unwrap_or(0.0);
+    let spread = points
+        .iter()
+        .map(|point| coordinate(point, 0).abs())
+        .fold(0.0, f64::max);
+    let scale = if spread > 0.0 { 1e-4 / spread } else { 1.0 };
+    let mut y: Vec<[f64; 2]> = points
+        .iter()
+        .map(|point| [coordinate(point, 0) * scale, coordinate(point, 1) * scale])
+        .collect();
+    let learning_rate = (n as f64 / EARLY_EXAGGERATION / 4.0).max(MIN_LEARNING_RATE);
+    let mut gains = vec![[1.0_f64; 2]; n];
+    let mut velocity = vec![[0.0; 2]; n];
+    let mut q = vec![0.0; n * n];
+    for iteration in 0..iterations {
+        let (exaggeration, momentum) = if iteration < EXAGGERATION_ITERATIONS {
+            (EARLY_EXAGGERATION, 0.5)
+        } else {
+            (1.0, 0.8)
+        };
+        let mut total = 0.0;
+        for i in 0..n {
+            for j in (i + 1)..n {
+                let dx = y[i][0] - y[j][0];
+                let dy = y[i][1] - y[j][1];
+                let similarity = 1.0 / (1.0 + dx * dx + dy * dy);
+                q[i * n + j] = similarity;
+                q[j * n + i] = similarity;
+                total += 2.0 * similarity;
+            }
+        }
+        let gradients: Vec<[f64; 2]> = (0..n)
+            .map(|i| {
+                let mut gradient = [0.0; 2];
+                for j in (0..n).filter(|j| *j != i) {
+                    let similarity = q[i * n + j];
+                    let force = (exaggeration * p[i * n + j] - similarity / total) * similarity;
+                    gradient[0] += 4.0 * force * (y[i][0] - y[j][0]);
+                    gradient[1] += 4.0 * force * (y[i][1] - y[j][1]);
+                }
+                gradient
+            })
+            .collect();
+        for i in 0..n {
+            for d in 0..2 {
+                // speed up while the gradient keeps pointing the same way, and slow down when it
+                // changes direction
+                gains[i][d] = if (gradients[i][d] > 0.0) != (velocity[i][d] > 0.0) {
+                    gains[i][d] + 0.2
+                } else {
+                    (gains[i][d] * 0.8).max(MIN_GAIN)
+                };
+                velocity[i][d] =
+                    momentum * velocity[i][d] - learning_rate * gains[i][d] * gradients[i][d];
+                y[i][d] += velocity[i][d];
+            }
+        }
+    }
+    y.into_iter().map(|[x, y]| (x, y)).collect()
+}
+
+/// `tsne` for at most `max_points` of `points`, spread evenly through them, since comparing every
+/// pair takes memory and time which grow with the square of how many points there are. Each point
+/// left out is placed at the middle of its nearest sampled points.
+pub fn tsne_sampled(
+    points: &[OpenAIVector],
+    max_points: usize,
+    perplexity: f64,
+    iterations: usize,
+) -> Vec<(f64, f64)> {
+    let n = points.len();
+    if n <= max_points {
+        return tsne(points, perplexity, iterations);
+    }
+    let sampled: Vec<usize> = (0..max_points).map(|i| i * n / max_points).collect();
+    let sample: Vec<OpenAIVector> = sampled.iter().map(|i| points[*i].clone()).collect();
+    let placed = tsne(&sample, perplexity, iterations);
+
+    let mut positions = vec![None; n];
+    for (i, position) in sampled.iter().zip(&placed) {
+        positions[*i] = Some(*position);
+    }
+    positions
+        .into_iter()
+        .enumerate()
+        .map(|(i, position)| {
+            position.unwrap_or_else(|| {
+                let mut nearest: Vec<(usize, f64)> = sample
+                    .iter()
+                    .enumerate()
+                    .map(|(s, point)| (s, (&points[i] - point).norm_squared()))
+                    .collect();
+                nearest.sort_by(|a, b| a.1.total_cmp(&b.1));
+                nearest.truncate(SAMPLED_NEIGHBOURS);
+                let (x, y) = nearest.iter().fold((0.0, 0.0), |(x, y), (s, _)| {
+                    (x + placed[*s].0, y + placed[*s].1)
+                });
+                (x / nearest.len() as f64, y / nearest.len() as f64)
+            })
+        })
+        .collect()
+}
+
+/// Symmetric probabilities of each pair of points being neighbours, where each point's
+/// neighbourhood is a gaussian sized to give it the same perplexity
+fn joint_probabilities(distances: &[f64], n: usize, perplexity: f64) -> Vec<f64> {
+    let target_entropy = perplexity.ln();
+    let mut conditional = vec![0.0; n * n];
+    for i in 0..n {
+        let row = &distances[i * n..(i + 1) * n];
+        let (mut beta, mut low, mut high) = (1.0, 0.0, f64::INFINITY);
+        for _ in 0..PERPLEXITY_SEARCH_STEPS {
+            let weights: Vec<f64> = (0..n)
+                .map(|j| if j == i { 0.0 } else { (-row[j] * beta).exp() })
+                .collect();
+            let sum: f64 = weights.iter().sum::<f64>().max(f64::MIN_POSITIVE);
+            let weighted: f64 = (0..n).map(|j| row[j] * weights[j]).sum::<f64>() / sum;
+            let entropy = sum.ln() + beta * weighted;
+            for j in 0..n {
+                conditional[i * n + j] = weights[j] / sum;
+            }
+            if (entropy - target_entropy).abs() < PERPLEXITY_TOLERANCE {
+                break;
+            }
+            // too much entropy means the neighbourhood is too wide, so narrow it
+            if entropy > target_entropy {
+                low = beta;
+                beta = if high.is_finite() {
+                    (beta + high) / 2.0
+                } else {
+                    beta * 2.0
+                };
+            } else {
+                high = beta;
+                beta = (beta + low) / 2.0;
+            }
+        }
+    }
+    let mut joint = vec![0.0; n * n];
+    for i in 0..n {
+        for j in 0..n {
+            joint[i * n + j] =
+                ((conditional[i * n + j] + conditional[j * n + i]) / (2.0 * n as f64)).max(1e-12);
+        }
+    }
+    joint
+}
+
+/// Scale and move `points` to fit between 0.0 and 1.0 on both axes, keeping their shape
+pub fn normalise(points: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    let bounds = |coordinate: fn(&(f64, f64)) -> f64| {
+        points
+            .iter()
+            .map(coordinate)
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), v| {
+                (min.min(v), max.max(v))
+            })
+    };
+    let (min_x, max_x) = bounds(|p| p.0);
+    let (min_y, max_y) = bounds(|p| p.1);
+    let range = (max_x - min_x).max(max_y - min_y);
+    if !range.is_finite() || range == 0.0 {
+        return points.iter().map(|_| (0.5, 0.5)).collect();
+    }
+    // centre whichever axis is narrower
+    let offset_x = (range - (max_x - min_x)) / 2.0;
+    let offset_y = (range - (max_y - min_y)) / 2.0;
+    points
+        .iter()
+        .map(|(x, y)| {
+            (
+                (x - min_x + offset_x) / range,
+                (y - min_y + offset_y) / range,
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use shared::nearest::XorShift;
+
+    use super::*;
+
+    #[test]
+    fn test_pca_keeps_the_direction_of_most_variance_first() {
+        let vectors: Vec<OpenAIVector> = (0..10)
+            .map(|i| OpenAIVector::from_row_slice(&[i as f64, 2.0 * i as f64, 1.0]))
+            .collect();
+        let references: Vec<&OpenAIVector> = vectors.iter().collect();
+
+        let projected = pca(&references, 2);
+
+        assert_eq!(2, projected[0].len());
+        let spread = |component: usize| {
+            projected
+                .iter()
+                .map(|p| p[component].abs())
+                .fold(0.0, f64::max)
+        };
+        assert!(spread(0) > 1.0);
+        assert!(spread(1) < 1e-6);
+    }
+
+    #[test]
+    fn test_tsne_keeps_neighbours_together() {
+        let mut rng = XorShift::new(7);
+        let points: Vec<OpenAIVector> = (0..20)
+            .map(|i| {
+                let group = if i < 10 { 0.0 } else { 10.0 };
+                OpenAIVector::from_fn(3, |_, _| group + rng.next_f64())
+            })
+            .collect();
+
+        let placed = tsne(&points, 5.0, 300);
+
+        for (i, p) in placed.iter().enumerate() {
+            let nearest = (0..placed.len())
+                .filter(|j| *j != i)
+                .min_by(|a, b| {
+                    let distance = |q: &(f64, f64)| (p.0 - q.0).powi(2) + (p.1 - q.1).powi(2);
+                    distance(&placed[*a]).total_cmp(&distance(&placed[*b]))
+                })
+                .unwrap();
+            assert_eq!(i < 10, nearest < 10, "nearest to {} is {}", i, nearest);
+        }
+    }
+
+    #[test]
+    fn test_tsne_sampled_places_the_rest_near_their_neighbours() {
+        let mut rng = XorShift::new(7);
+        let points: Vec<OpenAIVector> = (0..60)
+            .map(|i| {
+                let group = if i < 30 { 0.0 } else { 10.0 };
+                OpenAIVector::from_fn(3, |_, _| group + rng.next_f64())
+            })
+            .collect();
+
+        let placed = tsne_sampled(&points, 20, 5.0, 300);
+
+        assert_eq!(60, placed.len());
+        let centre = |group: std::ops::Range<usize>| {
+            let (x, y) = group
+                .clone()
+                .fold((0.0, 0.0), |(x, y), i| (x + placed[i].0, y + placed[i].1));
+            (x / group.len() as f64, y / group.len() as f64)
+        };
+        let centres = [centre(0..30), centre(30..60)];
+        for (i, p) in placed.iter().enumerate() {
+            let distance = |q: &(f64, f64)| (p.0 - q.0).powi(2) + (p.1 - q.1).powi(2);
+            let nearer_first = distance(&centres[0]) < distance(&centres[1]);
+            assert_eq!(i < 30, nearer_first, "{} is nearer the other group", i);
+        }
+    }
+
+    #[test]
+    fn test_normalise_fits_between_zero_and_one() {
+        let placed = normalise(&[(-10.0, 0.0), (10.0, 5.0)]);
+
+        assert_eq!(vec![(0.0, 0.375), (1.0, 0.625)], placed);
+    }
+}
@@ -0,0 +1,279 @@
+use std::collections::BTreeSet;
+
+use chrono::{NaiveDate, NaiveTime, Timelike};
+use shared::model::{Event, EventId, EventType};
+use tracing::warn;
+use url::Url;
+use xmlserde::xml_deserialize_from_str;
+
+use crate::schedule_lint::{Check, Issue};
+use crate::{frab, pentabarf};
+
+/// A schedule, as read from one of the formats conferences publish, which can be turned into
+/// events for the model
+pub trait ScheduleSource {
+    /// The events in the schedule, all of which are in `year`. Events which can't be read are
+    /// left out, and recorded as issues, rather than failing the whole schedule.
+    fn import(self, year: u32) -> Result<ImportedSchedule, Box<dyn std::error::Error>>;
+}
+
+/// The events read from a schedule, and what it says about the conference they can be checked
+/// against, see `schedule_lint::lint`
+#[derive(Debug, Default)]
+pub struct ImportedSchedule {
+    pub events: Vec<Event>,
+    /// the rooms the schedule lists, if it does
+    pub rooms: Option<BTreeSet<String>>,
+    /// the first and last days of the conference, if the schedule says
+    pub days: Option<(NaiveDate, NaiveDate)>,
+    /// what was wrong with the events as they were read
+    pub issues: Vec<Issue>,
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScheduleFormat {
+    /// Pentabarf XML, as published by FOSDEM, in `{year}.xml`
+    #[default]
+    Pentabarf,
+    /// frab JSON, as published by Pretalx and frab, in `{year}.json`
+    Frab,
+}
+
+impl ScheduleFormat {
+    pub fn file_name(&self, year: u32) -> String {
+        match self {
+            ScheduleFormat::Pentabarf => format!("{}.xml", year),
+            ScheduleFormat::Frab => format!("{}.json", year),
+        }
+    }
+
+    pub fn read(
+        &self,
+        text: &str,
+        year: u32,
+    ) -> Result<ImportedSchedule, Box<dyn std::error::Error>> {
+        match self {
+            ScheduleFormat::Pentabarf => {
+                let schedule: pentabarf::Schedule = xml_deserialize_from_str(text)?;
+                schedule.import(year)
+            }
+            ScheduleFormat::Frab => {
+                let schedule: frab::ScheduleFile = serde_json::from_str(text)?;
+                schedule.import(year)
+            }
+        }
+    }
+}
+
+/// A duration like `01:30`, in minutes
+pub fn parse_into_minutes(value: &str) -> Result<u32, Box<dyn std::error::Error>> {
+    let time = NaiveTime::parse_from_str(value, "%H:%M")?;
+    Ok((time.hour() * 60) + time.minute())
+}
+
+pub fn parse_date(value: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|e| format!("bad date {:?}: {}", value, e))
+}
+
+pub fn parse_start(value: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(value, "%H:%M")
+        .map_err(|e| format!("bad start time {:?}: {}", value, e))
+}
+
+pub fn parse_duration(value: &str) -> Result<u32, String> {
+    parse_into_minutes(value).map_err(|e| format!("bad duration {:?}: {}", value, e))
+}
+
+pub fn parse_url(value: &str) -> Result<Url, String> {
+    Url::parse(value).map_err(|e| format!("bad url {:?}: {}", value, e))
+}
+
+/// The first and last days of the conference, from its start and end, which may be dates or
+/// date-times
+pub fn conference_days(start: Option<&str>, end: Option<&str>) -> Option<(NaiveDate, NaiveDate)> {
+    let day = |value: Option<&str>| parse_date(value?.trim().get(..10)?).ok();
+    Some((day(start)?, day(end)?))
+}
+
+/// Links which can't be parsed are left out of the event, and recorded as issues
+pub fn parse_links<T>(
+    id: EventId,
+    links: impl IntoIterator<Item = (String, T)>,
+    issues: &mut Vec<Issue>,
+) -> Vec<(Url, T)> {
+    links
+        .into_iter()
+        .filter_map(|(url, value)| match parse_url(&url) {
+            Ok(url) => Some((url, value)),
+            Err(message) => {
+                issues.push(Issue::new(Check::BadLink, id, message));
+                None
+            }
+        })
+        .collect()
+}
+
+/// Schedules leave out what an event doesn't have as an empty element, rather than no element
+pub fn non_empty(value: String) -> Option<String> {
+    let value = value.trim();
+    if value.is_empty() {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+
+/// The type of event `id`, which Pentabarf gives as e.g. `lightningtalk`, and Pretalx as the
+/// name of the submission type, e.g. `Lightning Talk`
+pub fn event_type(id: u32, value: &str) -> EventType {
+    let name: String = value.chars().filter(|c| c.is_alphanumeric()).collect();
+    let event_type = name.parse().unwrap_or_default();
+    if event_type == EventType::Other && !name.is_empty() {
+        warn!("event id {} has unknown type {:?}", id, value);
+    }
+    event_type
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::schedule_lint::lint;
+
+    use super::*;
+
+    const PENTABARF_FIXTURE: &str = include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/fixtures/schedule.xml"
+    ));
+    const FRAB_FIXTURE: &str = include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/fixtures/schedule.json"
+    ));
+
+    #[test]
+    fn test_parse_into_minutes_min_value() {
+        assert_eq!(0, parse_into_minutes("00:00").unwrap());
+    }
+
+    #[test]
+    fn test_parse_into_minutes_hour_and_minute_detail() {
+        assert_eq!((2 * 60) + 22, parse_into_minutes("02:22").unwrap());
+    }
+
+    #[test]
+    fn test_parse_into_minutes_max_value() {
+        assert_eq!((23 * 60) + 59, parse_into_minutes("23:59").unwrap());
+    }
+
+    #[test]
+    fn test_empty_elements_are_missing() {
+        assert_eq!(None, non_empty("".to_string()));
+        assert_eq!(None, non_empty(" \n ".to_string()));
+        assert_eq!(Some("en".to_string()), non_empty(" en ".to_string()));
+    }
+
+    #[test]
+    fn test_parse_into_minutes_invalid_values() {
+        for example in ["foop", "24:00"] {
+            assert!(parse_into_minutes(example).is_err());
+        }
+    }
+
+    #[test]
+    fn test_event_types_from_either_format() {
+        assert_eq!(EventType::LightningTalk, event_type(1, "lightningtalk"));
+        assert_eq!(EventType::LightningTalk, event_type(1, "Lightning Talk"));
+        assert_eq!(EventType::Bof, event_type(1, "BoF"));
+        assert_eq!(EventType::Other, event_type(1, "Talk"));
+        assert_eq!(EventType::Other, event_type(1, ""));
+    }
+
+    #[test]
+    fn test_pentabarf_fixture() {
+        let events = ScheduleFormat::Pentabarf
+            .read(PENTABARF_FIXTURE, 2026)
+            .unwrap()
+            .events;
+
+        assert_eq!(3, events.len());
+        let welcome = &events[0];
+        assert_eq!("Welcome to FOSDEM 2026", welcome.title);
+        assert_eq!(EventType::Keynote, welcome.event_type);
+        assert_eq!(Some("janson".to_string()), welcome.room_slug);
+        assert_eq!(Some("keynotes".to_string()), welcome.track_slug);
+        assert_eq!(20, welcome.duration);
+        assert_eq!(1, welcome.slides.len());
+        assert_eq!(2, welcome.presenters.len());
+        assert!(welcome.has_video());
+        let bof = &events[2];
+        assert_eq!(EventType::Bof, bof.event_type);
+        assert_eq!("2026-02-01", bof.date.to_string());
+        assert_eq!(None, bof.language);
+    }
+
+    #[test]
+    fn test_frab_fixture_is_the_same_schedule_as_the_pentabarf_one() {
+        let pentabarf = ScheduleFormat::Pentabarf
+            .read(PENTABARF_FIXTURE, 2026)
+            .unwrap();
+        let frab = ScheduleFormat::Frab.read(FRAB_FIXTURE, 2026).unwrap();
+
+        assert_eq!(pentabarf.events, frab.events);
+        assert_eq!(pentabarf.rooms, frab.rooms);
+        assert_eq!(pentabarf.days, frab.days);
+    }
+
+    #[test]
+    fn test_fixtures_only_have_an_empty_abstract() {
+        for (format, fixture) in [
+            (ScheduleFormat::Pentabarf, PENTABARF_FIXTURE),
+            (ScheduleFormat::Frab, FRAB_FIXTURE),
+        ] {
+            let schedule = format.read(fixture, 2026).unwrap();
+
+            let issues = lint(&schedule);
+
+            let checks: Vec<_> = issues.iter().map(|i| (i.check, i.event)).collect();
+            assert_eq!(
+                vec![(Check::EmptyAbstract, EventId::new(2026, 8502))],
+                checks,
+                "{:?}",
+                format
+            );
+        }
+    }
+
+    #[test]
+    fn test_unreadable_events_are_left_out() {
+        let fixture = PENTABARF_FIXTURE
+            .replacen("<start>09:30</start>", "<start>25:00</start>", 1)
+            .replacen(
+                "<feedback_url></feedback_url>",
+                "<feedback_url>not a url</feedback_url>",
+                1,
+            );
+
+        let schedule = ScheduleFormat::Pentabarf.read(&fixture, 2026).unwrap();
+
+        assert_eq!(2, schedule.events.len());
+        let issues: Vec<_> = schedule
+            .issues
+            .iter()
+            .map(|i| (i.check, i.event.event_in_year()))
+            .collect();
+        assert_eq!(
+            vec![(Check::Unreadable, 8376), (Check::BadLink, 8401)],
+            issues
+        );
+        assert_eq!(
+            "bad start time \"25:00\": input is out of range",
+            schedule.issues[0].message
+        );
+        assert_eq!(None, schedule.events[0].feedback_url);
+    }
+
+    #[test]
+    fn test_each_format_rejects_the_other() {
+        assert!(ScheduleFormat::Pentabarf.read(FRAB_FIXTURE, 2026).is_err());
+        assert!(ScheduleFormat::Frab.read(PENTABARF_FIXTURE, 2026).is_err());
+    }
+}
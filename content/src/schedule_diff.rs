@@ -0,0 +1,418 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+use chrono::{NaiveDate, NaiveTime};
+use shared::model::{Event, EventId};
+
+/// When an event is scheduled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Slot {
+    pub date: NaiveDate,
+    pub start: NaiveTime,
+    /// in minutes
+    pub duration: u32,
+}
+
+impl Slot {
+    fn of(event: &Event) -> Slot {
+        Slot {
+            date: event.date,
+            start: event.start,
+            duration: event.duration,
+        }
+    }
+}
+
+impl std::fmt::Display for Slot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} ({} min)",
+            self.date.format("%a"),
+            self.start.format("%H:%M"),
+            self.duration
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    Retimed { before: Slot, after: Slot },
+    RoomChanged { before: String, after: String },
+    Retitled { before: String },
+    AbstractChanged,
+}
+
+/// An event which is in both imports, but not quite the same
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChangedEvent {
+    /// as it is now
+    pub event: Event,
+    /// if it has a different id now, it was matched by its guid
+    pub previous_id: EventId,
+    pub changes: Vec<Change>,
+}
+
+impl ChangedEvent {
+    fn changes_matching(&self, matches: ChangeKind) -> impl Iterator<Item = &Change> {
+        self.changes.iter().filter(move |c| matches(c))
+    }
+}
+
+/// How one import of the schedule differs from an earlier one
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ScheduleDiff {
+    pub added: Vec<Event>,
+    pub removed: Vec<Event>,
+    pub changed: Vec<ChangedEvent>,
+}
+
+/// Compare the events of two imports. Events are the same event if they have the same
+/// `EventId`, or failing that the same guid, so that an event which is given a new id is seen
+/// as changed rather than as removed and added.
+pub fn diff_events(before: &[Event], after: &[Event]) -> ScheduleDiff {
+    let after_by_id: HashMap<EventId, &Event> = after.iter().map(|e| (e.id, e)).collect();
+    let after_by_guid: HashMap<&str, &Event> = after.iter().map(|e| (e.guid.as_str(), e)).collect();
+
+    let mut diff = ScheduleDiff::default();
+    let mut matched = HashSet::new();
+    for previous in before {
+        let current = after_by_id
+            .get(&previous.id)
+            .or_else(|| after_by_guid.get(previous.guid.as_str()))
+            .filter(|current| !matched.contains(&current.id));
+        let Some(current) = current else {
+            diff.removed.push(previous.clone());
+            continue;
+        };
+        matched.insert(current.id);
+        let changes = changes(previous, current);
+        if !changes.is_empty() {
+            diff.changed.push(ChangedEvent {
+                event: (*current).clone(),
+                previous_id: previous.id,
+                changes,
+            });
+        }
+    }
+    diff.added = after
+        .iter()
+        .filter(|e| !matched.contains(&e.id))
+        .cloned()
+        .collect();
+
+    diff.added.sort_by_key(|e| (e.starting_time(), e.id));
+    diff.removed.sort_by_key(|e| (e.starting_time(), e.id));
+    diff.changed
+        .sort_by_key(|c| (c.event.starting_time(), c.event.id));
+    diff
+}
+
+fn changes(before: &Event, after: &Event) -> Vec<Change> {
+    let mut changes = vec![];
+    if Slot::of(before) != Slot::of(after) {
+        changes.push(Change::Retimed {
+            before: Slot::of(before),
+            after: Slot::of(after),
+        });
+    }
+    if before.room != after.room {
+        changes.push(Change::RoomChanged {
+            before: before.room.clone(),
+            after: after.room.clone(),
+        });
+    }
+    if before.title != after.title {
+        changes.push(Change::Retitled {
+            before: before.title.clone(),
+        });
+    }
+    if before.r#abstract.trim() != after.r#abstract.trim() {
+        changes.push(Change::AbstractChanged);
+    }
+    changes
+}
+
+/// Whether a change is of a particular kind
+type ChangeKind = fn(&Change) -> bool;
+
+/// The kinds of change, in the order they're reported, with what each is called
+const CHANGE_KINDS: [(&str, ChangeKind); 4] = [
+    ("retimed", |c| matches!(c, Change::Retimed { .. })),
+    ("room changed", |c| matches!(c, Change::RoomChanged { .. })),
+    ("retitled", |c| matches!(c, Change::Retitled { .. })),
+    ("abstract changed", |c| matches!(c, Change::AbstractChanged)),
+];
+
+impl ScheduleDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// e.g. "2 added, 1 removed, 3 retimed, 0 room changed, 1 retitled, 0 abstract changed"
+    pub fn counts(&self) -> String {
+        let mut counts = vec![
+            format!("{} added", self.added.len()),
+            format!("{} removed", self.removed.len()),
+        ];
+        for (name, matches) in CHANGE_KINDS {
+            let count = self
+                .changed
+                .iter()
+                .filter(|c| c.changes.iter().any(matches))
+                .count();
+            counts.push(format!("{} {}", count, name));
+        }
+        counts.join(", ")
+    }
+
+    /// A plain text report, with a line for each event under each kind of change
+    pub fn report(&self) -> String {
+        let mut report = String::new();
+        writeln!(report, "{}", self.counts()).unwrap();
+        for (heading, events) in [("Added", &self.added), ("Removed", &self.removed)] {
+            if !events.is_empty() {
+                writeln!(report, "\n{}", heading).unwrap();
+            }
+            for event in events.iter() {
+                writeln!(
+                    report,
+                    "  {} {} {}, {}: {}",
+                    event.id,
+                    event.date.format("%a"),
+                    event.start.format("%H:%M"),
+                    event.room,
+                    event.title
+                )
+                .unwrap();
+            }
+        }
+        for (name, matches) in CHANGE_KINDS {
+            let mut lines = vec![];
+            for changed in &self.changed {
+                for change in changed.changes_matching(matches) {
+                    let detail = match change {
+                        Change::Retimed { before, after } => format!("{} -> {}", before, after),
+                        Change::RoomChanged { before, after } => {
+                            format!("{} -> {}", before, after)
+                        }
+                        Change::Retitled { before } => format!("was {:?}", before),
+                        Change::AbstractChanged => String::new(),
+                    };
+                    let id = if changed.previous_id == changed.event.id {
+                        changed.event.id.to_string()
+                    } else {
+                        format!("{} (was {})", changed.event.id, changed.previous_id)
+                    };
+                    lines.push(if detail.is_empty() {
+                        format!("  {} {}", id, changed.event.title)
+                    } else {
+                        format!("  {} {}: {}", id, changed.event.title, detail)
+                    });
+                }
+            }
+            if !lines.is_empty() {
+                writeln!(report, "\n{}", capitalised(name)).unwrap();
+                for line in lines {
+                    writeln!(report, "{}", line).unwrap();
+                }
+            }
+        }
+        report
+    }
+
+    /// The same as `report`, as markdown with links to each event, to go in a blog post. It
+    /// starts with a summary line, and has a `###` section for each kind of change.
+    pub fn markdown(&self) -> String {
+        let mut markdown = String::new();
+        if self.is_empty() {
+            writeln!(markdown, "No events were added, removed or changed.").unwrap();
+            return markdown;
+        }
+        writeln!(markdown, "{}.", capitalised(&self.counts())).unwrap();
+        for (heading, events) in [("Added", &self.added), ("Removed", &self.removed)] {
+            if events.is_empty() {
+                continue;
+            }
+            writeln!(markdown, "\n### {}\n", heading).unwrap();
+            for event in events.iter() {
+                writeln!(
+                    markdown,
+                    "* {}, {} {} in {}",
+                    event_link(event),
+                    event.date.format("%a"),
+                    event.start.format("%H:%M"),
+                    event.room
+                )
+                .unwrap();
+            }
+        }
+        for (name, matches) in CHANGE_KINDS {
+            let mut lines = vec![];
+            for changed in &self.changed {
+                for change in changed.changes_matching(matches) {
+                    let link = event_link(&changed.event);
+                    lines.push(match change {
+                        Change::Retimed { before, after } => {
+                            format!("* {}: {} → {}", link, before, after)
+                        }
+                        Change::RoomChanged { before, after } => {
+                            format!("* {}: {} → {}", link, before, after)
+                        }
+                        Change::Retitled { before } => format!("* {}, was “{}”", link, before),
+                        Change::AbstractChanged => format!("* {}", link),
+                    });
+                }
+            }
+            if !lines.is_empty() {
+                writeln!(markdown, "\n### {}\n", capitalised(name)).unwrap();
+                for line in lines {
+                    writeln!(markdown, "{}", line).unwrap();
+                }
+            }
+        }
+        markdown
+    }
+}
+
+fn event_link(event: &Event) -> String {
+    format!(
+        "[{}](/{}/event/{}/)",
+        event.title.replace('[', "\\[").replace(']', "\\]"),
+        event.id.year(),
+        event.id.event_in_year()
+    )
+}
+
+fn capitalised(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use shared::model::{test_event, EventType};
+
+    use super::*;
+
+    fn event(id: u32, guid: &str, title: &str) -> Event {
+        Event {
+            guid: guid.to_string(),
+            date: NaiveDate::from_ymd_opt(2026, 1, 31).unwrap(),
+            room: "H.1302".to_string(),
+            track: "Rust".to_string(),
+            event_type: EventType::Devroom,
+            ..test_event(EventId::new(2026, id), title)
+        }
+    }
+
+    #[test]
+    fn test_each_kind_of_change_is_found() {
+        let unchanged = event(1, "a", "Unchanged");
+        let removed = event(2, "b", "Cancelled");
+        let moved = event(3, "c", "Moved");
+        let reworked = event(4, "d", "Reworked");
+        let before = vec![
+            unchanged.clone(),
+            removed.clone(),
+            moved.clone(),
+            reworked.clone(),
+        ];
+        let added = event(5, "e", "New");
+        let moved_now = Event {
+            start: NaiveTime::from_hms_opt(11, 0, 0).unwrap(),
+            room: "K.1.105".to_string(),
+            ..moved.clone()
+        };
+        // given a new id, but the same guid
+        let reworked_now = Event {
+            id: EventId::new(2026, 40),
+            title: "Reworked, again".to_string(),
+            r#abstract: "A new abstract".to_string(),
+            ..reworked.clone()
+        };
+        let after = vec![
+            unchanged,
+            moved_now.clone(),
+            reworked_now.clone(),
+            added.clone(),
+        ];
+
+        let diff = diff_events(&before, &after);
+
+        assert_eq!(vec![added], diff.added);
+        assert_eq!(vec![removed], diff.removed);
+        assert_eq!(
+            vec![
+                ChangedEvent {
+                    event: reworked_now,
+                    previous_id: reworked.id,
+                    changes: vec![
+                        Change::Retitled {
+                            before: "Reworked".to_string()
+                        },
+                        Change::AbstractChanged
+                    ],
+                },
+                ChangedEvent {
+                    event: moved_now,
+                    previous_id: moved.id,
+                    changes: vec![
+                        Change::Retimed {
+                            before: Slot::of(&moved),
+                            after: Slot {
+                                start: NaiveTime::from_hms_opt(11, 0, 0).unwrap(),
+                                ..Slot::of(&moved)
+                            },
+                        },
+                        Change::RoomChanged {
+                            before: "H.1302".to_string(),
+                            after: "K.1.105".to_string()
+                        }
+                    ],
+                },
+            ],
+            diff.changed
+        );
+        assert_eq!(
+            "1 added, 1 removed, 1 retimed, 1 room changed, 1 retitled, 1 abstract changed",
+            diff.counts()
+        );
+    }
+
+    #[test]
+    fn test_reports() {
+        let before = vec![event(1, "a", "Rust"), event(2, "b", "Go")];
+        let after = vec![
+            Event {
+                room: "K.1.105".to_string(),
+                ..event(1, "a", "Rust")
+            },
+            event(3, "c", "Zig [intro]"),
+        ];
+
+        let diff = diff_events(&before, &after);
+
+        let report = diff.report();
+        assert!(report.contains("\nAdded\n  2026-3 Sat 10:00, H.1302: Zig [intro]\n"));
+        assert!(report.contains("\nRemoved\n  2026-2 Sat 10:00, H.1302: Go\n"));
+        assert!(report.contains("\nRoom changed\n  2026-1 Rust: H.1302 -> K.1.105\n"));
+        assert!(!report.contains("Retimed"));
+
+        let markdown = diff.markdown();
+        assert!(markdown.starts_with("1 added, 1 removed, 0 retimed, 1 room changed"));
+        assert!(markdown
+            .contains("\n### Added\n\n* [Zig \\[intro\\]](/2026/event/3/), Sat 10:00 in H.1302\n"));
+        assert!(
+            markdown.contains("\n### Room changed\n\n* [Rust](/2026/event/1/): H.1302 → K.1.105\n")
+        );
+
+        assert_eq!(
+            "No events were added, removed or changed.\n",
+            diff_events(&before, &before).markdown()
+        );
+    }
+}
@@ -0,0 +1,328 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+use shared::model::{Event, EventId};
+use url::Url;
+
+use crate::schedule::ImportedSchedule;
+
+/// What's wrong with an event
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Check {
+    /// it couldn't be read, e.g. because of a bad date or time, so it's been left out
+    Unreadable,
+    /// it starts before another event in the same room has finished
+    Overlap,
+    /// it takes no time
+    NoDuration,
+    EmptyAbstract,
+    /// another event in the same year has the same title
+    DuplicateTitle,
+    /// a link which couldn't be parsed, and has been left out
+    BadLink,
+    /// a link which isn't HTTPS
+    InsecureLink,
+    /// it's in a room which the schedule doesn't list
+    UnknownRoom,
+    /// it's on a day which isn't one of the conference's
+    OutsideConference,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Issue {
+    pub check: Check,
+    pub event: EventId,
+    pub message: String,
+}
+
+impl Issue {
+    pub fn new(check: Check, event: EventId, message: String) -> Issue {
+        Issue {
+            check,
+            event,
+            message,
+        }
+    }
+}
+
+impl std::fmt::Display for Issue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {:?}: {}", self.event, self.check, self.message)
+    }
+}
+
+/// The issues found in an import, as written by `import_events --lint-report`
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct LintReport {
+    pub counts: BTreeMap<Check, usize>,
+    pub issues: Vec<Issue>,
+}
+
+impl LintReport {
+    pub fn new(issues: Vec<Issue>) -> LintReport {
+        let mut counts = BTreeMap::new();
+        for issue in &issues {
+            *counts.entry(issue.check).or_insert(0) += 1;
+        }
+        LintReport { counts, issues }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// e.g. "3 overlap, 1 empty_abstract"
+    pub fn summary(&self) -> String {
+        let counts: Vec<_> = self
+            .counts
+            .iter()
+            .map(|(check, count)| {
+                let name = serde_json::to_value(check)
+                    .ok()
+                    .and_then(|v| v.as_str().map(str::to_string))
+                    .unwrap_or_else(|| format!("{:?}", check));
+                format!("{} {}", count, name)
+            })
+            .collect();
+        counts.join(", ")
+    }
+}
+
+/// Everything which is wrong with the events of one year's schedule: those found reading it,
+/// and then those found checking what was read, in the order of the `Check`s
+pub fn lint(schedule: &ImportedSchedule) -> Vec<Issue> {
+    let events = &schedule.events;
+    let mut issues = schedule.issues.clone();
+    issues.extend(overlaps(events));
+    for event in events {
+        if event.duration == 0 {
+            issues.push(Issue::new(
+                Check::NoDuration,
+                event.id,
+                format!("{:?} has a duration of 0 minutes", event.title),
+            ));
+        }
+        if event.r#abstract.trim().is_empty() {
+            issues.push(Issue::new(
+                Check::EmptyAbstract,
+                event.id,
+                format!("{:?} has no abstract", event.title),
+            ));
+        }
+    }
+    issues.extend(duplicate_titles(events));
+    for event in events {
+        for url in links(event) {
+            if url.scheme() != "https" {
+                issues.push(Issue::new(
+                    Check::InsecureLink,
+                    event.id,
+                    format!("{} isn't HTTPS", url),
+                ));
+            }
+        }
+    }
+    if let Some(rooms) = &schedule.rooms {
+        for event in events.iter().filter(|e| !rooms.contains(&e.room)) {
+            issues.push(Issue::new(
+                Check::UnknownRoom,
+                event.id,
+                format!("{:?} isn't one of the schedule's rooms", event.room),
+            ));
+        }
+    }
+    if let Some((first, last)) = schedule.days {
+        for event in events.iter().filter(|e| e.date < first || e.date > last) {
+            issues.push(Issue::new(
+                Check::OutsideConference,
+                event.id,
+                format!(
+                    "is on {}, but the conference is from {} to {}",
+                    event.date, first, last
+                ),
+            ));
+        }
+    }
+    issues.sort_by_key(|i| i.check);
+    issues
+}
+
+/// Events which start before the one before them in the same room has ended
+fn overlaps(events: &[Event]) -> Vec<Issue> {
+    let mut by_room: BTreeMap<(_, &str), Vec<&Event>> = BTreeMap::new();
+    for event in events {
+        by_room
+            .entry((event.date, event.room.as_str()))
+            .or_default()
+            .push(event);
+    }
+    let mut issues = vec![];
+    for room_events in by_room.values_mut() {
+        room_events.sort_by_key(|e| (e.starting_time(), e.id));
+        // the event which ends last so far, which may not be the one just before
+        let mut latest: Option<&Event> = None;
+        for event in room_events.iter() {
+            if let Some(previous) = latest {
+                if event.starting_time() < previous.ending_time() {
+                    issues.push(Issue::new(
+                        Check::Overlap,
+                        event.id,
+                        format!(
+                            "{:?} starts at {}, before {} {:?} ends at {} in {}",
+                            event.title,
+                            event.start.format("%H:%M"),
+                            previous.id,
+                            previous.title,
+                            previous.ending_time().format("%H:%M"),
+                            event.room
+                        ),
+                    ));
+                }
+            }
+            if latest.is_none_or(|l| event.ending_time() > l.ending_time()) {
+                latest = Some(event);
+            }
+        }
+    }
+    issues
+}
+
+fn duplicate_titles(events: &[Event]) -> Vec<Issue> {
+    let mut first_with_title: HashMap<String, &Event> = HashMap::new();
+    let mut issues = vec![];
+    for event in events {
+        let title = event.title.trim().to_lowercase();
+        match first_with_title.get(&title) {
+            Some(first) => issues.push(Issue::new(
+                Check::DuplicateTitle,
+                event.id,
+                format!("{:?} is also the title of {}", event.title, first.id),
+            )),
+            None => {
+                first_with_title.insert(title, event);
+            }
+        }
+    }
+    issues
+}
+
+fn links(event: &Event) -> impl Iterator<Item = &Url> {
+    std::iter::once(&event.url)
+        .chain(event.slides.iter())
+        .chain(event.links.iter().map(|l| &l.url))
+        .chain(event.feedback_url.iter())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use chrono::{NaiveDate, NaiveTime};
+    use shared::model::{test_event, EventType};
+
+    use super::*;
+
+    fn event(id: u32, title: &str, start: (u32, u32), duration: u32) -> Event {
+        Event {
+            date: NaiveDate::from_ymd_opt(2026, 1, 31).unwrap(),
+            start: NaiveTime::from_hms_opt(start.0, start.1, 0).unwrap(),
+            duration,
+            room: "H.1302".to_string(),
+            track: "Rust".to_string(),
+            event_type: EventType::Devroom,
+            ..test_event(EventId::new(2026, id), title)
+        }
+    }
+
+    fn checks(issues: &[Issue]) -> Vec<(Check, u32)> {
+        issues
+            .iter()
+            .map(|i| (i.check, i.event.event_in_year()))
+            .collect()
+    }
+
+    #[test]
+    fn test_nothing_to_report() {
+        let schedule = ImportedSchedule {
+            events: vec![
+                event(1, "First", (10, 0), 30),
+                event(2, "Second", (10, 30), 30),
+            ],
+            ..Default::default()
+        };
+
+        assert_eq!(Vec::<Issue>::new(), lint(&schedule));
+    }
+
+    #[test]
+    fn test_each_check() {
+        let unreadable = Issue::new(
+            Check::Unreadable,
+            EventId::new(2026, 9),
+            "bad start time \"25:00\"".to_string(),
+        );
+        let schedule = ImportedSchedule {
+            events: vec![
+                event(1, "Long", (10, 0), 60),
+                // both start before the long one ends
+                event(2, "Short", (10, 15), 15),
+                event(3, "Later", (10, 45), 30),
+                event(4, "Empty", (12, 0), 0),
+                Event {
+                    r#abstract: " ".to_string(),
+                    ..event(5, "long", (13, 0), 30)
+                },
+                Event {
+                    links: vec![shared::model::Link {
+                        url: "http://example.com/notes".parse().unwrap(),
+                        name: "Notes".to_string(),
+                    }],
+                    ..event(6, "Insecure", (14, 0), 30)
+                },
+                Event {
+                    room: "Nowhere".to_string(),
+                    date: NaiveDate::from_ymd_opt(2026, 2, 2).unwrap(),
+                    ..event(7, "Lost", (10, 0), 30)
+                },
+            ],
+            rooms: Some(BTreeSet::from(["H.1302".to_string()])),
+            days: Some((
+                NaiveDate::from_ymd_opt(2026, 1, 31).unwrap(),
+                NaiveDate::from_ymd_opt(2026, 2, 1).unwrap(),
+            )),
+            issues: vec![unreadable.clone()],
+        };
+
+        let issues = lint(&schedule);
+
+        assert_eq!(
+            vec![
+                (Check::Unreadable, 9),
+                (Check::Overlap, 2),
+                (Check::Overlap, 3),
+                (Check::NoDuration, 4),
+                (Check::EmptyAbstract, 5),
+                (Check::DuplicateTitle, 5),
+                (Check::InsecureLink, 6),
+                (Check::UnknownRoom, 7),
+                (Check::OutsideConference, 7),
+            ],
+            checks(&issues)
+        );
+        assert_eq!(unreadable, issues[0]);
+        assert_eq!(
+            "\"Later\" starts at 10:45, before 2026-1 \"Long\" ends at 11:00 in H.1302",
+            issues[2].message
+        );
+
+        let report = LintReport::new(issues);
+        assert_eq!(Some(&2), report.counts.get(&Check::Overlap));
+        assert!(report
+            .summary()
+            .starts_with("1 unreadable, 2 overlap, 1 no_duration"));
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(2, json["counts"]["overlap"]);
+        assert_eq!("empty_abstract", json["issues"][4]["check"]);
+    }
+}
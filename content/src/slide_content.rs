@@ -0,0 +1,27 @@
+use std::{collections::HashMap, fs::File, io::Read, path::Path};
+
+use shared::model::{Event, EventId};
+use tracing::info;
+
+/// Read the slide text written by `fetch_slide_content`, for any of `events` which have some
+pub fn load_slide_content(
+    base_path: &Path,
+    events: &[Event],
+) -> Result<HashMap<EventId, String>, Box<dyn std::error::Error>> {
+    info!("Fetching slide content from {:?} ... ", base_path);
+    let mut slide_content_for_event: HashMap<EventId, String> = HashMap::new();
+    for event in events.iter() {
+        let slide_content_path = base_path.join(event.id.to_string()).with_extension("txt");
+        if slide_content_path.exists() {
+            let mut file = File::open(slide_content_path)?;
+            let mut slide_content = String::new();
+            file.read_to_string(&mut slide_content)?;
+            slide_content_for_event.insert(event.id, slide_content);
+        }
+    }
+    info!(
+        "Read {} events with slide content ",
+        slide_content_for_event.len()
+    );
+    Ok(slide_content_for_event)
+}
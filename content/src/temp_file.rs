@@ -28,6 +28,24 @@ impl TempFile {
         Ok(temp_file)
     }
 
+    /// Like `create`, but leaves anything already at `real_path` there until `commit` replaces
+    /// it, for files which should never be missing
+    pub fn replacing(
+        real_path: PathBuf,
+        tmp_path: PathBuf,
+    ) -> Result<TempFile, Box<dyn std::error::Error>> {
+        debug!("using {:?} as tmp file", tmp_path);
+
+        let temp_file = TempFile {
+            real_path,
+            tmp_path,
+        };
+
+        temp_file.cleanup_tmp_file()?;
+
+        Ok(temp_file)
+    }
+
     pub fn commit(self) -> Result<(), Box<dyn std::error::Error>> {
         debug!(
             "committing, renaming {:?} to {:?}",
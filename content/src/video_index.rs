@@ -7,8 +7,11 @@ use std::{
 
 use regex::Regex;
 use shared::model;
+use subtp::vtt::VttBlock;
 use tracing::info;
 
+use crate::passages::transcript_passages;
+
 #[derive(Debug)]
 pub struct VideoIndex {
     entries: HashMap<model::EventId, VideoIndexEntry>,
@@ -58,4 +61,38 @@ impl VideoIndex {
             .get(&event_id)
             .map(|entry| entry.webvtt.clone())
     }
+
+    /// The text of all cues for an event, with consecutive repeats removed
+    pub fn transcript_for_event_id(&self, event_id: model::EventId) -> Option<String> {
+        self.entries.get(&event_id).map(|entry| {
+            let mut block_content: Vec<_> = entry
+                .webvtt
+                .blocks
+                .iter()
+                .map(|b| match b {
+                    VttBlock::Que(cue) => cue.payload.join("\n"),
+                    _ => "".into(),
+                })
+                .collect();
+            block_content.dedup();
+            block_content.join("\n")
+        })
+    }
+
+    pub fn passages_for_event_id(&self, event_id: model::EventId) -> Vec<model::Passage> {
+        self.entries
+            .get(&event_id)
+            .map(|entry| transcript_passages(event_id, &entry.webvtt))
+            .unwrap_or_default()
+    }
+
+    pub fn transcripts(&self) -> HashMap<model::EventId, String> {
+        self.entries
+            .keys()
+            .filter_map(|event_id| {
+                self.transcript_for_event_id(*event_id)
+                    .map(|transcript| (*event_id, transcript))
+            })
+            .collect()
+    }
 }
@@ -1,13 +1,15 @@
 use std::path::PathBuf;
+use std::time::Duration;
 
 use axum::{http::StatusCode, routing::get};
-use chrono::Utc;
 use clap::Parser;
 use fly::tracing::{init_opentelemetry_from_environment, init_safe_default_from_environment};
-use shared::env::load_secret;
+use shared::cli::{EmbeddingProviderArgs, NearestNeighbourArgs};
+use shared::manifest::ManifestPolicy;
 use tokio::net::TcpListener;
 use tracing::info;
-use webapp::router::{app_state, router};
+use webapp::router::router;
+use webapp::state::{AppState, SnapshotLoader};
 
 async fn health() -> StatusCode {
     StatusCode::NO_CONTENT
@@ -33,6 +35,10 @@ struct Args {
     #[arg(long)]
     include_video_content: Option<PathBuf>,
 
+    /// include slide content at path, so that it can be searched for
+    #[arg(long)]
+    include_slide_content: Option<PathBuf>,
+
     /// path to blog posts directory
     #[arg(long)]
     blog_content_dir: PathBuf,
@@ -40,6 +46,20 @@ struct Args {
     /// enable opentelemetry
     #[arg(long)]
     opentelemetry: bool,
+
+    #[command(flatten)]
+    embedding: EmbeddingProviderArgs,
+
+    #[command(flatten)]
+    nearest_neighbours: NearestNeighbourArgs,
+
+    /// what to do if the model dir doesn't match its manifest
+    #[arg(long, value_enum, default_value_t = ManifestPolicy::Strict)]
+    manifest_policy: ManifestPolicy,
+
+    /// check the model dir this often, in seconds, and reload when it changes
+    #[arg(long)]
+    watch_interval: Option<u64>,
 }
 
 #[tokio::main]
@@ -63,17 +83,26 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         init_safe_default_from_environment()?;
     }
 
-    let openai_api_key = load_secret("OPENAI_API_KEY")?;
-    let app_state = app_state(
-        &openai_api_key,
-        &args.model_dir,
-        &args.include_video_content,
-        &args.blog_content_dir,
-        args.current_year,
-        args.selectable_years,
-        Utc::now(),
-    )
-    .await;
+    let loader = SnapshotLoader {
+        model_dir: args.model_dir,
+        embedding: args.embedding.config(None),
+        index_config: args.nearest_neighbours.config(),
+        manifest_policy: args.manifest_policy,
+        video_content_dir: args.include_video_content,
+        slide_content_dir: args.include_slide_content,
+        blog_content_dir: args.blog_content_dir,
+        current_year: args.current_year,
+        selectable_years: args.selectable_years,
+    };
+    let admin_token = std::env::var("ADMIN_TOKEN").ok();
+    if admin_token.is_none() {
+        info!("ADMIN_TOKEN is not set, so reloading via /admin/reload is off");
+    }
+    let app_state = AppState::load(loader, admin_token).await?;
+    if let Some(seconds) = args.watch_interval {
+        info!("Watching model dir every {}s", seconds);
+        app_state.watch_model_dir(Duration::from_secs(seconds));
+    }
 
     let router = router(app_state).await;
     let app = router.route("/health", get(health));
@@ -224,30 +224,24 @@ fn create_timetable_for_day(
 mod tests {
     use super::*;
     use chrono::Datelike;
-    use shared::model::{EventId, Person, PersonId};
-    use url::Url;
+    use shared::model::{EventId, Person, PersonId, test_event};
 
     fn make_event(id: u32, date: NaiveDate, start: NaiveTime, duration: u32, room: &str) -> Event {
         let year = date.year() as u32;
         Event {
-            id: EventId::new(year, id),
-            guid: format!("guid-{}", id),
-            year,
             date,
             start,
             duration,
             room: room.to_string(),
             track: "Test Track".to_string(),
-            title: format!("Event {}", id),
             slug: format!("event-{}", id),
-            url: Url::parse("https://example.com").unwrap(),
             r#abstract: String::new(),
-            slides: vec![],
             presenters: vec![Person {
                 id: PersonId::new(year, 1),
                 name: "Test".to_string(),
+                global_id: None,
             }],
-            links: vec![],
+            ..test_event(EventId::new(year, id), &format!("Event {}", id))
         }
     }
 
@@ -353,7 +347,7 @@ mod tests {
         let day = NaiveDate::from_ymd_opt(2024, 2, 3).unwrap();
         let start = NaiveTime::from_hms_opt(10, 0, 0).unwrap();
 
-        let events = vec![make_event(1, day, start, 60, "Room1")];
+        let events = [make_event(1, day, start, 60, "Room1")];
 
         let timetables = allocate(&events).unwrap();
 
@@ -367,7 +361,7 @@ mod tests {
         let day = NaiveDate::from_ymd_opt(2024, 2, 3).unwrap();
         let start = NaiveTime::from_hms_opt(10, 0, 0).unwrap();
 
-        let events = vec![make_event(1, day, start, 60, "Room1")];
+        let events = [make_event(1, day, start, 60, "Room1")];
 
         let timetables = allocate(&events).unwrap();
 
@@ -415,10 +409,10 @@ mod tests {
     fn get_slot_coverage_for_event(timetable: &Timetable, event_id: EventId) -> Option<usize> {
         for slot in &timetable.slots {
             for overlap in slot.overlaps.values() {
-                if let EventOverlap::Beginning { slot_coverage, .. } = overlap {
-                    if overlap.event().id == event_id {
-                        return Some(*slot_coverage);
-                    }
+                if let EventOverlap::Beginning { slot_coverage, .. } = overlap
+                    && overlap.event().id == event_id
+                {
+                    return Some(*slot_coverage);
                 }
             }
         }
@@ -431,7 +425,7 @@ mod tests {
         let day = NaiveDate::from_ymd_opt(2024, 2, 3).unwrap();
         let start = NaiveTime::from_hms_opt(10, 0, 0).unwrap();
 
-        let events = vec![make_event(1, day, start, 15, "Room1")];
+        let events = [make_event(1, day, start, 15, "Room1")];
         let event_refs: Vec<&Event> = events.iter().collect();
 
         let result = create_timetable_for_day(day, &event_refs, Duration::minutes(5));
@@ -449,7 +443,7 @@ mod tests {
         let day = NaiveDate::from_ymd_opt(2024, 2, 3).unwrap();
         let start = NaiveTime::from_hms_opt(10, 0, 0).unwrap();
 
-        let events = vec![make_event(1, day, start, 30, "Room1")];
+        let events = [make_event(1, day, start, 30, "Room1")];
         let event_refs: Vec<&Event> = events.iter().collect();
 
         let result = create_timetable_for_day(day, &event_refs, Duration::minutes(15));
@@ -467,7 +461,7 @@ mod tests {
         let day = NaiveDate::from_ymd_opt(2024, 2, 3).unwrap();
         let start = NaiveTime::from_hms_opt(10, 0, 0).unwrap();
 
-        let events = vec![make_event(1, day, start, 60, "Room1")];
+        let events = [make_event(1, day, start, 60, "Room1")];
         let event_refs: Vec<&Event> = events.iter().collect();
 
         let result = create_timetable_for_day(day, &event_refs, Duration::minutes(15));
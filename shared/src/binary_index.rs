@@ -0,0 +1,406 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use memmap2::Mmap;
+
+use crate::error::QueryError;
+use crate::model::{EventId, OpenAIVector};
+
+/// Identifies an `embeddings.bin` file. All numbers in the file are little-endian:
+///
+/// | what          | size                                               |
+/// |---------------|----------------------------------------------------|
+/// | magic         | 8 bytes                                            |
+/// | version       | u32                                                |
+/// | encoding      | u32, 0 for f32 and 1 for int8                      |
+/// | count         | u32                                                |
+/// | dimensions    | u32                                                |
+/// | source        | 64 bytes, hex sha256 of its embeddings.json.gz     |
+/// | event table   | `count` pairs of u32, the year and id of each event |
+/// | scales        | `count` f32s, only present for int8                |
+/// | vectors       | `count * dimensions` f32s or i8s, one row per event |
+pub const MAGIC: &[u8; 8] = b"FOSDEMVX";
+pub const VERSION: u32 = 2;
+const SOURCE_LEN: usize = 64;
+const HEADER_LEN: usize = MAGIC.len() + 4 * 4 + SOURCE_LEN;
+
+/// How the components of each vector are stored
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VectorEncoding {
+    /// 4 bytes per component; plenty of precision for comparing embeddings
+    F32,
+    /// 1 byte per component, scaled per vector; a quarter of the size, slightly less precise
+    Int8,
+}
+
+impl VectorEncoding {
+    fn code(&self) -> u32 {
+        match self {
+            VectorEncoding::F32 => 0,
+            VectorEncoding::Int8 => 1,
+        }
+    }
+
+    fn from_code(code: u32) -> Option<VectorEncoding> {
+        match code {
+            0 => Some(VectorEncoding::F32),
+            1 => Some(VectorEncoding::Int8),
+            _ => None,
+        }
+    }
+
+    fn component_len(&self) -> usize {
+        match self {
+            VectorEncoding::F32 => 4,
+            VectorEncoding::Int8 => 1,
+        }
+    }
+}
+
+/// Write embeddings to `path` in the binary format, along with `source`, the checksum of the file
+/// they were read from. The file is written alongside and then renamed into place, so a server
+/// with the old file mapped never sees it change underneath it.
+pub fn write_binary_index(
+    path: &Path,
+    encoding: VectorEncoding,
+    source: &str,
+    embeddings: &[(EventId, &OpenAIVector)],
+) -> std::io::Result<()> {
+    if source.len() != SOURCE_LEN {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("source {:?} is not a hex sha256", source),
+        ));
+    }
+    let dimensions = embeddings.first().map_or(0, |(_, v)| v.len());
+    if let Some((event_id, v)) = embeddings.iter().find(|(_, v)| v.len() != dimensions) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!(
+                "embedding for {} has {} dimensions, expected {}",
+                event_id,
+                v.len(),
+                dimensions
+            ),
+        ));
+    }
+
+    let temp_path = path.with_extension("bin.tmp");
+    let mut writer = BufWriter::new(File::create(&temp_path)?);
+    writer.write_all(MAGIC)?;
+    for n in [
+        VERSION,
+        encoding.code(),
+        embeddings.len() as u32,
+        dimensions as u32,
+    ] {
+        writer.write_all(&n.to_le_bytes())?;
+    }
+    writer.write_all(source.as_bytes())?;
+    for (event_id, _) in embeddings {
+        writer.write_all(&event_id.year().to_le_bytes())?;
+        writer.write_all(&event_id.event_in_year().to_le_bytes())?;
+    }
+    match encoding {
+        VectorEncoding::F32 => {
+            for (_, v) in embeddings {
+                for x in v.iter() {
+                    writer.write_all(&(*x as f32).to_le_bytes())?;
+                }
+            }
+        }
+        VectorEncoding::Int8 => {
+            let scales: Vec<f32> = embeddings.iter().map(|(_, v)| int8_scale(v)).collect();
+            for scale in &scales {
+                writer.write_all(&scale.to_le_bytes())?;
+            }
+            for ((_, v), scale) in embeddings.iter().zip(&scales) {
+                let quantised: Vec<u8> = v
+                    .iter()
+                    .map(|x| ((*x as f32 / scale).round().clamp(-127.0, 127.0) as i8) as u8)
+                    .collect();
+                writer.write_all(&quantised)?;
+            }
+        }
+    }
+    writer.flush()?;
+    drop(writer);
+    std::fs::rename(temp_path, path)
+}
+
+/// the scale which maps the largest component of `v` onto 127
+fn int8_scale(v: &OpenAIVector) -> f32 {
+    let max = v.iter().fold(0.0f64, |max, x| max.max(x.abs())) as f32;
+    if max > 0.0 {
+        max / 127.0
+    } else {
+        1.0
+    }
+}
+
+/// Embeddings read in place from a memory-mapped `embeddings.bin`, so only the pages which are
+/// used are read in, and they can be dropped again by the OS under memory pressure
+#[derive(Debug)]
+pub struct BinaryIndex {
+    mmap: Mmap,
+    encoding: VectorEncoding,
+    dimensions: usize,
+    source: String,
+    event_ids: Vec<EventId>,
+    scales: Vec<f32>,
+    vectors_offset: usize,
+}
+
+impl BinaryIndex {
+    pub fn open(path: &Path) -> Result<BinaryIndex, QueryError> {
+        let file = File::open(path)?;
+        // SAFETY: the mapping is only valid while the file is unchanged. The pipeline only ever
+        // replaces the file by renaming a new one over it (see `write_binary_index`), which
+        // leaves the mapped file intact.
+        let mmap = unsafe { Mmap::map(&file)? };
+        Self::from_mmap(mmap)
+    }
+
+    fn from_mmap(mmap: Mmap) -> Result<BinaryIndex, QueryError> {
+        let corrupt =
+            |reason: &str| QueryError::IndexCorrupt(format!("embeddings.bin: {}", reason));
+        if mmap.len() < HEADER_LEN || &mmap[..MAGIC.len()] != MAGIC {
+            return Err(corrupt("not a binary index"));
+        }
+        let header: Vec<u32> = mmap[MAGIC.len()..HEADER_LEN - SOURCE_LEN]
+            .chunks_exact(4)
+            .map(read_u32)
+            .collect();
+        let [version, encoding, count, dimensions] = header[..] else {
+            unreachable!("header is four u32s");
+        };
+        if version != VERSION {
+            return Err(corrupt(&format!(
+                "version {} is not supported, expected {}; rebuild it with build_binary_index",
+                version, VERSION
+            )));
+        }
+        let source = String::from_utf8(mmap[HEADER_LEN - SOURCE_LEN..HEADER_LEN].to_vec())
+            .map_err(|_| corrupt("source is not a checksum"))?;
+        let encoding = VectorEncoding::from_code(encoding)
+            .ok_or_else(|| corrupt(&format!("unknown encoding {}", encoding)))?;
+        let (count, dimensions) = (count as usize, dimensions as usize);
+
+        let event_table_len = count * 8;
+        let scales_len = match encoding {
+            VectorEncoding::F32 => 0,
+            VectorEncoding::Int8 => count * 4,
+        };
+        let vectors_offset = HEADER_LEN + event_table_len + scales_len;
+        let expected_len = vectors_offset + count * dimensions * encoding.component_len();
+        if mmap.len() != expected_len {
+            return Err(corrupt(&format!(
+                "expected {} bytes for {} vectors of {} dimensions, found {}",
+                expected_len,
+                count,
+                dimensions,
+                mmap.len()
+            )));
+        }
+
+        let event_ids = mmap[HEADER_LEN..HEADER_LEN + event_table_len]
+            .chunks_exact(8)
+            .map(|pair| EventId::new(read_u32(&pair[..4]), read_u32(&pair[4..])))
+            .collect();
+        let scales = mmap[HEADER_LEN + event_table_len..vectors_offset]
+            .chunks_exact(4)
+            .map(read_f32)
+            .collect();
+
+        Ok(BinaryIndex {
+            mmap,
+            encoding,
+            dimensions,
+            source,
+            event_ids,
+            scales,
+            vectors_offset,
+        })
+    }
+
+    pub fn encoding(&self) -> VectorEncoding {
+        self.encoding
+    }
+
+    pub fn dimensions(&self) -> usize {
+        self.dimensions
+    }
+
+    /// The hex sha256 of the embeddings.json.gz this was built from
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// the event each row is for, in row order
+    pub fn event_ids(&self) -> &[EventId] {
+        &self.event_ids
+    }
+
+    fn row_bytes(&self, row: usize) -> &[u8] {
+        let row_len = self.dimensions * self.encoding.component_len();
+        let start = self.vectors_offset + row * row_len;
+        &self.mmap[start..start + row_len]
+    }
+
+    fn scale(&self, row: usize) -> f32 {
+        self.scales.get(row).copied().unwrap_or(1.0)
+    }
+
+    pub fn vector(&self, row: usize) -> OpenAIVector {
+        let bytes = self.row_bytes(row);
+        match self.encoding {
+            VectorEncoding::F32 => OpenAIVector::from_iterator(
+                self.dimensions,
+                bytes.chunks_exact(4).map(|b| read_f32(b) as f64),
+            ),
+            VectorEncoding::Int8 => {
+                let scale = self.scale(row);
+                OpenAIVector::from_iterator(
+                    self.dimensions,
+                    bytes.iter().map(|b| (*b as i8 as f32 * scale) as f64),
+                )
+            }
+        }
+    }
+
+    /// Euclidean distance between a row and some other vector
+    pub fn distance_to(&self, row: usize, other: &OpenAIVector) -> f64 {
+        let bytes = self.row_bytes(row);
+        let other = other.as_slice().iter().copied();
+        match self.encoding {
+            VectorEncoding::F32 => {
+                euclidean(bytes.chunks_exact(4).map(|b| read_f32(b) as f64), other)
+            }
+            VectorEncoding::Int8 => {
+                let scale = self.scale(row) as f64;
+                euclidean(bytes.iter().map(|b| *b as i8 as f64 * scale), other)
+            }
+        }
+    }
+
+    /// Euclidean distance between two rows
+    pub fn distance_between(&self, a: usize, b: usize) -> f64 {
+        let (a_bytes, b_bytes) = (self.row_bytes(a), self.row_bytes(b));
+        match self.encoding {
+            VectorEncoding::F32 => euclidean(
+                a_bytes.chunks_exact(4).map(|b| read_f32(b) as f64),
+                b_bytes.chunks_exact(4).map(|b| read_f32(b) as f64),
+            ),
+            VectorEncoding::Int8 => {
+                let (a_scale, b_scale) = (self.scale(a) as f64, self.scale(b) as f64);
+                euclidean(
+                    a_bytes.iter().map(|b| *b as i8 as f64 * a_scale),
+                    b_bytes.iter().map(|b| *b as i8 as f64 * b_scale),
+                )
+            }
+        }
+    }
+}
+
+fn euclidean(lhs: impl Iterator<Item = f64>, rhs: impl Iterator<Item = f64>) -> f64 {
+    lhs.zip(rhs)
+        .map(|(l, r)| (l - r) * (l - r))
+        .sum::<f64>()
+        .sqrt()
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes(bytes.try_into().expect("4 bytes"))
+}
+
+fn read_f32(bytes: &[u8]) -> f32 {
+    f32::from_le_bytes(bytes.try_into().expect("4 bytes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    const SOURCE: &str = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+
+    /// the index is only valid as long as the dir is kept
+    fn write_and_open(
+        encoding: VectorEncoding,
+        embeddings: &[(EventId, OpenAIVector)],
+    ) -> (TempDir, BinaryIndex) {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("embeddings.bin");
+        let entries: Vec<(EventId, &OpenAIVector)> =
+            embeddings.iter().map(|(id, v)| (*id, v)).collect();
+        write_binary_index(&path, encoding, SOURCE, &entries).unwrap();
+        let index = BinaryIndex::open(&path).unwrap();
+        (dir, index)
+    }
+
+    fn embeddings() -> Vec<(EventId, OpenAIVector)> {
+        vec![
+            (
+                EventId::new(2025, 7),
+                OpenAIVector::from(vec![0.6, -0.8, 0.0]),
+            ),
+            (
+                EventId::new(2026, 1),
+                OpenAIVector::from(vec![0.0, 0.1, -0.25]),
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_f32_round_trip() {
+        let embeddings = embeddings();
+
+        let (_dir, index) = write_and_open(VectorEncoding::F32, &embeddings);
+
+        assert_eq!(
+            vec![EventId::new(2025, 7), EventId::new(2026, 1)],
+            index.event_ids()
+        );
+        assert_eq!(3, index.dimensions());
+        assert_eq!(SOURCE, index.source());
+        for (row, (_, v)) in embeddings.iter().enumerate() {
+            assert!((index.vector(row) - v).norm() < 1e-6);
+        }
+        let expected = embeddings[0].1.metric_distance(&embeddings[1].1);
+        assert!((index.distance_between(0, 1) - expected).abs() < 1e-6);
+        assert!((index.distance_to(0, &embeddings[1].1) - expected).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_int8_is_close_to_original() {
+        let embeddings = embeddings();
+
+        let (_dir, index) = write_and_open(VectorEncoding::Int8, &embeddings);
+
+        assert_eq!(VectorEncoding::Int8, index.encoding());
+        for (row, (_, v)) in embeddings.iter().enumerate() {
+            // at most half a step of the scale out, per component
+            let max = v.iter().fold(0.0f64, |max, x| max.max(x.abs()));
+            assert!((index.vector(row) - v).amax() <= max / 127.0 / 2.0 + 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_truncated_file_is_corrupt() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("embeddings.bin");
+        let embeddings = embeddings();
+        let entries: Vec<(EventId, &OpenAIVector)> =
+            embeddings.iter().map(|(id, v)| (*id, v)).collect();
+        write_binary_index(&path, VectorEncoding::F32, SOURCE, &entries).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::write(&path, &bytes[..bytes.len() - 1]).unwrap();
+
+        assert!(matches!(
+            BinaryIndex::open(&path),
+            Err(QueryError::IndexCorrupt(_))
+        ));
+    }
+}
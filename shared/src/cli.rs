@@ -1,5 +1,11 @@
+use clap::ValueEnum;
 use indicatif::{ProgressBar, ProgressState, ProgressStyle};
 use std::fmt::Write;
+use std::time::Duration;
+use url::Url;
+
+use crate::embedding::{EmbeddingProviderConfig, EmbeddingProviderKind};
+use crate::nearest::{HnswConfig, IndexConfig};
 
 pub fn progress_bar(total_size: u64) -> ProgressBar {
     use indicatif::HumanDuration;
@@ -16,3 +22,125 @@ pub fn progress_bar(total_size: u64) -> ProgressBar {
     );
     bar
 }
+
+/// Options for choosing an `EmbeddingProvider`, for use with `#[command(flatten)]`
+#[derive(clap::Args, Debug, Clone)]
+pub struct EmbeddingProviderArgs {
+    /// which embedding provider to use
+    #[arg(long, value_enum, default_value_t = EmbeddingProviderKind::OpenAI)]
+    pub embedding_provider: EmbeddingProviderKind,
+
+    /// server base url for an `openai-compatible` provider e.g. http://localhost:11434/v1
+    #[arg(long)]
+    pub embedding_base_url: Option<Url>,
+
+    /// model to ask for, when using an `openai-compatible` provider
+    #[arg(long)]
+    pub embedding_model: Option<String>,
+}
+
+impl EmbeddingProviderArgs {
+    pub fn config(&self, timeout: Option<Duration>) -> EmbeddingProviderConfig {
+        EmbeddingProviderConfig {
+            kind: self.embedding_provider,
+            base_url: self.embedding_base_url.clone(),
+            model: self.embedding_model.clone(),
+            timeout,
+        }
+    }
+
+    /// These options as they would be given on the command line, to pass on to another binary
+    pub fn to_args(&self) -> Vec<String> {
+        let mut args = vec![
+            "--embedding-provider".to_string(),
+            self.embedding_provider
+                .to_possible_value()
+                .expect("all providers can be chosen")
+                .get_name()
+                .to_string(),
+        ];
+        if let Some(base_url) = &self.embedding_base_url {
+            args.extend(["--embedding-base-url".to_string(), base_url.to_string()]);
+        }
+        if let Some(model) = &self.embedding_model {
+            args.extend(["--embedding-model".to_string(), model.clone()]);
+        }
+        args
+    }
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NearestNeighbourKind {
+    /// exact, compares against every event
+    BruteForce,
+    /// approximate, using an HNSW graph built at load time
+    Hnsw,
+}
+
+/// Options for choosing how nearest neighbours are found, for use with `#[command(flatten)]`
+#[derive(clap::Args, Debug, Clone)]
+pub struct NearestNeighbourArgs {
+    /// how to find nearest neighbours
+    #[arg(long, value_enum, default_value_t = NearestNeighbourKind::Hnsw)]
+    pub nearest_neighbours: NearestNeighbourKind,
+
+    /// HNSW: size of candidate list when searching; higher gives better recall, but is slower
+    #[arg(long, default_value_t = HnswConfig::default().ef_search)]
+    pub hnsw_ef_search: usize,
+
+    /// HNSW: size of candidate list when building; higher gives a better graph, but is slower
+    #[arg(long, default_value_t = HnswConfig::default().ef_construction)]
+    pub hnsw_ef_construction: usize,
+
+    /// HNSW: max neighbours per node
+    #[arg(long, default_value_t = HnswConfig::default().m)]
+    pub hnsw_m: usize,
+}
+
+impl NearestNeighbourArgs {
+    pub fn config(&self) -> IndexConfig {
+        match self.nearest_neighbours {
+            NearestNeighbourKind::BruteForce => IndexConfig::BruteForce,
+            NearestNeighbourKind::Hnsw => IndexConfig::Hnsw(HnswConfig {
+                m: self.hnsw_m,
+                ef_construction: self.hnsw_ef_construction,
+                ef_search: self.hnsw_ef_search,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    #[derive(Parser)]
+    struct Args {
+        #[command(flatten)]
+        embedding: EmbeddingProviderArgs,
+    }
+
+    #[test]
+    fn test_embedding_args_can_be_passed_on() {
+        let args = Args::parse_from([
+            "test",
+            "--embedding-provider",
+            "openai-compatible",
+            "--embedding-base-url",
+            "http://localhost:11434/v1",
+            "--embedding-model",
+            "nomic-embed-text",
+        ]);
+
+        let passed_on =
+            Args::parse_from(std::iter::once("test".to_string()).chain(args.embedding.to_args()));
+
+        assert_eq!(args.embedding.to_args(), passed_on.embedding.to_args());
+        assert_eq!(
+            EmbeddingProviderKind::OpenAICompatible,
+            passed_on.embedding.embedding_provider
+        );
+    }
+}
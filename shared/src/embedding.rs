@@ -0,0 +1,126 @@
+use std::time::Duration;
+
+use tracing::info;
+use url::Url;
+
+use crate::env::load_secret;
+use crate::model::OpenAIVector;
+use crate::offline::OfflineEmbeddingProvider;
+use crate::openai::{OpenAICompatibleEmbeddingProvider, OpenAIEmbeddingProvider};
+
+/// Something which can turn text into an embedding vector. Both indexing (in `content`) and
+/// querying (in `InMemoryOpenAIQueryable`) go through this, so they must agree on the `model`.
+#[allow(async_fn_in_trait)]
+pub trait EmbeddingProvider {
+    fn model(&self) -> &str;
+
+    async fn embed(&self, input: &str) -> Result<OpenAIVector, Box<dyn std::error::Error>>;
+
+    /// An embedding for each of `inputs`, in the same order. Providers which can embed many
+    /// inputs in one request should override this.
+    async fn embed_batch(
+        &self,
+        inputs: &[String],
+    ) -> Result<Vec<OpenAIVector>, Box<dyn std::error::Error>> {
+        let mut embeddings = vec![];
+        for input in inputs {
+            embeddings.push(self.embed(input).await?);
+        }
+        Ok(embeddings)
+    }
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmbeddingProviderKind {
+    /// the hosted OpenAI API, which needs `OPENAI_API_KEY`
+    #[value(name = "openai")]
+    OpenAI,
+    /// any server which speaks the OpenAI embeddings API, e.g. one running locally
+    #[value(name = "openai-compatible")]
+    OpenAICompatible,
+    /// deterministic embeddings computed locally, with no network access
+    Offline,
+}
+
+#[derive(Debug, Clone)]
+pub struct EmbeddingProviderConfig {
+    pub kind: EmbeddingProviderKind,
+    pub base_url: Option<Url>,
+    pub model: Option<String>,
+    pub timeout: Option<Duration>,
+}
+
+impl EmbeddingProviderConfig {
+    pub fn build(&self) -> Result<AnyEmbeddingProvider, Box<dyn std::error::Error>> {
+        info!("Using {:?} embedding provider", self.kind);
+        match self.kind {
+            EmbeddingProviderKind::OpenAI => {
+                let api_key = load_secret("OPENAI_API_KEY")?;
+                Ok(AnyEmbeddingProvider::OpenAI(OpenAIEmbeddingProvider::new(
+                    &api_key,
+                    self.timeout,
+                )?))
+            }
+            EmbeddingProviderKind::OpenAICompatible => {
+                let base_url = self
+                    .base_url
+                    .as_ref()
+                    .ok_or("an OpenAI-compatible provider needs a base url")?;
+                let model = self
+                    .model
+                    .as_ref()
+                    .ok_or("an OpenAI-compatible provider needs a model")?;
+                // local servers usually don't check this, so it is optional here
+                let api_key = std::env::var("OPENAI_API_KEY").unwrap_or_default();
+                Ok(AnyEmbeddingProvider::OpenAICompatible(
+                    OpenAICompatibleEmbeddingProvider::new(
+                        base_url,
+                        model,
+                        &api_key,
+                        self.timeout,
+                    )?,
+                ))
+            }
+            EmbeddingProviderKind::Offline => Ok(AnyEmbeddingProvider::Offline(
+                OfflineEmbeddingProvider::default(),
+            )),
+        }
+    }
+}
+
+/// All the providers we know about, so that the choice can be made at runtime
+#[derive(Debug)]
+pub enum AnyEmbeddingProvider {
+    OpenAI(OpenAIEmbeddingProvider),
+    OpenAICompatible(OpenAICompatibleEmbeddingProvider),
+    Offline(OfflineEmbeddingProvider),
+}
+
+impl EmbeddingProvider for AnyEmbeddingProvider {
+    fn model(&self) -> &str {
+        match self {
+            AnyEmbeddingProvider::OpenAI(p) => p.model(),
+            AnyEmbeddingProvider::OpenAICompatible(p) => p.model(),
+            AnyEmbeddingProvider::Offline(p) => p.model(),
+        }
+    }
+
+    async fn embed(&self, input: &str) -> Result<OpenAIVector, Box<dyn std::error::Error>> {
+        match self {
+            AnyEmbeddingProvider::OpenAI(p) => p.embed(input).await,
+            AnyEmbeddingProvider::OpenAICompatible(p) => p.embed(input).await,
+            AnyEmbeddingProvider::Offline(p) => p.embed(input).await,
+        }
+    }
+
+    async fn embed_batch(
+        &self,
+        inputs: &[String],
+    ) -> Result<Vec<OpenAIVector>, Box<dyn std::error::Error>> {
+        match self {
+            AnyEmbeddingProvider::OpenAI(p) => p.embed_batch(inputs).await,
+            AnyEmbeddingProvider::OpenAICompatible(p) => p.embed_batch(inputs).await,
+            AnyEmbeddingProvider::Offline(p) => p.embed_batch(inputs).await,
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use std::time::Duration;
+
+use thiserror::Error;
+
+use crate::model::EventId;
+
+/// Everything that can go wrong when loading or querying events. Unlike
+/// `Box<dyn std::error::Error>` this is `Send + Sync`, so it can be held across an `.await`.
+#[derive(Debug, Error)]
+pub enum QueryError {
+    #[error("Event not found: {0}")]
+    NotFound(EventId),
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+    #[error("Embedding unavailable: {0}")]
+    EmbeddingUnavailable(String),
+    #[error("Index corrupt: {0}")]
+    IndexCorrupt(String),
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// A request to an OpenAI-compatible embeddings endpoint which didn't succeed
+#[derive(Debug, Error)]
+pub enum EmbeddingRequestError {
+    /// rate limited (429), a server error (5xx), or no response at all, any of which may succeed
+    /// if tried again later, but not before `retry_after` if the server said when
+    #[error("Embedding request failed, but may be retried: {message}")]
+    Retryable {
+        status: Option<u16>,
+        retry_after: Option<Duration>,
+        message: String,
+    },
+    #[error("Embedding request failed with {status}: {message}")]
+    Failed { status: u16, message: String },
+}
+
+impl EmbeddingRequestError {
+    pub fn is_rate_limited(&self) -> bool {
+        matches!(
+            self,
+            EmbeddingRequestError::Retryable {
+                status: Some(429),
+                ..
+            }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_query_error_is_send_and_sync() {
+        assert_send_sync::<QueryError>();
+    }
+}
@@ -0,0 +1,233 @@
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::QueryError;
+use crate::filter::SearchFilter;
+use crate::model::EventId;
+use crate::queryable::{Queryable, SearchMode};
+
+/// how many results of each search are scored, unless asked otherwise
+pub const DEFAULT_CUTOFF: u8 = 10;
+
+/// A query, and the events which a good search should find for it
+#[derive(Debug, Clone, PartialEq)]
+pub struct GoldenQuery {
+    pub query: String,
+    /// only search within this year, or across all years if None
+    pub year: Option<u32>,
+    pub relevant: Vec<EventId>,
+}
+
+/// as stored, with event ids in their `Display` form, e.g. `2026-8816`
+#[derive(Deserialize)]
+struct StoredGoldenQuery {
+    query: String,
+    #[serde(default)]
+    year: Option<u32>,
+    relevant: Vec<String>,
+}
+
+pub fn load_golden_queries(path: &Path) -> io::Result<Vec<GoldenQuery>> {
+    let stored: Vec<StoredGoldenQuery> =
+        serde_json::from_reader(BufReader::new(File::open(path)?))?;
+    stored
+        .into_iter()
+        .map(|stored| {
+            let relevant = stored
+                .relevant
+                .iter()
+                .map(|event_id| event_id.parse())
+                .collect::<Result<Vec<EventId>, String>>()
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            Ok(GoldenQuery {
+                query: stored.query,
+                year: stored.year,
+                relevant,
+            })
+        })
+        .collect()
+}
+
+/// The share of `relevant` events in the first `k` of `ranked`
+pub fn recall_at_k(ranked: &[EventId], relevant: &[EventId], k: usize) -> f64 {
+    if relevant.is_empty() {
+        return 0.0;
+    }
+    let found = ranked
+        .iter()
+        .take(k)
+        .filter(|event_id| relevant.contains(event_id))
+        .count();
+    found as f64 / relevant.len() as f64
+}
+
+/// 1 / the rank of the first relevant event in `ranked`, or 0.0 if there isn't one
+pub fn reciprocal_rank(ranked: &[EventId], relevant: &[EventId]) -> f64 {
+    ranked
+        .iter()
+        .position(|event_id| relevant.contains(event_id))
+        .map_or(0.0, |position| 1.0 / (position + 1) as f64)
+}
+
+/// Normalised discounted cumulative gain of the first `k` of `ranked`, where every relevant event
+/// is equally relevant: 1.0 when the relevant events are all ranked first, falling the further
+/// down they are
+pub fn ndcg_at_k(ranked: &[EventId], relevant: &[EventId], k: usize) -> f64 {
+    let discount = |position: usize| 1.0 / (position as f64 + 2.0).log2();
+    let ideal: f64 = (0..relevant.len().min(k)).map(discount).sum();
+    if ideal == 0.0 {
+        return 0.0;
+    }
+    let actual: f64 = ranked
+        .iter()
+        .take(k)
+        .enumerate()
+        .filter(|(_, event_id)| relevant.contains(event_id))
+        .map(|(position, _)| discount(position))
+        .fold(0.0, |sum, gain| sum + gain);
+    actual / ideal
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, PartialEq)]
+pub struct Scores {
+    pub recall: f64,
+    pub reciprocal_rank: f64,
+    pub ndcg: f64,
+}
+
+impl Scores {
+    pub fn of(ranked: &[EventId], relevant: &[EventId], k: usize) -> Scores {
+        Scores {
+            recall: recall_at_k(ranked, relevant, k),
+            reciprocal_rank: reciprocal_rank(&ranked[..k.min(ranked.len())], relevant),
+            ndcg: ndcg_at_k(ranked, relevant, k),
+        }
+    }
+
+    /// e.g. the mean of `reciprocal_rank` is the MRR
+    pub fn mean(scores: &[Scores]) -> Scores {
+        if scores.is_empty() {
+            return Scores::default();
+        }
+        let n = scores.len() as f64;
+        Scores {
+            recall: scores.iter().map(|s| s.recall).sum::<f64>() / n,
+            reciprocal_rank: scores.iter().map(|s| s.reciprocal_rank).sum::<f64>() / n,
+            ndcg: scores.iter().map(|s| s.ndcg).sum::<f64>() / n,
+        }
+    }
+
+    /// How much better (positive) or worse (negative) these are than `baseline`
+    pub fn change_from(&self, baseline: &Scores) -> Scores {
+        Scores {
+            recall: self.recall - baseline.recall,
+            reciprocal_rank: self.reciprocal_rank - baseline.reciprocal_rank,
+            ndcg: self.ndcg - baseline.ndcg,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryEvaluation {
+    pub query: String,
+    pub year: Option<u32>,
+    /// what the search found, best first
+    pub found: Vec<EventId>,
+    /// relevant events which weren't found
+    pub missed: Vec<EventId>,
+    pub scores: Scores,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Evaluation {
+    pub mode: SearchMode,
+    /// how many results of each search were scored
+    pub k: u8,
+    pub queries: Vec<QueryEvaluation>,
+    pub mean: Scores,
+}
+
+/// Run each of `golden` against `queryable`, scoring the first `k` results
+pub async fn evaluate<Q: Queryable>(
+    queryable: &Q,
+    golden: &[GoldenQuery],
+    k: u8,
+    mode: SearchMode,
+    mmr_lambda: f64,
+) -> Result<Evaluation, QueryError> {
+    let mut queries = vec![];
+    for golden_query in golden {
+        let results = queryable
+            .search(
+                &golden_query.query,
+                k,
+                false,
+                &SearchFilter::for_year(golden_query.year),
+                mode,
+                mmr_lambda,
+            )
+            .await?;
+        let found: Vec<EventId> = results.items.iter().map(|item| item.event.id).collect();
+        let missed = golden_query
+            .relevant
+            .iter()
+            .filter(|event_id| !found.contains(event_id))
+            .copied()
+            .collect();
+        queries.push(QueryEvaluation {
+            query: golden_query.query.clone(),
+            year: golden_query.year,
+            scores: Scores::of(&found, &golden_query.relevant, k as usize),
+            found,
+            missed,
+        });
+    }
+    let mean = Scores::mean(&queries.iter().map(|q| q.scores).collect::<Vec<_>>());
+    Ok(Evaluation {
+        mode,
+        k,
+        queries,
+        mean,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(ids: &[u32]) -> Vec<EventId> {
+        ids.iter().map(|id| EventId::new(2026, *id)).collect()
+    }
+
+    #[test]
+    fn test_recall_only_counts_the_first_k() {
+        let ranked = ids(&[1, 2, 3, 4]);
+
+        assert_eq!(0.5, recall_at_k(&ranked, &ids(&[2, 4]), 2));
+        assert_eq!(1.0, recall_at_k(&ranked, &ids(&[2, 4]), 4));
+        assert_eq!(0.0, recall_at_k(&ranked, &[], 4));
+    }
+
+    #[test]
+    fn test_reciprocal_rank_is_of_the_first_relevant_event() {
+        let ranked = ids(&[1, 2, 3, 4]);
+
+        assert_eq!(1.0, reciprocal_rank(&ranked, &ids(&[1, 3])));
+        assert_eq!(1.0 / 3.0, reciprocal_rank(&ranked, &ids(&[3, 4])));
+        assert_eq!(0.0, reciprocal_rank(&ranked, &ids(&[5])));
+    }
+
+    #[test]
+    fn test_ndcg_is_one_only_when_relevant_events_are_first() {
+        let relevant = ids(&[1, 2]);
+
+        assert_eq!(1.0, ndcg_at_k(&ids(&[1, 2, 3]), &relevant, 3));
+        assert_eq!(1.0, ndcg_at_k(&ids(&[2, 1, 3]), &relevant, 3));
+        let lower = ndcg_at_k(&ids(&[3, 1, 2]), &relevant, 3);
+        assert!(0.0 < lower && lower < 1.0, "{}", lower);
+        assert_eq!(0.0, ndcg_at_k(&ids(&[3, 1, 2]), &relevant, 1));
+    }
+}
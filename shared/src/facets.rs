@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use chrono::{Datelike, Weekday};
+
+use crate::model::{Event, EventType};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FacetCount<T> {
+    pub value: T,
+    pub count: usize,
+}
+
+/// How many of a set of events have each year, day, track etc, so that a search can be refined
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Facets {
+    /// in year order
+    pub years: Vec<FacetCount<u32>>,
+    /// in day-of-week order
+    pub days: Vec<FacetCount<Weekday>>,
+    /// this, and the other lists of names, are most common first
+    pub tracks: Vec<FacetCount<String>>,
+    pub rooms: Vec<FacetCount<String>>,
+    pub presenters: Vec<FacetCount<String>>,
+    /// most common first
+    pub types: Vec<FacetCount<EventType>>,
+    pub languages: Vec<FacetCount<String>>,
+    pub with_video: usize,
+    pub with_slides: usize,
+}
+
+impl Facets {
+    pub fn from_events<'a, I>(events: I) -> Facets
+    where
+        I: IntoIterator<Item = &'a Event>,
+    {
+        let events: Vec<&Event> = events.into_iter().collect();
+
+        let mut years = counts(events.iter().map(|e| e.year));
+        years.sort_by_key(|c| c.value);
+        let mut days = counts(events.iter().map(|e| e.date.weekday()));
+        days.sort_by_key(|c| c.value.num_days_from_monday());
+        let mut types = counts(events.iter().map(|e| e.event_type));
+        types.sort_by(|a, b| b.count.cmp(&a.count).then(a.value.cmp(&b.value)));
+
+        Facets {
+            years,
+            days,
+            tracks: most_common_first(counts(events.iter().map(|e| e.track.clone()))),
+            rooms: most_common_first(counts(events.iter().map(|e| e.room.clone()))),
+            presenters: most_common_first(counts(
+                events
+                    .iter()
+                    .flat_map(|e| e.presenters.iter().map(|p| p.name.clone())),
+            )),
+            types,
+            languages: most_common_first(counts(events.iter().filter_map(|e| e.language.clone()))),
+            with_video: events.iter().filter(|e| e.has_video()).count(),
+            with_slides: events.iter().filter(|e| e.has_slides()).count(),
+        }
+    }
+}
+
+fn counts<T, I>(values: I) -> Vec<FacetCount<T>>
+where
+    T: Eq + Hash,
+    I: Iterator<Item = T>,
+{
+    let mut counts: HashMap<T, usize> = HashMap::new();
+    for value in values {
+        *counts.entry(value).or_insert(0) += 1;
+    }
+    counts
+        .into_iter()
+        .map(|(value, count)| FacetCount { value, count })
+        .collect()
+}
+
+fn most_common_first(mut counts: Vec<FacetCount<String>>) -> Vec<FacetCount<String>> {
+    counts.sort_by(|a, b| b.count.cmp(&a.count).then(a.value.cmp(&b.value)));
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+    use crate::model::{test_event, Event, EventId};
+
+    fn event(id: u32, date: NaiveDate, track: &str) -> Event {
+        Event {
+            date,
+            track: track.to_string(),
+            ..test_event(EventId::new(date.year() as u32, id), "Title")
+        }
+    }
+
+    #[test]
+    fn test_counts_are_ordered() {
+        let saturday = NaiveDate::from_ymd_opt(2025, 2, 1).unwrap();
+        let sunday = NaiveDate::from_ymd_opt(2025, 2, 2).unwrap();
+        let events = [
+            event(1, sunday, "Go"),
+            event(2, saturday, "Rust"),
+            event(3, sunday, "Rust"),
+        ];
+
+        let facets = Facets::from_events(&events);
+
+        assert_eq!(
+            vec![
+                FacetCount {
+                    value: Weekday::Sat,
+                    count: 1
+                },
+                FacetCount {
+                    value: Weekday::Sun,
+                    count: 2
+                }
+            ],
+            facets.days
+        );
+        assert_eq!(
+            vec!["Rust", "Go"],
+            facets
+                .tracks
+                .iter()
+                .map(|c| c.value.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![FacetCount {
+                value: EventType::Other,
+                count: 3
+            }],
+            facets.types
+        );
+        assert!(facets.languages.is_empty());
+        assert_eq!(0, facets.with_video);
+    }
+}
@@ -0,0 +1,145 @@
+use chrono::{Datelike, NaiveTime, Weekday};
+
+use crate::model::{Event, EventType};
+
+/// Restricts which events can be found. Each field is a separate constraint which must hold;
+/// within a list field, matching any one of the values is enough. Empty lists and `None` don't
+/// constrain anything, so `SearchFilter::default()` matches every event.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchFilter {
+    pub years: Vec<u32>,
+    pub days: Vec<Weekday>,
+    pub tracks: Vec<String>,
+    pub rooms: Vec<String>,
+    /// matched against presenter names, ignoring case
+    pub presenters: Vec<String>,
+    pub types: Vec<EventType>,
+    /// matched against the event's language, ignoring case, so events without one never match
+    pub languages: Vec<String>,
+    pub has_video: Option<bool>,
+    pub has_slides: Option<bool>,
+    /// events must start at or after this time of day
+    pub starts_after: Option<NaiveTime>,
+    /// events must end at or before this time of day
+    pub ends_before: Option<NaiveTime>,
+}
+
+impl SearchFilter {
+    pub fn for_year(year: Option<u32>) -> SearchFilter {
+        SearchFilter {
+            years: year.into_iter().collect(),
+            ..SearchFilter::default()
+        }
+    }
+
+    pub fn matches(&self, event: &Event) -> bool {
+        (self.years.is_empty() || self.years.contains(&event.year))
+            && (self.days.is_empty() || self.days.contains(&event.date.weekday()))
+            && (self.tracks.is_empty() || self.tracks.contains(&event.track))
+            && (self.rooms.is_empty() || self.rooms.contains(&event.room))
+            && (self.presenters.is_empty()
+                || event.presenters.iter().any(|p| {
+                    self.presenters
+                        .iter()
+                        .any(|name| name.eq_ignore_ascii_case(&p.name))
+                }))
+            && (self.types.is_empty() || self.types.contains(&event.event_type))
+            && (self.languages.is_empty()
+                || event.language.as_ref().is_some_and(|language| {
+                    self.languages
+                        .iter()
+                        .any(|l| l.eq_ignore_ascii_case(language))
+                }))
+            && self.has_video.is_none_or(|v| v == event.has_video())
+            && self.has_slides.is_none_or(|s| s == event.has_slides())
+            && self.starts_after.is_none_or(|t| event.start >= t)
+            && self
+                .ends_before
+                .is_none_or(|t| event.ending_time().time() <= t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use url::Url;
+
+    use super::*;
+    use crate::model::{test_event, EventId, Link, Person, PersonId};
+
+    fn sunday_rust_talk() -> Event {
+        Event {
+            date: NaiveDate::from_ymd_opt(2025, 2, 2).unwrap(),
+            room: "H.1302".to_string(),
+            track: "Rust".to_string(),
+            presenters: vec![Person {
+                id: PersonId::new(2025, 1),
+                name: "Jane Doe".to_string(),
+                global_id: None,
+            }],
+            links: vec![Link {
+                name: "Video recording (MP4)".to_string(),
+                url: Url::parse("https://video.fosdem.org/2025/talk.mp4").unwrap(),
+            }],
+            event_type: EventType::LightningTalk,
+            language: Some("en".to_string()),
+            room_slug: Some("h1302".to_string()),
+            track_slug: Some("rust".to_string()),
+            ..test_event(EventId::new(2025, 1), "Title")
+        }
+    }
+
+    #[test]
+    fn test_default_matches_everything() {
+        assert!(SearchFilter::default().matches(&sunday_rust_talk()));
+    }
+
+    #[test]
+    fn test_each_constraint_must_hold() {
+        let event = sunday_rust_talk();
+        let matching = SearchFilter {
+            years: vec![2024, 2025],
+            days: vec![Weekday::Sun],
+            tracks: vec!["Rust".to_string()],
+            rooms: vec!["H.1302".to_string()],
+            presenters: vec!["jane doe".to_string()],
+            types: vec![EventType::Keynote, EventType::LightningTalk],
+            languages: vec!["EN".to_string()],
+            has_video: Some(true),
+            has_slides: Some(false),
+            starts_after: NaiveTime::from_hms_opt(10, 0, 0),
+            ends_before: NaiveTime::from_hms_opt(10, 30, 0),
+        };
+        assert!(matching.matches(&event));
+
+        let not_matching = [
+            SearchFilter {
+                days: vec![Weekday::Sat],
+                ..matching.clone()
+            },
+            SearchFilter {
+                tracks: vec!["Go".to_string()],
+                ..matching.clone()
+            },
+            SearchFilter {
+                types: vec![EventType::Bof],
+                ..matching.clone()
+            },
+            SearchFilter {
+                languages: vec!["nl".to_string()],
+                ..matching.clone()
+            },
+            SearchFilter {
+                has_slides: Some(true),
+                ..matching.clone()
+            },
+            SearchFilter {
+                ends_before: NaiveTime::from_hms_opt(10, 15, 0),
+                ..matching.clone()
+            },
+        ];
+        for filter in not_matching {
+            assert!(!filter.matches(&event), "{:?}", filter);
+        }
+    }
+}
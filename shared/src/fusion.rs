@@ -0,0 +1,45 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// Dampens the influence of the very top ranks; 60 is the value from the original paper
+pub const RRF_K: f64 = 60.0;
+
+/// Reciprocal Rank Fusion: combine several rankings, best first, into one. Each item scores
+/// `1 / (RRF_K + rank)` for each ranking it appears in, so items ranked well by several rankings
+/// rise to the top. Returns items with their fused score, highest first.
+pub fn reciprocal_rank_fusion<T>(rankings: &[Vec<T>]) -> Vec<(T, f64)>
+where
+    T: Copy + Eq + Hash + Ord,
+{
+    let mut scores: HashMap<T, f64> = HashMap::new();
+    for ranking in rankings {
+        for (rank, item) in ranking.iter().enumerate() {
+            *scores.entry(*item).or_insert(0.0) += 1.0 / (RRF_K + (rank + 1) as f64);
+        }
+    }
+    let mut fused: Vec<(T, f64)> = scores.into_iter().collect();
+    fused.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+    fused
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_agreement_beats_single_top_rank() {
+        let fused = reciprocal_rank_fusion(&[vec![1, 2, 3], vec![2, 4, 5]]);
+
+        assert_eq!(2, fused[0].0);
+    }
+
+    #[test]
+    fn test_single_ranking_preserves_order() {
+        let fused = reciprocal_rank_fusion(&[vec![3, 1, 2]]);
+
+        assert_eq!(
+            vec![3, 1, 2],
+            fused.iter().map(|(i, _)| *i).collect::<Vec<_>>()
+        );
+    }
+}
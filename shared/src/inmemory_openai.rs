@@ -1,81 +1,163 @@
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
 
-use openai_dive::v1::api::Client;
+use tracing::{debug, error, span, warn};
 
-use tracing::{debug, span};
+use crate::binary_index::BinaryIndex;
+use crate::embedding::{AnyEmbeddingProvider, EmbeddingProvider};
+use crate::error::QueryError;
+use crate::facets::Facets;
+use crate::filter::SearchFilter;
+use crate::fusion::reciprocal_rank_fusion;
+use crate::lexical::{event_fields, LexicalIndex};
+use crate::manifest::{Manifest, ManifestPolicy};
+use crate::model::{Event, EventId, OpenAIVector, Passage, SearchItem};
+use crate::nearest::{IndexConfig, NearestNeighbourIndex};
+use crate::queryable::{Queryable, SearchMode, SearchResults};
+use crate::queryable::{FACETED_RESULTS, MAX_RELATED_EVENTS};
+use crate::rerank::{maximal_marginal_relevance, MMR_CANDIDATES_PER_RESULT};
 
-use crate::model::{Event, EventId, OpenAIEmbedding, OpenAIVector, SearchItem};
-use crate::queryable::Queryable;
-use crate::{openai::get_embedding, queryable::MAX_RELATED_EVENTS};
+/// in hybrid mode, how many candidates to take from each ranking, relative to the limit
+const HYBRID_CANDIDATES_PER_RESULT: usize = 3;
+
+/// how many passages to consider, relative to the limit, as several may be from the same event
+const PASSAGE_CANDIDATES_PER_RESULT: usize = 3;
+
+/// when recommending, how many candidates to take for each seed, relative to the limit
+const RECOMMEND_CANDIDATES_PER_RESULT: usize = 3;
 
 #[derive(Debug)]
-pub struct InMemoryOpenAIQueryable {
-    openai_client: Client,
-    events: Vec<EmbeddedEvent>,
+pub struct InMemoryOpenAIQueryable<P = AnyEmbeddingProvider> {
+    embedding_provider: P,
+    events: Vec<Event>,
+    embeddings: EventEmbeddings,
+    index: NearestNeighbourIndex,
+    lexical_index: LexicalIndex,
+    additional_text: HashMap<EventId, Vec<String>>,
+    passages: Vec<EmbeddedPassage>,
+    passage_index: NearestNeighbourIndex,
+    /// the indexes of the passages of each event
+    passages_for_event: Vec<Vec<usize>>,
+    manifest: Option<Manifest>,
 }
 
 fn distance(lhs: &OpenAIVector, rhs: &OpenAIVector) -> f64 {
-    lhs.metric_distance(rhs)
+    // equivalent to `lhs.metric_distance(rhs)`, but this is called a lot when building the
+    // index, and a plain loop over slices is much quicker in unoptimised builds
+    lhs.as_slice()
+        .iter()
+        .zip(rhs.as_slice())
+        .map(|(l, r)| (l - r) * (l - r))
+        .sum::<f64>()
+        .sqrt()
 }
 
+/// The embeddings of events, in the same order as the events
 #[derive(Debug)]
-struct EmbeddedEvent {
-    event: Event,
+enum EventEmbeddings {
+    /// parsed from `embeddings.json.gz`
+    Parsed(Vec<OpenAIVector>),
+    /// read in place from `embeddings.bin`, where the embedding of event `i` is at `rows[i]`
+    Mapped {
+        index: BinaryIndex,
+        rows: Vec<usize>,
+    },
+}
+
+impl EventEmbeddings {
+    fn distance_to(&self, i: usize, other: &OpenAIVector) -> f64 {
+        match self {
+            EventEmbeddings::Parsed(embeddings) => distance(&embeddings[i], other),
+            EventEmbeddings::Mapped { index, rows } => index.distance_to(rows[i], other),
+        }
+    }
+
+    fn dimensions(&self) -> Option<usize> {
+        match self {
+            EventEmbeddings::Parsed(embeddings) => embeddings.first().map(|e| e.len()),
+            EventEmbeddings::Mapped { index, .. } => Some(index.dimensions()),
+        }
+    }
+
+    fn distance_between(&self, a: usize, b: usize) -> f64 {
+        match self {
+            EventEmbeddings::Parsed(embeddings) => distance(&embeddings[a], &embeddings[b]),
+            EventEmbeddings::Mapped { index, rows } => index.distance_between(rows[a], rows[b]),
+        }
+    }
+}
+
+/// An event as ranked for a query: `distance` is what's shown for it, and `relevance`, from 0.0
+/// to 1.0, is how well it was ranked, which MMR balances against diversity
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Ranked {
+    /// index of the event
+    event: usize,
+    distance: f64,
+    relevance: f64,
+}
+
+impl Ranked {
+    /// for rankings by distance, where relevance is similarity, `1 - distance`
+    fn by_distance((event, distance): (usize, f64)) -> Ranked {
+        Ranked {
+            event,
+            distance,
+            relevance: 1.0 - distance,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct EmbeddedPassage {
+    passage: Passage,
+    /// index of the event the passage is from
+    event: usize,
     openai_embedding: OpenAIVector,
 }
 
-impl Queryable for InMemoryOpenAIQueryable {
+impl<P: EmbeddingProvider> Queryable for InMemoryOpenAIQueryable<P> {
     #[tracing::instrument(skip(self))]
-    async fn load_all_events(&self) -> Result<Vec<Event>, Box<dyn std::error::Error>> {
-        Ok(self.events.iter().map(|e| e.event.clone()).collect())
+    async fn load_all_events(&self) -> Result<Vec<Event>, QueryError> {
+        Ok(self.events.clone())
     }
 
     #[tracing::instrument(skip(self))]
-    async fn find_event_by_id(
-        &self,
-        event_id: EventId,
-    ) -> Result<Option<Event>, Box<dyn std::error::Error>> {
-        Ok(self
-            .events
-            .iter()
-            .find(|e| e.event.id == event_id)
-            .map(|e| e.event.clone()))
+    async fn find_event_by_id(&self, event_id: EventId) -> Result<Option<Event>, QueryError> {
+        Ok(self.events.iter().find(|e| e.id == event_id).cloned())
     }
 
     #[tracing::instrument(skip(self))]
     async fn find_related_events(
         &self,
-        title: &str,
+        event_id: EventId,
         limit: u8,
-        year_filter: Option<u32>,
-    ) -> Result<Vec<SearchItem>, Box<dyn std::error::Error>> {
-        debug!("Finding embedding for title");
-        let event = match self.events.iter().find(|e| e.event.title == *title) {
-            Some(e) => e,
-            None => return Err(format!("no embedding for \'{}\'", title).into()),
+        filter: &SearchFilter,
+        mmr_lambda: f64,
+    ) -> Result<Vec<SearchItem>, QueryError> {
+        validate_mmr_lambda(mmr_lambda)?;
+        debug!("Finding embedding for event");
+        let source = match self.events.iter().position(|e| e.id == event_id) {
+            Some(position) => position,
+            None => return Err(QueryError::NotFound(event_id)),
         };
 
-        debug!("Finding all distances from embedding");
-        let mut entries = vec![];
-        for embedded_event in &self.events {
-            if let Some(year) = year_filter {
-                if embedded_event.event.year != year {
-                    continue;
-                }
-            }
-            if embedded_event.event.id != event.event.id {
-                entries.push(SearchItem {
-                    event: embedded_event.event.clone(),
-                    distance: distance(&event.openai_embedding, &embedded_event.openai_embedding),
-                    related: None,
-                });
-            }
-        }
-        debug!("Limiting to the top {}", limit);
-        entries.sort_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap());
-        entries.truncate(limit as usize);
-
-        Ok(entries)
+        debug!("Finding nearest {} events to embedding", limit);
+        let ranking: Vec<Ranked> = self
+            .index
+            .search(
+                candidate_count(limit, mmr_lambda),
+                |i| self.embeddings.distance_between(source, i),
+                |i| i != source && filter.matches(&self.events[i]),
+            )
+            .into_iter()
+            .map(Ranked::by_distance)
+            .collect();
+        Ok(self
+            .diversify(&ranking, limit, mmr_lambda)
+            .into_iter()
+            .map(|r| self.item(r.event, r.distance))
+            .collect())
     }
 
     #[tracing::instrument(skip(self))]
@@ -84,29 +166,65 @@ impl Queryable for InMemoryOpenAIQueryable {
         query: &str,
         limit: u8,
         find_related: bool,
-        year_filter: Option<u32>,
-    ) -> Result<Vec<SearchItem>, Box<dyn std::error::Error>> {
-        debug!("Getting embedding for query");
-        let response = get_embedding(&self.openai_client, query).await?;
-        let embedding = OpenAIEmbedding::embedding_from_response(&response)?;
-
-        debug!("Finding all distances from embedding");
-        let mut entries = vec![];
-        for embedded_event in &self.events {
-            if let Some(year) = year_filter {
-                if embedded_event.event.year != year {
-                    continue;
-                }
-            }
-            entries.push(SearchItem {
-                event: embedded_event.event.clone(),
-                distance: distance(&embedding, &embedded_event.openai_embedding),
-                related: None,
-            });
+        filter: &SearchFilter,
+        mode: SearchMode,
+        mmr_lambda: f64,
+    ) -> Result<SearchResults, QueryError> {
+        if query.trim().is_empty() {
+            return Err(QueryError::InvalidInput("query is empty".to_string()));
+        }
+        if limit == 0 {
+            return Err(QueryError::InvalidInput(
+                "limit must be at least 1".to_string(),
+            ));
         }
-        debug!("Limiting to the top {}", limit);
-        entries.sort_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap());
-        entries.truncate(limit as usize);
+        validate_mmr_lambda(mmr_lambda)?;
+        let include = |i: usize| filter.matches(&self.events[i]);
+        // rank enough to count facets over, then diversify just the best of them
+        let candidates = candidate_count(limit, mmr_lambda);
+        let ranked = candidates.max(FACETED_RESULTS);
+        let (mut ranking, embedding) = match mode {
+            SearchMode::Lexical => {
+                debug!("Finding top {} events matching terms", ranked);
+                (self.lexical_ranking(query, ranked, include), None)
+            }
+            SearchMode::Semantic => {
+                debug!("Getting embedding for query");
+                let embedding = self.embed(query).await?;
+
+                debug!(
+                    "Finding nearest {} events and passages to embedding",
+                    ranked
+                );
+                (
+                    self.semantic_ranking(&embedding, ranked, include)
+                        .into_iter()
+                        .map(Ranked::by_distance)
+                        .collect(),
+                    Some(embedding),
+                )
+            }
+            SearchMode::Hybrid => {
+                debug!("Getting embedding for query");
+                let embedding = self.embed(query).await?;
+
+                debug!("Fusing nearest and matching events");
+                (
+                    self.hybrid_ranking(query, &embedding, ranked, include),
+                    Some(embedding),
+                )
+            }
+        };
+        let facets = Facets::from_events(ranking.iter().map(|r| &self.events[r.event]));
+        ranking.truncate(candidates);
+        let entries: Vec<SearchItem> = self
+            .diversify(&ranking, limit, mmr_lambda)
+            .into_iter()
+            .map(|r| match &embedding {
+                Some(embedding) => self.item_with_passage(r.event, embedding, r.distance),
+                None => self.item(r.event, r.distance),
+            })
+            .collect();
 
         if find_related {
             span!(tracing::Level::INFO, "find_related")
@@ -116,9 +234,10 @@ impl Queryable for InMemoryOpenAIQueryable {
                     for mut entry in entries.into_iter() {
                         entry.related = Some(
                             self.find_related_events(
-                                &entry.event.title,
+                                entry.event.id,
                                 MAX_RELATED_EVENTS,
-                                year_filter,
+                                filter,
+                                mmr_lambda,
                             )
                             .await?,
                         );
@@ -128,77 +247,589 @@ impl Queryable for InMemoryOpenAIQueryable {
                         "Found {} Events, with related Events",
                         entries_with_related.len()
                     );
-                    Ok(entries_with_related)
+                    Ok(SearchResults {
+                        items: entries_with_related,
+                        facets,
+                    })
                 })
                 .await
         } else {
-            Ok(entries)
+            Ok(SearchResults {
+                items: entries,
+                facets,
+            })
         }
     }
+
+    /// Rather than looking for events near the centroid of the seeds, which can fall between
+    /// unrelated interests and be near none of them, this ranks events by their distance to
+    /// each seed separately, and fuses those rankings
+    #[tracing::instrument(skip(self))]
+    async fn recommend(
+        &self,
+        seeds: &[EventId],
+        exclude: &[EventId],
+        filter: &SearchFilter,
+        limit: u8,
+    ) -> Result<Vec<SearchItem>, QueryError> {
+        if limit == 0 {
+            return Err(QueryError::InvalidInput(
+                "limit must be at least 1".to_string(),
+            ));
+        }
+        let Some(first_seed) = seeds.first() else {
+            return Err(QueryError::InvalidInput(
+                "no events to recommend from".to_string(),
+            ));
+        };
+        let positions = |event_ids: &[EventId]| -> Vec<usize> {
+            event_ids
+                .iter()
+                .filter_map(|event_id| self.events.iter().position(|e| e.id == *event_id))
+                .collect()
+        };
+        let seed_positions = positions(seeds);
+        if seed_positions.is_empty() {
+            return Err(QueryError::NotFound(*first_seed));
+        }
+        if seed_positions.len() < seeds.len() {
+            warn!(
+                "ignoring {} unknown seeds",
+                seeds.len() - seed_positions.len()
+            );
+        }
+        let excluded: HashSet<usize> = seed_positions
+            .iter()
+            .copied()
+            .chain(positions(exclude))
+            .collect();
+        let include = |i: usize| !excluded.contains(&i) && filter.matches(&self.events[i]);
+
+        debug!("Finding events near each of {} seeds", seed_positions.len());
+        let candidates = limit as usize * RECOMMEND_CANDIDATES_PER_RESULT;
+        let rankings: Vec<Vec<usize>> = seed_positions
+            .iter()
+            .map(|&seed| {
+                self.index
+                    .search(
+                        candidates,
+                        |i| self.embeddings.distance_between(seed, i),
+                        include,
+                    )
+                    .into_iter()
+                    .map(|(i, _)| i)
+                    .collect()
+            })
+            .collect();
+        Ok(reciprocal_rank_fusion(&rankings)
+            .into_iter()
+            .take(limit as usize)
+            .map(|(i, _)| SearchItem {
+                event: self.events[i].clone(),
+                distance: seed_positions
+                    .iter()
+                    .map(|&seed| self.embeddings.distance_between(seed, i))
+                    .fold(f64::MAX, f64::min),
+                related: None,
+                passage: None,
+            })
+            .collect())
+    }
 }
 
-impl InMemoryOpenAIQueryable {
+impl<P: EmbeddingProvider> InMemoryOpenAIQueryable<P> {
+    /// Load the events and embeddings in `model_dir`, checking them against its manifest, if
+    /// it has one, according to `manifest_policy`
     pub async fn connect(
         model_dir: &Path,
-        openai_api_key: &str,
-    ) -> Result<InMemoryOpenAIQueryable, Box<dyn std::error::Error>> {
-        debug!("Creating OpenAI Client");
-        let openai_client = Client::new(openai_api_key.into());
+        embedding_provider: P,
+        index_config: IndexConfig,
+        manifest_policy: ManifestPolicy,
+    ) -> Result<InMemoryOpenAIQueryable<P>, QueryError> {
+        debug!(
+            "Using embedding model '{}' for queries",
+            embedding_provider.model()
+        );
 
         debug!("Loading data from {:?}", model_dir);
-        Ok(InMemoryOpenAIQueryable {
-            openai_client,
-            events: parsing::parse_embedded_events(model_dir)?,
+        let (events, embeddings) = parsing::parse_embedded_events(model_dir, manifest_policy)?;
+        let passages = parsing::parse_embedded_passages(model_dir, &events)?;
+
+        let manifest = Manifest::load(model_dir)?;
+        match &manifest {
+            Some(manifest) => {
+                let mismatches = manifest.mismatches(
+                    model_dir,
+                    embedding_provider.model(),
+                    embeddings.dimensions().unwrap_or(0),
+                )?;
+                if !mismatches.is_empty() {
+                    let message = format!(
+                        "{:?} doesn't match its manifest: {}",
+                        model_dir,
+                        mismatches.join("; ")
+                    );
+                    match manifest_policy {
+                        ManifestPolicy::Strict => return Err(QueryError::IndexCorrupt(message)),
+                        ManifestPolicy::Warn => error!("{}", message),
+                    }
+                }
+            }
+            None => warn!(
+                "No manifest in {:?}, so can't check it was built with '{}'",
+                model_dir,
+                embedding_provider.model()
+            ),
+        }
+
+        let mut queryable = Self::from_embedded(
+            embedding_provider,
+            events,
+            embeddings,
+            passages,
+            index_config,
+        );
+        queryable.manifest = manifest;
+        Ok(queryable)
+    }
+
+    /// How the loaded index was built, if that was recorded
+    pub fn manifest(&self) -> Option<&Manifest> {
+        self.manifest.as_ref()
+    }
+
+    fn from_embedded(
+        embedding_provider: P,
+        events: Vec<Event>,
+        embeddings: EventEmbeddings,
+        passages: Vec<EmbeddedPassage>,
+        index_config: IndexConfig,
+    ) -> InMemoryOpenAIQueryable<P> {
+        debug!("Indexing embeddings using {:?}", index_config);
+        let index = NearestNeighbourIndex::build(index_config, events.len(), |a, b| {
+            embeddings.distance_between(a, b)
+        });
+
+        debug!("Indexing event text");
+        let lexical_index = build_lexical_index(&events, &HashMap::new());
+
+        debug!("Indexing {} passage embeddings", passages.len());
+        let passage_index = NearestNeighbourIndex::build(index_config, passages.len(), |a, b| {
+            distance(&passages[a].openai_embedding, &passages[b].openai_embedding)
+        });
+        let mut passages_for_event = vec![vec![]; events.len()];
+        for (i, passage) in passages.iter().enumerate() {
+            passages_for_event[passage.event].push(i);
+        }
+
+        InMemoryOpenAIQueryable {
+            embedding_provider,
+            events,
+            embeddings,
+            index,
+            lexical_index,
+            additional_text: HashMap::new(),
+            passages,
+            passage_index,
+            passages_for_event,
+            manifest: None,
+        }
+    }
+
+    async fn embed(&self, query: &str) -> Result<OpenAIVector, QueryError> {
+        let embedding = self
+            .embedding_provider
+            .embed(query)
+            .await
+            .map_err(|e| QueryError::EmbeddingUnavailable(e.to_string()))?;
+        match self.embeddings.dimensions() {
+            Some(dimensions) if dimensions != embedding.len() => {
+                Err(QueryError::EmbeddingUnavailable(format!(
+                    "'{}' gave {} dimensions, but the index has {}",
+                    self.embedding_provider.model(),
+                    embedding.len(),
+                    dimensions
+                )))
+            }
+            _ => Ok(embedding),
+        }
+    }
+
+    /// Make extra text, such as slide content or transcripts, searchable lexically
+    pub fn add_lexical_text(&mut self, text_for_event: HashMap<EventId, String>) {
+        debug!("Adding text for {} events", text_for_event.len());
+        for (event_id, text) in text_for_event {
+            self.additional_text.entry(event_id).or_default().push(text);
+        }
+        self.lexical_index = build_lexical_index(&self.events, &self.additional_text);
+    }
+
+    fn lexical_ranking<F>(&self, query: &str, limit: usize, include: F) -> Vec<Ranked>
+    where
+        F: Fn(usize) -> bool,
+    {
+        let found = self.lexical_index.search(query, limit, include);
+        // there's no natural distance here, so scale scores so that the best match is at 0.0
+        let top_score = found.first().map_or(1.0, |(_, score)| *score);
+        found
+            .into_iter()
+            .map(|(i, score)| Ranked::by_distance((i, 1.0 - (score / top_score))))
+            .collect()
+    }
+
+    fn hybrid_ranking<F>(
+        &self,
+        query: &str,
+        embedding: &OpenAIVector,
+        limit: usize,
+        include: F,
+    ) -> Vec<Ranked>
+    where
+        F: Fn(usize) -> bool + Copy,
+    {
+        let candidates = limit * HYBRID_CANDIDATES_PER_RESULT;
+        let semantic_ranking = self.semantic_ranking(embedding, candidates, include);
+        let semantic_distances: HashMap<usize, f64> = semantic_ranking.iter().copied().collect();
+        let semantic: Vec<usize> = semantic_ranking.into_iter().map(|(i, _)| i).collect();
+        let lexical: Vec<usize> = self
+            .lexical_index
+            .search(query, candidates, include)
+            .into_iter()
+            .map(|(i, _)| i)
+            .collect();
+        let fused = reciprocal_rank_fusion(&[semantic, lexical]);
+        // the fused order isn't that of distance, so relevance is the fused score, scaled so that
+        // the best match is at 1.0
+        let top_score = fused.first().map_or(1.0, |(_, score)| *score);
+        fused
+            .into_iter()
+            .take(limit)
+            .map(|(i, score)| Ranked {
+                event: i,
+                distance: semantic_distances
+                    .get(&i)
+                    .copied()
+                    .unwrap_or_else(|| self.embeddings.distance_to(i, embedding)),
+                relevance: score / top_score,
+            })
+            .collect()
+    }
+
+    /// Events closest to `embedding`, closest first, where an event is as close as the closer
+    /// of its own embedding and that of any of its passages
+    fn semantic_ranking<F>(
+        &self,
+        embedding: &OpenAIVector,
+        limit: usize,
+        include: F,
+    ) -> Vec<(usize, f64)>
+    where
+        F: Fn(usize) -> bool + Copy,
+    {
+        let mut closest: HashMap<usize, f64> = self
+            .index
+            .search(
+                limit,
+                |i| self.embeddings.distance_to(i, embedding),
+                include,
+            )
+            .into_iter()
+            .collect();
+        for (p, passage_distance) in self.passage_index.search(
+            limit * PASSAGE_CANDIDATES_PER_RESULT,
+            |p| distance(embedding, &self.passages[p].openai_embedding),
+            |p| include(self.passages[p].event),
+        ) {
+            let event_distance = closest.entry(self.passages[p].event).or_insert(f64::MAX);
+            *event_distance = event_distance.min(passage_distance);
+        }
+        let mut ranking: Vec<(usize, f64)> = closest.into_iter().collect();
+        ranking.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+        ranking.truncate(limit);
+        ranking
+    }
+
+    fn item_with_passage(
+        &self,
+        i: usize,
+        embedding: &OpenAIVector,
+        item_distance: f64,
+    ) -> SearchItem {
+        let passage = self.passages_for_event[i]
+            .iter()
+            .map(|p| &self.passages[*p])
+            .min_by(|a, b| {
+                distance(embedding, &a.openai_embedding)
+                    .total_cmp(&distance(embedding, &b.openai_embedding))
+            })
+            .map(|p| p.passage.clone());
+        SearchItem {
+            event: self.events[i].clone(),
+            distance: item_distance,
+            related: None,
+            passage,
+        }
+    }
+
+    /// Re-rank `ranking` so that near duplicates of events already picked give way to other
+    /// events, unless `mmr_lambda` is 1.0, which keeps the ranking as it is
+    fn diversify(&self, ranking: &[Ranked], limit: u8, mmr_lambda: f64) -> Vec<Ranked> {
+        if mmr_lambda >= 1.0 {
+            return ranking.iter().take(limit as usize).copied().collect();
+        }
+        let candidates: Vec<(usize, f64)> = ranking
+            .iter()
+            .enumerate()
+            .map(|(position, r)| (position, r.relevance))
+            .collect();
+        maximal_marginal_relevance(&candidates, limit as usize, mmr_lambda, |a, b| {
+            self.embeddings
+                .distance_between(ranking[a].event, ranking[b].event)
         })
+        .into_iter()
+        .map(|(position, _)| ranking[position])
+        .collect()
+    }
+
+    fn item(&self, i: usize, distance: f64) -> SearchItem {
+        SearchItem {
+            event: self.events[i].clone(),
+            distance,
+            related: None,
+            passage: None,
+        }
+    }
+}
+
+fn validate_mmr_lambda(mmr_lambda: f64) -> Result<(), QueryError> {
+    if (0.0..=1.0).contains(&mmr_lambda) {
+        Ok(())
+    } else {
+        Err(QueryError::InvalidInput(format!(
+            "lambda must be between 0 and 1, not {}",
+            mmr_lambda
+        )))
     }
 }
 
+/// how many candidates to rank before diversifying them down to `limit`
+fn candidate_count(limit: u8, mmr_lambda: f64) -> usize {
+    if mmr_lambda >= 1.0 {
+        limit as usize
+    } else {
+        limit as usize * MMR_CANDIDATES_PER_RESULT
+    }
+}
+
+fn build_lexical_index(
+    events: &[Event],
+    additional_text: &HashMap<EventId, Vec<String>>,
+) -> LexicalIndex {
+    let documents: Vec<Vec<(&str, f64)>> = events
+        .iter()
+        .map(|event| {
+            let additional: Vec<&str> = additional_text
+                .get(&event.id)
+                .map(|texts| texts.iter().map(|t| t.as_str()).collect())
+                .unwrap_or_default();
+            event_fields(event, &additional)
+        })
+        .collect();
+    LexicalIndex::build(&documents)
+}
+
 mod parsing {
     use std::{
+        collections::HashMap,
         fs::File,
         io::{BufReader, Read},
         path::Path,
     };
 
     use flate2::read::GzDecoder;
-    use tracing::{error, info};
+    use serde::Deserialize;
+    use tracing::{error, info, warn};
 
-    use crate::model::{Event, OpenAIEmbedding};
+    use crate::binary_index::BinaryIndex;
+    use crate::error::QueryError;
+    use crate::manifest::{sha256_file, ManifestPolicy};
+    use crate::model::{Event, EventId, OpenAIVector, PassageEmbedding};
 
-    use super::EmbeddedEvent;
+    use super::{EmbeddedPassage, EventEmbeddings};
 
+    /// Embeddings are read from `embeddings.bin` if there is one, which was built from the
+    /// `embeddings.json.gz` which is there now, or from `embeddings.json.gz` if not. One built
+    /// from other embeddings is an error, unless `manifest_policy` is to only warn.
     pub fn parse_embedded_events(
         model_dir: &Path,
-    ) -> Result<Vec<EmbeddedEvent>, Box<dyn std::error::Error>> {
+        manifest_policy: ManifestPolicy,
+    ) -> Result<(Vec<Event>, EventEmbeddings), QueryError> {
         let events_path = model_dir.join("events").with_extension("json");
         let events = parse_all_events(&events_path)?;
         let embeddings_path = model_dir.join("embeddings").with_extension("json.gz");
-        let embeddings: Vec<OpenAIEmbedding> = parse_all_embeddings(&embeddings_path)?;
+
+        let binary_path = model_dir.join("embeddings").with_extension("bin");
+        if binary_path.exists() {
+            info!("Mapping embeddings from {:?}", binary_path);
+            match open_binary_index(&binary_path, &embeddings_path) {
+                Ok(index) => {
+                    info!(
+                        "Mapped {} {:?} embeddings of {} dimensions",
+                        index.event_ids().len(),
+                        index.encoding(),
+                        index.dimensions()
+                    );
+                    let rows = match_rows_to_events(&events, index.event_ids())?;
+                    return Ok((events, EventEmbeddings::Mapped { index, rows }));
+                }
+                Err(QueryError::IndexCorrupt(message))
+                    if manifest_policy == ManifestPolicy::Warn =>
+                {
+                    warn!("{}, so reading {:?} instead", message, embeddings_path);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        let embeddings = parse_all_embeddings(&embeddings_path)?;
 
         info!(
             "Parsing embeddings and events from {:?} and {:?}",
             events_path, embeddings_path
         );
 
+        let embeddings = match_embeddings_to_events(&events, &embeddings)?;
+        Ok((events, EventEmbeddings::Parsed(embeddings)))
+    }
+
+    /// The binary index at `binary_path`, as long as it was built from the embeddings at
+    /// `embeddings_path`, when they're there to check
+    fn open_binary_index(
+        binary_path: &Path,
+        embeddings_path: &Path,
+    ) -> Result<BinaryIndex, QueryError> {
+        let index = BinaryIndex::open(binary_path)?;
+        if embeddings_path.exists() && sha256_file(embeddings_path)? != index.source() {
+            return Err(QueryError::IndexCorrupt(format!(
+                "{:?} was built from other embeddings than {:?}; rebuild it with build_binary_index",
+                binary_path, embeddings_path
+            )));
+        }
+        Ok(index)
+    }
+
+    /// For each event, the row of the binary index holding its embedding
+    pub(super) fn match_rows_to_events(
+        events: &[Event],
+        event_ids: &[EventId],
+    ) -> Result<Vec<usize>, QueryError> {
+        let row_for_event_id: HashMap<EventId, usize> = event_ids
+            .iter()
+            .enumerate()
+            .map(|(row, event_id)| (*event_id, row))
+            .collect();
+        events
+            .iter()
+            .map(|event| {
+                row_for_event_id.get(&event.id).copied().ok_or_else(|| {
+                    QueryError::IndexCorrupt(format!(
+                        "failed to find embedding for event {}",
+                        event.id
+                    ))
+                })
+            })
+            .collect()
+    }
+
+    /// Passages are optional, as they are only made when slide or video content is available
+    pub fn parse_embedded_passages(
+        model_dir: &Path,
+        events: &[Event],
+    ) -> Result<Vec<EmbeddedPassage>, QueryError> {
+        let passages_path = model_dir.join("passages").with_extension("json.gz");
+        if !passages_path.exists() {
+            info!("No passages at {:?}", passages_path);
+            return Ok(vec![]);
+        }
+
+        info!("Loading passages data from {:?}", passages_path);
+        let reader = reader_for_path(&passages_path)?;
+        let passage_embeddings: Vec<PassageEmbedding> =
+            serde_json::from_reader(reader).map_err(|e| {
+                QueryError::IndexCorrupt(format!("Could not parse_embedded_passages: {}", e))
+            })?;
+
+        let event_index: HashMap<EventId, usize> =
+            events.iter().enumerate().map(|(i, e)| (e.id, i)).collect();
+        let mut embedded_passages = vec![];
+        for passage_embedding in passage_embeddings {
+            match event_index.get(&passage_embedding.passage.event_id) {
+                Some(event) => embedded_passages.push(EmbeddedPassage {
+                    event: *event,
+                    passage: passage_embedding.passage,
+                    openai_embedding: passage_embedding.embedding,
+                }),
+                None => warn!(
+                    "ignoring passage for unknown event {}",
+                    passage_embedding.passage.event_id
+                ),
+            }
+        }
+        info!("Loaded {} passages", embedded_passages.len());
+        Ok(embedded_passages)
+    }
+
+    /// An entry in `embeddings.json.gz`. These used to be keyed by `title`, which is ambiguous
+    /// when titles are reused, and are now keyed by `event_id`; either is accepted when reading.
+    #[derive(Debug, Deserialize)]
+    pub(super) struct StoredEmbedding {
+        #[serde(default)]
+        pub event_id: Option<EventId>,
+        #[serde(default)]
+        pub title: Option<String>,
+        pub embedding: OpenAIVector,
+    }
+
+    pub(super) fn match_embeddings_to_events(
+        events: &[Event],
+        embeddings: &[StoredEmbedding],
+    ) -> Result<Vec<OpenAIVector>, QueryError> {
+        let mut by_event_id = HashMap::new();
+        let mut by_title = HashMap::new();
+        for stored in embeddings {
+            if let Some(event_id) = stored.event_id {
+                by_event_id.insert(event_id, &stored.embedding);
+            } else if let Some(title) = &stored.title {
+                // for compatibility, the first embedding for a title wins, as it did before
+                by_title.entry(title.as_str()).or_insert(&stored.embedding);
+            }
+        }
+        if !by_title.is_empty() {
+            warn!(
+                "{} embeddings are keyed by title rather than event id; re-fetch them to avoid mismatches",
+                by_title.len()
+            );
+        }
+
         let mut embedded_events = vec![];
         for event in events {
-            let result = embeddings.iter().find(|e| e.title == event.title);
+            let result = by_event_id
+                .get(&event.id)
+                .or_else(|| by_title.get(event.title.as_str()));
             match result {
-                Some(embedding) => embedded_events.push(EmbeddedEvent {
-                    event,
-                    openai_embedding: embedding.embedding.clone(),
-                }),
+                Some(embedding) => embedded_events.push((*embedding).clone()),
                 None => {
-                    return Err(
-                        format!("failed to find embedding for title \'{}\'", event.title).into(),
-                    );
+                    return Err(QueryError::IndexCorrupt(format!(
+                        "failed to find embedding for event {}",
+                        event.id
+                    )));
                 }
             }
         }
         Ok(embedded_events)
     }
 
-    fn parse_all_events(events_path: &Path) -> Result<Vec<Event>, Box<dyn std::error::Error>> {
+    fn parse_all_events(events_path: &Path) -> Result<Vec<Event>, QueryError> {
         info!("Loading events data from {:?}", events_path);
 
         let reader = reader_for_path(events_path)?;
@@ -210,19 +841,20 @@ mod parsing {
             }
             Err(e) => {
                 error!("error: {}", e);
-                Err(format!("Could not parse_all_events: {}", e).into())
+                Err(QueryError::IndexCorrupt(format!(
+                    "Could not parse_all_events: {}",
+                    e
+                )))
             }
         }
     }
 
-    fn parse_all_embeddings(
-        embeddings_path: &Path,
-    ) -> Result<Vec<OpenAIEmbedding>, Box<dyn std::error::Error>> {
+    fn parse_all_embeddings(embeddings_path: &Path) -> Result<Vec<StoredEmbedding>, QueryError> {
         info!("Loading embeddings data from {:?}", embeddings_path);
 
         let reader = reader_for_path(embeddings_path)?;
 
-        match serde_json::from_reader::<BufReader<Box<dyn std::io::Read>>, Vec<OpenAIEmbedding>>(
+        match serde_json::from_reader::<BufReader<Box<dyn std::io::Read>>, Vec<StoredEmbedding>>(
             reader,
         ) {
             Ok(embeddings) => {
@@ -231,14 +863,15 @@ mod parsing {
             }
             Err(e) => {
                 error!("error: {}", e);
-                Err(format!("Could not parse_all_embeddings: {}", e).into())
+                Err(QueryError::IndexCorrupt(format!(
+                    "Could not parse_all_embeddings: {}",
+                    e
+                )))
             }
         }
     }
 
-    fn reader_for_path(
-        path: &Path,
-    ) -> Result<BufReader<Box<dyn Read>>, Box<dyn std::error::Error>> {
+    fn reader_for_path(path: &Path) -> Result<BufReader<Box<dyn Read>>, QueryError> {
         match File::open(path) {
             Ok(file) => {
                 let reader: BufReader<Box<dyn Read>> =
@@ -251,8 +884,364 @@ mod parsing {
             }
             Err(e) => {
                 error!("error: {}", e);
-                Err(format!("Could not choose reader_for_path: {}", e).into())
+                Err(e.into())
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use tempfile::TempDir;
+
+    use super::parsing::{
+        match_embeddings_to_events, match_rows_to_events, parse_embedded_events, StoredEmbedding,
+    };
+    use super::{EmbeddedPassage, EventEmbeddings, InMemoryOpenAIQueryable, Ranked};
+    use crate::binary_index::{write_binary_index, VectorEncoding};
+    use crate::error::QueryError;
+    use crate::filter::SearchFilter;
+    use crate::manifest::{sha256_file, ManifestPolicy};
+    use crate::model::{
+        test_event, Event, EventId, OpenAIEmbedding, OpenAIVector, Passage, PassageLocation,
+    };
+    use crate::nearest::IndexConfig;
+    use crate::offline::OfflineEmbeddingProvider;
+    use crate::queryable::{Queryable, SearchMode};
+
+    #[test]
+    fn test_events_sharing_a_title_get_their_own_embedding() {
+        let first = EventId::new(2025, 1);
+        let second = EventId::new(2026, 1);
+        let events = vec![
+            test_event(first, "Welcome to the Rust devroom"),
+            test_event(second, "Welcome to the Rust devroom"),
+        ];
+        let embeddings = vec![
+            StoredEmbedding {
+                event_id: Some(second),
+                title: None,
+                embedding: OpenAIVector::from(vec![0.0, 1.0]),
+            },
+            StoredEmbedding {
+                event_id: Some(first),
+                title: None,
+                embedding: OpenAIVector::from(vec![1.0, 0.0]),
+            },
+        ];
+
+        let embedded = match_embeddings_to_events(&events, &embeddings).unwrap();
+
+        assert_eq!(OpenAIVector::from(vec![1.0, 0.0]), embedded[0]);
+        assert_eq!(OpenAIVector::from(vec![0.0, 1.0]), embedded[1]);
+    }
+
+    #[test]
+    fn test_title_keyed_embeddings_are_still_readable() {
+        let events = vec![test_event(EventId::new(2025, 1), "Some Title")];
+        let embeddings: Vec<StoredEmbedding> = serde_json::from_str(
+            r#"[{"title": "Some Title", "embedding": [[1.0, 0.0], 2, null]}]"#,
+        )
+        .unwrap();
+
+        let embedded = match_embeddings_to_events(&events, &embeddings).unwrap();
+
+        assert_eq!(OpenAIVector::from(vec![1.0, 0.0]), embedded[0]);
+    }
+
+    #[test]
+    fn test_missing_embedding_is_an_error() {
+        let events = vec![test_event(EventId::new(2025, 1), "Some Title")];
+
+        assert!(match_embeddings_to_events(&events, &[]).is_err());
+    }
+
+    #[test]
+    fn test_binary_rows_are_matched_by_event_id() {
+        let events = vec![
+            test_event(EventId::new(2025, 1), "First"),
+            test_event(EventId::new(2025, 2), "Second"),
+        ];
+        let event_ids = [
+            EventId::new(2025, 2),
+            EventId::new(2024, 9),
+            EventId::new(2025, 1),
+        ];
+
+        assert_eq!(
+            vec![2, 0],
+            match_rows_to_events(&events, &event_ids).unwrap()
+        );
+        assert!(match_rows_to_events(&events, &event_ids[..2]).is_err());
+    }
+
+    #[test]
+    fn test_binary_index_built_from_other_embeddings_is_not_used() {
+        let dir = TempDir::new().unwrap();
+        let model_dir = dir.path();
+        let id = EventId::new(2026, 1);
+        let events = vec![test_event(id, "Rust")];
+        std::fs::write(
+            model_dir.join("events.json"),
+            serde_json::to_vec(&events).unwrap(),
+        )
+        .unwrap();
+        let vector = OpenAIVector::from(vec![0.6, 0.8]);
+        let embeddings = vec![OpenAIEmbedding {
+            event_id: id,
+            embedding: vector.clone(),
+        }];
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        serde_json::to_writer(&mut encoder, &embeddings).unwrap();
+        let embeddings_path = model_dir.join("embeddings.json.gz");
+        std::fs::write(&embeddings_path, encoder.finish().unwrap()).unwrap();
+        let binary_path = model_dir.join("embeddings.bin");
+        let other_source = "0".repeat(64);
+        write_binary_index(
+            &binary_path,
+            VectorEncoding::F32,
+            &other_source,
+            &[(id, &vector)],
+        )
+        .unwrap();
+
+        assert!(matches!(
+            parse_embedded_events(model_dir, ManifestPolicy::Strict),
+            Err(QueryError::IndexCorrupt(_))
+        ));
+        let (_, embeddings) = parse_embedded_events(model_dir, ManifestPolicy::Warn).unwrap();
+        assert!(matches!(embeddings, EventEmbeddings::Parsed(_)));
+
+        let source = sha256_file(&embeddings_path).unwrap();
+        write_binary_index(&binary_path, VectorEncoding::F32, &source, &[(id, &vector)]).unwrap();
+        let (_, embeddings) = parse_embedded_events(model_dir, ManifestPolicy::Strict).unwrap();
+        assert!(matches!(embeddings, EventEmbeddings::Mapped { .. }));
+    }
+
+    fn embedded_titles(
+        provider: &OfflineEmbeddingProvider,
+        titles: &[(EventId, &str)],
+    ) -> (Vec<Event>, EventEmbeddings) {
+        let events = titles
+            .iter()
+            .map(|(id, title)| test_event(*id, title))
+            .collect();
+        let embeddings = EventEmbeddings::Parsed(
+            titles
+                .iter()
+                .map(|(_, title)| provider.embed_sync(title))
+                .collect(),
+        );
+        (events, embeddings)
+    }
+
+    async fn recommended_ids(
+        queryable: &InMemoryOpenAIQueryable<OfflineEmbeddingProvider>,
+        seeds: &[EventId],
+        exclude: &[EventId],
+        limit: u8,
+    ) -> Vec<EventId> {
+        let mut ids: Vec<EventId> = queryable
+            .recommend(seeds, exclude, &SearchFilter::default(), limit)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|item| item.event.id)
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    #[tokio::test]
+    async fn test_recommendations_are_near_each_seed() {
+        let provider = OfflineEmbeddingProvider::default();
+        let (events, embeddings) = embedded_titles(
+            &provider,
+            &[
+                (EventId::new(2025, 1), "async runtimes in rust"),
+                (EventId::new(2025, 2), "async executors in rust"),
+                (EventId::new(2025, 3), "the postgres query planner"),
+                (EventId::new(2025, 4), "postgres query optimisation"),
+                (EventId::new(2025, 5), "welcome and community update"),
+            ],
+        );
+        let queryable = InMemoryOpenAIQueryable::from_embedded(
+            provider,
+            events,
+            embeddings,
+            vec![],
+            IndexConfig::BruteForce,
+        );
+        let seeds = [EventId::new(2025, 1), EventId::new(2025, 3)];
+
+        assert_eq!(
+            vec![EventId::new(2025, 2), EventId::new(2025, 4)],
+            recommended_ids(&queryable, &seeds, &[], 2).await
+        );
+        assert_eq!(
+            vec![EventId::new(2025, 4)],
+            recommended_ids(&queryable, &seeds, &[EventId::new(2025, 2)], 1).await
+        );
+    }
+
+    #[tokio::test]
+    async fn test_recommending_from_only_unknown_seeds_is_not_found() {
+        let provider = OfflineEmbeddingProvider::default();
+        let (events, embeddings) =
+            embedded_titles(&provider, &[(EventId::new(2025, 1), "some title")]);
+        let queryable = InMemoryOpenAIQueryable::from_embedded(
+            provider,
+            events,
+            embeddings,
+            vec![],
+            IndexConfig::BruteForce,
+        );
+
+        let result = queryable
+            .recommend(&[EventId::new(2024, 1)], &[], &SearchFilter::default(), 5)
+            .await;
+
+        assert!(matches!(result, Err(QueryError::NotFound(_))));
+    }
+
+    #[test]
+    fn test_diversifying_keeps_to_a_ranking_which_is_not_in_distance_order() {
+        let provider = OfflineEmbeddingProvider::default();
+        let (events, embeddings) = embedded_titles(
+            &provider,
+            &[
+                (EventId::new(2025, 1), "async runtimes in rust"),
+                (EventId::new(2025, 2), "the postgres query planner"),
+                (EventId::new(2025, 3), "welcome and community update"),
+            ],
+        );
+        let queryable = InMemoryOpenAIQueryable::from_embedded(
+            provider,
+            events,
+            embeddings,
+            vec![],
+            IndexConfig::BruteForce,
+        );
+        // as from hybrid search, where the best fused match needn't be the closest
+        let ranked = |event, distance, relevance| Ranked {
+            event,
+            distance,
+            relevance,
+        };
+        let ranking = [
+            ranked(2, 0.6, 1.0),
+            ranked(0, 0.2, 0.98),
+            ranked(1, 0.4, 0.5),
+        ];
+
+        let diversified = queryable.diversify(&ranking, 3, 0.7);
+
+        assert_eq!(ranking[0], diversified[0]);
+        assert_eq!(3, diversified.len());
+        assert!(diversified.iter().all(|r| ranking.contains(r)));
+    }
+
+    #[tokio::test]
+    async fn test_facets_count_more_than_the_results_returned() {
+        let provider = OfflineEmbeddingProvider::default();
+        let (events, embeddings) = embedded_titles(
+            &provider,
+            &[
+                (EventId::new(2024, 1), "rust in the kernel"),
+                (EventId::new(2025, 2), "rust for embedded"),
+                (EventId::new(2026, 3), "rust and webassembly"),
+                (EventId::new(2026, 4), "the postgres query planner"),
+            ],
+        );
+        let queryable = InMemoryOpenAIQueryable::from_embedded(
+            provider,
+            events,
+            embeddings,
+            vec![],
+            IndexConfig::BruteForce,
+        );
+
+        let results = queryable
+            .search(
+                "rust",
+                1,
+                false,
+                &SearchFilter::default(),
+                SearchMode::Lexical,
+                1.0,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(1, results.items.len());
+        assert_eq!(
+            vec![(2024, 1), (2025, 1), (2026, 1)],
+            results
+                .facets
+                .years
+                .iter()
+                .map(|c| (c.value, c.count))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_event_is_found_by_its_best_passage() {
+        let provider = OfflineEmbeddingProvider::default();
+        let (events, embeddings) = embedded_titles(
+            &provider,
+            &[
+                (EventId::new(2025, 1), "Writing a kernel driver in Rust"),
+                (EventId::new(2025, 2), "Community update"),
+            ],
+        );
+        let passages = [
+            (0, "we wrote the driver using unsafe code", 60),
+            (1, "welcome to the community update", 0),
+            (
+                1,
+                "garbage collection pauses in the JVM were the biggest problem",
+                600,
+            ),
+        ]
+        .into_iter()
+        .map(|(event, text, start_seconds)| EmbeddedPassage {
+            event,
+            passage: Passage {
+                event_id: EventId::new(2025, event as u32 + 1),
+                location: PassageLocation::Transcript { start_seconds },
+                text: text.to_string(),
+            },
+            openai_embedding: provider.embed_sync(text),
+        })
+        .collect();
+        let queryable = InMemoryOpenAIQueryable::from_embedded(
+            provider,
+            events,
+            embeddings,
+            passages,
+            IndexConfig::BruteForce,
+        );
+
+        let found = queryable
+            .search(
+                "garbage collection pauses in the JVM",
+                1,
+                false,
+                &SearchFilter::default(),
+                SearchMode::Semantic,
+                1.0,
+            )
+            .await
+            .unwrap()
+            .items;
+
+        assert_eq!(EventId::new(2025, 2), found[0].event.id);
+        assert_eq!(
+            PassageLocation::Transcript { start_seconds: 600 },
+            found[0].passage.as_ref().unwrap().location
+        );
+    }
+}
@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+
+use crate::model::Event;
+
+const K1: f64 = 1.2;
+const B: f64 = 0.75;
+
+const TITLE_WEIGHT: f64 = 3.0;
+const PRESENTER_WEIGHT: f64 = 3.0;
+const TRACK_WEIGHT: f64 = 2.0;
+const ABSTRACT_WEIGHT: f64 = 1.0;
+const ADDITIONAL_TEXT_WEIGHT: f64 = 0.5;
+
+/// A BM25 inverted index, where documents are made up of weighted fields. A term appearing in a
+/// field counts `weight` times towards its frequency in the document.
+#[derive(Debug, Default)]
+pub struct LexicalIndex {
+    postings: HashMap<String, Vec<Posting>>,
+    document_lengths: Vec<f64>,
+    average_length: f64,
+}
+
+#[derive(Debug)]
+struct Posting {
+    document: usize,
+    frequency: f64,
+}
+
+impl LexicalIndex {
+    pub fn build(documents: &[Vec<(&str, f64)>]) -> LexicalIndex {
+        let mut postings: HashMap<String, Vec<Posting>> = HashMap::new();
+        let mut document_lengths = Vec::with_capacity(documents.len());
+        for (document, fields) in documents.iter().enumerate() {
+            let mut frequencies: HashMap<String, f64> = HashMap::new();
+            let mut length = 0.0;
+            for (text, weight) in fields {
+                for term in tokenize(text) {
+                    *frequencies.entry(term).or_insert(0.0) += weight;
+                    length += weight;
+                }
+            }
+            for (term, frequency) in frequencies {
+                postings.entry(term).or_default().push(Posting {
+                    document,
+                    frequency,
+                });
+            }
+            document_lengths.push(length);
+        }
+        let average_length = if document_lengths.is_empty() {
+            0.0
+        } else {
+            document_lengths.iter().sum::<f64>() / document_lengths.len() as f64
+        };
+        LexicalIndex {
+            postings,
+            document_lengths,
+            average_length,
+        }
+    }
+
+    /// The documents with the highest BM25 score for `query`, highest first. Documents which
+    /// share no terms with the query are never returned.
+    pub fn search<F>(&self, query: &str, k: usize, include: F) -> Vec<(usize, f64)>
+    where
+        F: Fn(usize) -> bool,
+    {
+        let document_count = self.document_lengths.len() as f64;
+        let mut query_terms = tokenize(query);
+        query_terms.sort();
+        query_terms.dedup();
+
+        let mut scores: HashMap<usize, f64> = HashMap::new();
+        for term in query_terms {
+            let Some(postings) = self.postings.get(&term) else {
+                continue;
+            };
+            let matching = postings.len() as f64;
+            let idf = (1.0 + (document_count - matching + 0.5) / (matching + 0.5)).ln();
+            for posting in postings {
+                let normalised_length =
+                    self.document_lengths[posting.document] / self.average_length;
+                let tf = (posting.frequency * (K1 + 1.0))
+                    / (posting.frequency + K1 * (1.0 - B + B * normalised_length));
+                *scores.entry(posting.document).or_insert(0.0) += idf * tf;
+            }
+        }
+
+        let mut ranked: Vec<(usize, f64)> = scores
+            .into_iter()
+            .filter(|(document, _)| include(*document))
+            .collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        ranked.truncate(k);
+        ranked
+    }
+}
+
+/// The fields of an event which are searched lexically, with how much each counts
+pub fn event_fields<'a>(event: &'a Event, additional_text: &[&'a str]) -> Vec<(&'a str, f64)> {
+    let mut fields = vec![
+        (event.title.as_str(), TITLE_WEIGHT),
+        (event.track.as_str(), TRACK_WEIGHT),
+        (event.r#abstract.as_str(), ABSTRACT_WEIGHT),
+    ];
+    for presenter in &event.presenters {
+        fields.push((presenter.name.as_str(), PRESENTER_WEIGHT));
+    }
+    for text in additional_text {
+        fields.push((text, ADDITIONAL_TEXT_WEIGHT));
+    }
+    fields
+}
+
+/// Lowercased alphanumeric terms, ignoring anything inside html tags (abstracts are html)
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut terms = vec![];
+    let mut current = String::new();
+    let mut in_tag = false;
+    for c in text.chars() {
+        if c == '<' {
+            in_tag = true;
+        } else if c == '>' {
+            in_tag = false;
+        } else if !in_tag && c.is_alphanumeric() {
+            current.extend(c.to_lowercase());
+            continue;
+        }
+        if !current.is_empty() {
+            terms.push(std::mem::take(&mut current));
+        }
+    }
+    if !current.is_empty() {
+        terms.push(current);
+    }
+    terms
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize_ignores_html_and_case() {
+        assert_eq!(
+            vec!["testing", "with", "openqa", "and", "os", "autoinst"],
+            tokenize("<p>Testing <em>with</em> openQA and os-autoinst</p>")
+        );
+    }
+
+    #[test]
+    fn test_exact_name_ranks_first() {
+        let index = LexicalIndex::build(&[
+            vec![("Testing the desktop", 1.0)],
+            vec![("Testing GNOME with openQA", 1.0)],
+            vec![("openQA openQA", 1.0), ("Unrelated", 1.0)],
+        ]);
+
+        let found = index.search("openqa", 10, |_| true);
+
+        assert_eq!(
+            vec![2, 1],
+            found.iter().map(|(d, _)| *d).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_field_weight_counts() {
+        let index = LexicalIndex::build(&[
+            vec![("Some Title", 1.0), ("Jane Doe", 1.0)],
+            vec![("Other Title", 1.0), ("Jane Doe", 3.0)],
+        ]);
+
+        let found = index.search("doe", 10, |_| true);
+
+        assert_eq!(1, found[0].0);
+    }
+
+    #[test]
+    fn test_search_respects_filter_and_limit() {
+        let index = LexicalIndex::build(&[
+            vec![("rust", 1.0)],
+            vec![("rust", 1.0)],
+            vec![("rust", 1.0)],
+        ]);
+
+        let found = index.search("rust", 1, |d| d != 0);
+
+        assert_eq!(vec![1], found.iter().map(|(d, _)| *d).collect::<Vec<_>>());
+    }
+}
@@ -1,7 +1,22 @@
+pub mod binary_index;
 pub mod cli;
+pub mod embedding;
 pub mod env;
+pub mod error;
+pub mod evaluation;
+pub mod facets;
+pub mod filter;
+pub mod fusion;
 pub mod inmemory_openai;
+pub mod lexical;
+pub mod manifest;
+pub mod map;
 pub mod model;
+pub mod nearest;
+pub mod offline;
 pub mod openai;
+pub mod people;
 pub mod queryable;
+pub mod rerank;
 pub mod summary;
+pub mod topics;
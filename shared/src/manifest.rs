@@ -0,0 +1,228 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::warn;
+
+use crate::error::QueryError;
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// The files of a model dir which have their checksums recorded, when they are present
+pub const CHECKSUMMED_FILES: [&str; 6] = [
+    "events.json",
+    "embeddings.json.gz",
+    "embeddings.bin",
+    "passages.json.gz",
+    "topics.json",
+    "map.json",
+];
+
+/// What went into the text which was embedded for each event
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentSource {
+    Title,
+    Track,
+    /// the kind of event, e.g. keynote or lightning talk
+    Type,
+    Language,
+    Abstract,
+    Presenters,
+    Slides,
+    Video,
+}
+
+impl Display for ContentSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ContentSource::Title => write!(f, "title"),
+            ContentSource::Track => write!(f, "track"),
+            ContentSource::Type => write!(f, "type"),
+            ContentSource::Language => write!(f, "language"),
+            ContentSource::Abstract => write!(f, "abstract"),
+            ContentSource::Presenters => write!(f, "presenters"),
+            ContentSource::Slides => write!(f, "slides"),
+            ContentSource::Video => write!(f, "video"),
+        }
+    }
+}
+
+/// A record of how the contents of a model dir were made, written by the content pipeline as
+/// `manifest.json`, so that an index can't silently be queried with a different model
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub model: String,
+    pub dimensions: usize,
+    pub years: Vec<u32>,
+    pub sources: Vec<ContentSource>,
+    /// sha256 of each file, by file name
+    #[serde(default)]
+    pub checksums: BTreeMap<String, String>,
+    pub built_at: DateTime<Utc>,
+}
+
+/// What to do when the manifest doesn't match the model dir or the embedding model
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ManifestPolicy {
+    /// refuse to load
+    #[default]
+    Strict,
+    /// log an error, and carry on
+    Warn,
+}
+
+impl Manifest {
+    /// The manifest in `model_dir`, if there is one
+    pub fn load(model_dir: &Path) -> Result<Option<Manifest>, QueryError> {
+        let path = model_dir.join(MANIFEST_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let reader = BufReader::new(File::open(&path)?);
+        serde_json::from_reader(reader)
+            .map(Some)
+            .map_err(|e| QueryError::IndexCorrupt(format!("Could not parse {:?}: {}", path, e)))
+    }
+
+    pub fn save(&self, model_dir: &Path) -> std::io::Result<()> {
+        let mut writer = BufWriter::new(File::create(model_dir.join(MANIFEST_FILE))?);
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.flush()
+    }
+
+    /// Record the checksums of whichever of `CHECKSUMMED_FILES` are in `model_dir` now
+    pub fn record_checksums(&mut self, model_dir: &Path) -> std::io::Result<()> {
+        self.checksums.clear();
+        for name in CHECKSUMMED_FILES {
+            let path = model_dir.join(name);
+            if path.exists() {
+                self.checksums.insert(name.to_string(), sha256_file(&path)?);
+            }
+        }
+        Ok(())
+    }
+
+    /// Everything about `model_dir` and the embedding model which doesn't match the manifest.
+    /// Files which are recorded but have since been removed are not a mismatch, as files like
+    /// `embeddings.bin` are optional.
+    pub fn mismatches(
+        &self,
+        model_dir: &Path,
+        model: &str,
+        dimensions: usize,
+    ) -> Result<Vec<String>, QueryError> {
+        let mut mismatches = vec![];
+        if self.model != model {
+            mismatches.push(format!(
+                "index was built with model '{}', but queries use '{}'",
+                self.model, model
+            ));
+        }
+        if self.dimensions != dimensions {
+            mismatches.push(format!(
+                "index should have {} dimensions, but has {}",
+                self.dimensions, dimensions
+            ));
+        }
+        for (name, expected) in &self.checksums {
+            let path = model_dir.join(name);
+            if !path.exists() {
+                warn!("{} is in the manifest, but is missing", name);
+                continue;
+            }
+            let actual = sha256_file(&path)?;
+            if &actual != expected {
+                mismatches.push(format!(
+                    "checksum of {} is {}, expected {}",
+                    name, actual, expected
+                ));
+            }
+        }
+        Ok(mismatches)
+    }
+}
+
+pub fn sha256_file(path: &Path) -> std::io::Result<String> {
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut BufReader::new(File::open(path)?), &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn model_dir() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("events.json"), "[]").unwrap();
+        dir
+    }
+
+    fn manifest(model_dir: &Path) -> Manifest {
+        let mut manifest = Manifest {
+            model: "some-model".to_string(),
+            dimensions: 3,
+            years: vec![2025, 2026],
+            sources: vec![ContentSource::Title, ContentSource::Abstract],
+            checksums: BTreeMap::new(),
+            built_at: Utc::now(),
+        };
+        manifest.record_checksums(model_dir).unwrap();
+        manifest
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let dir = model_dir();
+        let manifest = manifest(dir.path());
+
+        manifest.save(dir.path()).unwrap();
+
+        assert_eq!(Some(manifest), Manifest::load(dir.path()).unwrap());
+    }
+
+    #[test]
+    fn test_matching_dir_has_no_mismatches() {
+        let dir = model_dir();
+        let manifest = manifest(dir.path());
+
+        assert_eq!(
+            vec!["events.json"],
+            manifest.checksums.keys().collect::<Vec<_>>()
+        );
+        assert!(manifest
+            .mismatches(dir.path(), "some-model", 3)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_changed_model_dimensions_and_file_are_mismatches() {
+        let dir = model_dir();
+        let manifest = manifest(dir.path());
+        std::fs::write(dir.path().join("events.json"), "[ ]").unwrap();
+
+        assert_eq!(
+            3,
+            manifest
+                .mismatches(dir.path(), "other-model", 4)
+                .unwrap()
+                .len()
+        );
+    }
+
+    #[test]
+    fn test_missing_manifest_is_none() {
+        let dir = model_dir();
+
+        assert_eq!(None, Manifest::load(dir.path()).unwrap());
+    }
+}
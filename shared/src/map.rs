@@ -0,0 +1,62 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::error::QueryError;
+use crate::model::EventId;
+
+pub const MAP_FILE: &str = "map.json";
+
+/// Where an event is on a 2D map, where nearby events are similar. Both coordinates are between
+/// 0.0 and 1.0.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq)]
+pub struct MapPoint {
+    pub event_id: EventId,
+    pub x: f64,
+    pub y: f64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct EventMap {
+    points: Vec<MapPoint>,
+}
+
+impl EventMap {
+    pub fn new(points: Vec<MapPoint>) -> EventMap {
+        EventMap { points }
+    }
+
+    pub fn empty_map() -> EventMap {
+        EventMap::default()
+    }
+
+    /// The map is optional, as it is made by a separate projection step
+    pub fn load(model_dir: &Path) -> Result<EventMap, QueryError> {
+        let path = model_dir.join(MAP_FILE);
+        if !path.exists() {
+            info!("No map at {:?}", path);
+            return Ok(EventMap::empty_map());
+        }
+        let reader =
+            BufReader::new(File::open(&path).map_err(|e| {
+                QueryError::IndexCorrupt(format!("failed to open {:?}: {}", path, e))
+            })?);
+        let points: Vec<MapPoint> = serde_json::from_reader(reader)
+            .map_err(|e| QueryError::IndexCorrupt(format!("failed to parse {:?}: {}", path, e)))?;
+        info!("Loaded {} map points from {:?}", points.len(), path);
+        Ok(EventMap { points })
+    }
+
+    pub fn save(&self, model_dir: &Path) -> std::io::Result<()> {
+        let mut writer = BufWriter::new(File::create(model_dir.join(MAP_FILE))?);
+        serde_json::to_writer(&mut writer, &self.points)?;
+        writer.flush()
+    }
+
+    pub fn points(&self) -> &[MapPoint] {
+        &self.points
+    }
+}
@@ -3,6 +3,7 @@ use nalgebra::DVector;
 use openai_dive::v1::resources::embedding::{EmbeddingOutput, EmbeddingResponse};
 use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter};
+use std::str::FromStr;
 use url::Url;
 
 #[derive(Debug, Clone)]
@@ -17,6 +18,8 @@ pub struct SearchItem {
     pub event: Event,
     pub distance: f64,
     pub related: Option<Vec<SearchItem>>,
+    /// the part of the event's slides or transcript which best matched, if any
+    pub passage: Option<Passage>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq, PartialOrd, Eq, Ord, Hash, Copy)]
@@ -45,6 +48,20 @@ impl Display for EventId {
     }
 }
 
+/// Parses the `Display` form, e.g. `2026-7327`, as used in bookmark exports
+impl FromStr for EventId {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let not_an_event_id = || format!("'{}' is not an event id, like 2026-7327", s);
+        let (year, id) = s.split_once('-').ok_or_else(not_an_event_id)?;
+        Ok(EventId::new(
+            year.parse().map_err(|_| not_an_event_id())?,
+            id.parse().map_err(|_| not_an_event_id())?,
+        ))
+    }
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
 pub struct Event {
     pub id: EventId,
@@ -62,6 +79,99 @@ pub struct Event {
     pub slides: Vec<Url>,
     pub presenters: Vec<Person>,
     pub links: Vec<Link>,
+    // the fields below weren't imported at first, so are defaulted when reading older events.json
+    #[serde(default)]
+    pub event_type: EventType,
+    /// e.g. `en`, if the schedule says
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subtitle: Option<String>,
+    /// more detail than the abstract, which few events have
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub feedback_url: Option<Url>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub room_slug: Option<String>,
+    /// not in schedules before 2025
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub track_slug: Option<String>,
+}
+
+/// The kind of event, as given by the Pentabarf `<type>`
+#[derive(
+    Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, PartialOrd, Eq, Ord, Hash,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum EventType {
+    Keynote,
+    MainTrack,
+    Devroom,
+    LightningTalk,
+    Workshop,
+    Bof,
+    Junior,
+    /// anything else, including events imported before the type was
+    #[default]
+    #[serde(other)]
+    Other,
+}
+
+impl EventType {
+    pub const ALL: [EventType; 8] = [
+        EventType::Keynote,
+        EventType::MainTrack,
+        EventType::Devroom,
+        EventType::LightningTalk,
+        EventType::Workshop,
+        EventType::Bof,
+        EventType::Junior,
+        EventType::Other,
+    ];
+
+    /// e.g. "Lightning talk", where `Display` gives the Pentabarf form, `lightningtalk`
+    pub fn label(&self) -> &'static str {
+        match self {
+            EventType::Keynote => "Keynote",
+            EventType::MainTrack => "Main track",
+            EventType::Devroom => "Devroom",
+            EventType::LightningTalk => "Lightning talk",
+            EventType::Workshop => "Workshop",
+            EventType::Bof => "BoF",
+            EventType::Junior => "Junior",
+            EventType::Other => "Other",
+        }
+    }
+}
+
+impl Display for EventType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            EventType::Keynote => "keynote",
+            EventType::MainTrack => "maintrack",
+            EventType::Devroom => "devroom",
+            EventType::LightningTalk => "lightningtalk",
+            EventType::Workshop => "workshop",
+            EventType::Bof => "bof",
+            EventType::Junior => "junior",
+            EventType::Other => "other",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Parses the Pentabarf form, e.g. `lightningtalk`, ignoring case. Types we don't know are
+/// `Other`, so that a new one in a schedule doesn't stop it being imported.
+impl FromStr for EventType {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(EventType::ALL
+            .into_iter()
+            .find(|t| t.to_string().eq_ignore_ascii_case(s.trim()))
+            .unwrap_or(EventType::Other))
+    }
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq, PartialOrd, Eq, Ord, Hash, Copy)]
@@ -74,6 +184,15 @@ impl PersonId {
     pub fn new(year: u32, id: u32) -> PersonId {
         PersonId { year, id }
     }
+
+    pub const fn year(&self) -> u32 {
+        self.year
+    }
+
+    /// the Pentabarf person id, which is usually, but not always, the same across years
+    pub const fn person_in_year(&self) -> u32 {
+        self.id
+    }
 }
 
 impl Display for PersonId {
@@ -82,10 +201,54 @@ impl Display for PersonId {
     }
 }
 
+/// Parses the `Display` form, e.g. `2026-1324`
+impl FromStr for PersonId {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let not_a_person_id = || format!("'{}' is not a person id, like 2026-1324", s);
+        let (year, id) = s.split_once('-').ok_or_else(not_a_person_id)?;
+        Ok(PersonId::new(
+            year.parse().map_err(|_| not_a_person_id())?,
+            id.parse().map_err(|_| not_a_person_id())?,
+        ))
+    }
+}
+
+/// Identifies a presenter across all years, e.g. `karen-sandler`
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, PartialOrd, Eq, Ord, Hash)]
+pub struct GlobalPersonId(String);
+
+impl GlobalPersonId {
+    pub fn new(id: String) -> GlobalPersonId {
+        GlobalPersonId(id)
+    }
+
+    /// The normalised form of a name: lowercase, with anything other than letters and digits
+    /// collapsed into single dashes, so that `Karen  Sandler` and `karen sandler` are the same
+    pub fn from_name(name: &str) -> GlobalPersonId {
+        let words: Vec<String> = name
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(|word| word.to_lowercase())
+            .collect();
+        GlobalPersonId(words.join("-"))
+    }
+}
+
+impl Display for GlobalPersonId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
 pub struct Person {
     pub id: PersonId,
     pub name: String,
+    /// set on import, see `people::resolve_people`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub global_id: Option<GlobalPersonId>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
@@ -181,55 +344,183 @@ impl Event {
     pub fn has_video(&self) -> bool {
         self.mp4_video_link().is_some()
     }
+
+    pub fn has_slides(&self) -> bool {
+        !self.slides.is_empty()
+    }
+}
+
+/// A chunk of an event's slides or transcript, which is embedded and found separately, so that
+/// long content isn't cut short and we can say where in it something matched
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct Passage {
+    pub event_id: EventId,
+    pub location: PassageLocation,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PassageLocation {
+    /// starts this many seconds into the video
+    Transcript { start_seconds: u32 },
+    /// on this page of the slides, if the page is known
+    Slides { page: Option<u32> },
+}
+
+impl Passage {
+    /// Where the passage can be seen in context
+    pub fn url(&self, event: &Event) -> Option<String> {
+        match self.location {
+            PassageLocation::Transcript { start_seconds } => Some(format!(
+                "/{}/video/{}/#t={}",
+                self.event_id.year(),
+                self.event_id.event_in_year(),
+                start_seconds
+            )),
+            PassageLocation::Slides { page } => event.slides.first().map(|slides| match page {
+                Some(page) => format!("{}#page={}", slides, page),
+                None => slides.to_string(),
+            }),
+        }
+    }
+
+    /// e.g. "at 12:05" or "on slide page 3"
+    pub fn location_label(&self) -> String {
+        match self.location {
+            PassageLocation::Transcript { start_seconds } => {
+                format!("at {}:{:02}", start_seconds / 60, start_seconds % 60)
+            }
+            PassageLocation::Slides { page: Some(page) } => format!("on slide page {}", page),
+            PassageLocation::Slides { page: None } => "in slides".to_string(),
+        }
+    }
 }
 
 pub type OpenAIVector = DVector<f64>;
 
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PassageEmbedding {
+    pub passage: Passage,
+    pub embedding: OpenAIVector,
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct OpenAIEmbedding {
-    pub title: String,
+    pub event_id: EventId,
     pub embedding: OpenAIVector,
 }
 
 impl OpenAIEmbedding {
-    pub fn embedding_from_response(
+    /// The embeddings in `response`, in the order of the inputs they were made from
+    pub fn embeddings_from_response(
         response: &EmbeddingResponse,
-    ) -> Result<OpenAIVector, Box<dyn std::error::Error>> {
-        let output = response.data[0].embedding.clone();
-        match output {
-            EmbeddingOutput::Float(parts) => Ok(OpenAIVector::from(parts)),
-            EmbeddingOutput::Base64(base64) => {
-                Err(format!("Base64 encoding not supported: {}", base64).into())
-            }
-        }
+    ) -> Result<Vec<OpenAIVector>, Box<dyn std::error::Error>> {
+        let mut data: Vec<_> = response.data.iter().collect();
+        data.sort_by_key(|embedding| embedding.index);
+        data.into_iter()
+            .map(|embedding| match &embedding.embedding {
+                EmbeddingOutput::Float(parts) => Ok(OpenAIVector::from(parts.clone())),
+                EmbeddingOutput::Base64(base64) => {
+                    Err(format!("Base64 encoding not supported: {}", base64).into())
+                }
+            })
+            .collect()
+    }
+}
+
+/// An event with placeholder details, on the 1st of February of its year, for tests to override
+/// as they need, e.g. `Event { track: "Rust".to_string(), ..test_event(id, "Title") }`
+#[cfg(any(test, feature = "test-util"))]
+pub fn test_event(id: EventId, title: &str) -> Event {
+    Event {
+        id,
+        guid: format!("guid-{}", id),
+        year: id.year(),
+        date: NaiveDate::from_ymd_opt(id.year() as i32, 2, 1).unwrap(),
+        start: NaiveTime::from_hms_opt(10, 0, 0).unwrap(),
+        duration: 30,
+        room: "Room".to_string(),
+        track: "Track".to_string(),
+        title: title.to_string(),
+        slug: "slug".to_string(),
+        url: "https://example.com".parse().unwrap(),
+        r#abstract: "Abstract".to_string(),
+        slides: vec![],
+        presenters: vec![],
+        links: vec![],
+        event_type: EventType::Other,
+        language: None,
+        subtitle: None,
+        description: None,
+        feedback_url: None,
+        room_slug: None,
+        track_slug: None,
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use chrono::NaiveDate;
+
+    #[test]
+    fn test_event_id_parses_from_its_display_form() {
+        let event_id = EventId::new(2026, 7327);
+
+        assert_eq!(Ok(event_id), event_id.to_string().parse());
+        assert!("7327".parse::<EventId>().is_err());
+        assert!("2026-".parse::<EventId>().is_err());
+    }
+
+    #[test]
+    fn test_global_person_id_from_name_is_normalised() {
+        assert_eq!(
+            GlobalPersonId::from_name("Karen Sandler"),
+            GlobalPersonId::from_name("  karen   SANDLER ")
+        );
+        assert_eq!(
+            "richard-richih-hartmann",
+            GlobalPersonId::from_name("Richard \"RichiH\" Hartmann").to_string()
+        );
+    }
 
     fn make_event_with_links(links: Vec<Link>) -> Event {
         Event {
-            id: EventId::new(2024, 1),
-            guid: "guid".to_string(),
-            year: 2024,
-            date: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
-            start: NaiveTime::from_hms_opt(10, 0, 0).unwrap(),
-            duration: 30,
-            room: "Room".to_string(),
-            track: "Track".to_string(),
-            title: "Title".to_string(),
-            slug: "slug".to_string(),
-            url: "https://example.com".parse().unwrap(),
-            r#abstract: "Abstract".to_string(),
-            slides: vec![],
-            presenters: vec![],
             links,
+            ..test_event(EventId::new(2024, 1), "Title")
         }
     }
 
+    #[test]
+    fn test_event_type_parses_from_its_display_form() {
+        for event_type in EventType::ALL {
+            assert_eq!(Ok(event_type), event_type.to_string().parse());
+        }
+        assert_eq!(Ok(EventType::LightningTalk), "LightningTalk".parse());
+        assert_eq!(Ok(EventType::Other), "hackathon".parse());
+    }
+
+    #[test]
+    fn test_events_from_before_type_and_language_were_imported_can_be_read() {
+        let event = make_event_with_links(vec![]);
+        let mut json = serde_json::to_value(&event).unwrap();
+        let fields = json.as_object_mut().unwrap();
+        fields.remove("event_type");
+        assert!(!fields.contains_key("language"));
+
+        let read: Event = serde_json::from_value(json).unwrap();
+        assert_eq!(event, read);
+
+        let json = serde_json::json!({"event_type": "hackathon"});
+        let mut fields = serde_json::to_value(&event).unwrap();
+        fields
+            .as_object_mut()
+            .unwrap()
+            .extend(json.as_object().unwrap().clone());
+        let read: Event = serde_json::from_value(fields).unwrap();
+        assert_eq!(EventType::Other, read.event_type);
+    }
+
     #[test]
     fn test_mp4_video_link_exact_name_match() {
         let name = "Video recording";
@@ -275,4 +566,29 @@ mod tests {
 
         assert!(event.mp4_video_link().is_none(), "event: {:?}", event);
     }
+
+    #[test]
+    fn test_passage_links_into_video_or_slides() {
+        let mut event = make_event_with_links(vec![]);
+        event.slides = vec!["https://fosdem.org/slides.pdf".parse().unwrap()];
+        let in_transcript = Passage {
+            event_id: event.id,
+            location: PassageLocation::Transcript { start_seconds: 725 },
+            text: "text".to_string(),
+        };
+        let in_slides = Passage {
+            location: PassageLocation::Slides { page: Some(3) },
+            ..in_transcript.clone()
+        };
+
+        assert_eq!(
+            Some("/2024/video/1/#t=725".to_string()),
+            in_transcript.url(&event)
+        );
+        assert_eq!("at 12:05", in_transcript.location_label());
+        assert_eq!(
+            Some("https://fosdem.org/slides.pdf#page=3".to_string()),
+            in_slides.url(&event)
+        );
+    }
 }
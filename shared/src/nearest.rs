@@ -0,0 +1,452 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashSet};
+
+use tracing::{debug, info};
+
+/// How nearest neighbours are found amongst embedded events
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IndexConfig {
+    /// compare against every event; exact, but cost grows with the number of events
+    BruteForce,
+    /// Hierarchical Navigable Small World graph; approximate, but much cheaper per query
+    Hnsw(HnswConfig),
+}
+
+impl Default for IndexConfig {
+    fn default() -> Self {
+        IndexConfig::Hnsw(HnswConfig::default())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HnswConfig {
+    /// max neighbours kept per node on each layer (twice this on the bottom layer)
+    pub m: usize,
+    /// size of the candidate list used when building; higher gives a better graph, slower
+    pub ef_construction: usize,
+    /// size of the candidate list used when searching; higher gives better recall, slower
+    pub ef_search: usize,
+}
+
+impl Default for HnswConfig {
+    fn default() -> Self {
+        HnswConfig {
+            m: 16,
+            ef_construction: 100,
+            ef_search: 64,
+        }
+    }
+}
+
+/// An index over `size` vectors, which are identified only by their position. The vectors
+/// themselves are owned elsewhere, and are only accessed through the distance functions given.
+#[derive(Debug)]
+pub enum NearestNeighbourIndex {
+    BruteForce { size: usize },
+    Hnsw(Hnsw),
+}
+
+impl NearestNeighbourIndex {
+    pub fn build<D>(config: IndexConfig, size: usize, distance_between: D) -> NearestNeighbourIndex
+    where
+        D: Fn(usize, usize) -> f64,
+    {
+        match config {
+            IndexConfig::BruteForce => NearestNeighbourIndex::BruteForce { size },
+            IndexConfig::Hnsw(config) => {
+                info!("Building HNSW index over {} vectors, {:?}", size, config);
+                NearestNeighbourIndex::Hnsw(Hnsw::build(config, size, distance_between))
+            }
+        }
+    }
+
+    /// Find the `k` nearest positions to some query, where `distance` gives the distance from
+    /// the query to a position. Only positions for which `include` is true are returned.
+    pub fn search<D, F>(&self, k: usize, distance: D, include: F) -> Vec<(usize, f64)>
+    where
+        D: Fn(usize) -> f64,
+        F: Fn(usize) -> bool,
+    {
+        match self {
+            NearestNeighbourIndex::BruteForce { size } => {
+                brute_force_search(*size, k, distance, include)
+            }
+            NearestNeighbourIndex::Hnsw(hnsw) => hnsw.search(k, distance, include),
+        }
+    }
+}
+
+fn brute_force_search<D, F>(size: usize, k: usize, distance: D, include: F) -> Vec<(usize, f64)>
+where
+    D: Fn(usize) -> f64,
+    F: Fn(usize) -> bool,
+{
+    let mut entries: Vec<(usize, f64)> = (0..size)
+        .filter(|i| include(*i))
+        .map(|i| (i, distance(i)))
+        .collect();
+    entries.sort_by(|a, b| a.1.total_cmp(&b.1));
+    entries.truncate(k);
+    entries
+}
+
+#[derive(Debug)]
+pub struct Hnsw {
+    config: HnswConfig,
+    // neighbours[node][layer]
+    neighbours: Vec<Vec<Vec<usize>>>,
+    entry_point: Option<usize>,
+    top_layer: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Candidate {
+    distance: f64,
+    position: usize,
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance
+            .total_cmp(&other.distance)
+            .then(self.position.cmp(&other.position))
+    }
+}
+
+impl Hnsw {
+    fn build<D>(config: HnswConfig, size: usize, distance_between: D) -> Hnsw
+    where
+        D: Fn(usize, usize) -> f64,
+    {
+        let mut hnsw = Hnsw {
+            config,
+            neighbours: Vec::with_capacity(size),
+            entry_point: None,
+            top_layer: 0,
+        };
+        // a fixed seed, so the same data always gives the same graph
+        let mut rng = XorShift(0x2545f4914f6cdd1d);
+        let level_multiplier = 1.0 / (config.m.max(2) as f64).ln();
+        for position in 0..size {
+            let level = (-rng.next_f64().ln() * level_multiplier).floor() as usize;
+            hnsw.insert(position, level, &distance_between);
+        }
+        hnsw
+    }
+
+    fn max_neighbours(&self, layer: usize) -> usize {
+        if layer == 0 {
+            self.config.m * 2
+        } else {
+            self.config.m
+        }
+    }
+
+    fn insert<D>(&mut self, position: usize, level: usize, distance_between: &D)
+    where
+        D: Fn(usize, usize) -> f64,
+    {
+        self.neighbours.push(vec![vec![]; level + 1]);
+        let Some(mut entry_point) = self.entry_point else {
+            self.entry_point = Some(position);
+            self.top_layer = level;
+            return;
+        };
+
+        let distance = |other: usize| distance_between(position, other);
+        for layer in ((level + 1)..=self.top_layer).rev() {
+            entry_point = self.greedy_closest(entry_point, layer, &distance);
+        }
+
+        let mut entry_points = vec![entry_point];
+        for layer in (0..=level.min(self.top_layer)).rev() {
+            let candidates =
+                self.search_layer(&entry_points, self.config.ef_construction, layer, &distance);
+            let selected = select_neighbours(&candidates, self.config.m, distance_between);
+            for &neighbour in &selected {
+                self.neighbours[neighbour][layer].push(position);
+                self.prune(neighbour, layer, distance_between);
+            }
+            self.neighbours[position][layer] = selected;
+            entry_points = candidates.iter().map(|c| c.position).collect();
+        }
+
+        if level > self.top_layer {
+            self.top_layer = level;
+            self.entry_point = Some(position);
+        }
+    }
+
+    // keep only the closest links of `node` once it has too many
+    fn prune<D>(&mut self, node: usize, layer: usize, distance_between: &D)
+    where
+        D: Fn(usize, usize) -> f64,
+    {
+        let max = self.max_neighbours(layer);
+        let links = &mut self.neighbours[node][layer];
+        if links.len() <= max {
+            return;
+        }
+        let mut candidates: Vec<Candidate> = links
+            .iter()
+            .map(|&position| Candidate {
+                distance: distance_between(node, position),
+                position,
+            })
+            .collect();
+        candidates.sort();
+        *links = candidates.iter().take(max).map(|c| c.position).collect();
+    }
+
+    fn greedy_closest<D>(&self, start: usize, layer: usize, distance: &D) -> usize
+    where
+        D: Fn(usize) -> f64,
+    {
+        let mut current = start;
+        let mut current_distance = distance(current);
+        loop {
+            let mut improved = false;
+            for &neighbour in &self.neighbours[current][layer] {
+                let d = distance(neighbour);
+                if d < current_distance {
+                    current = neighbour;
+                    current_distance = d;
+                    improved = true;
+                }
+            }
+            if !improved {
+                return current;
+            }
+        }
+    }
+
+    // returns up to `ef` closest candidates found on `layer`, closest first
+    fn search_layer<D>(
+        &self,
+        entry_points: &[usize],
+        ef: usize,
+        layer: usize,
+        distance: &D,
+    ) -> Vec<Candidate>
+    where
+        D: Fn(usize) -> f64,
+    {
+        let mut visited: HashSet<usize> = entry_points.iter().cloned().collect();
+        let mut candidates: BinaryHeap<Reverse<Candidate>> = BinaryHeap::new();
+        let mut found: BinaryHeap<Candidate> = BinaryHeap::new();
+        for &position in entry_points {
+            let candidate = Candidate {
+                distance: distance(position),
+                position,
+            };
+            candidates.push(Reverse(candidate));
+            found.push(candidate);
+        }
+        while found.len() > ef {
+            found.pop();
+        }
+
+        while let Some(Reverse(closest)) = candidates.pop() {
+            let furthest_found = found.peek().map_or(f64::INFINITY, |c| c.distance);
+            if closest.distance > furthest_found && found.len() >= ef {
+                break;
+            }
+            for &neighbour in &self.neighbours[closest.position][layer] {
+                if !visited.insert(neighbour) {
+                    continue;
+                }
+                let d = distance(neighbour);
+                let furthest_found = found.peek().map_or(f64::INFINITY, |c| c.distance);
+                if found.len() < ef || d < furthest_found {
+                    let candidate = Candidate {
+                        distance: d,
+                        position: neighbour,
+                    };
+                    candidates.push(Reverse(candidate));
+                    found.push(candidate);
+                    if found.len() > ef {
+                        found.pop();
+                    }
+                }
+            }
+        }
+
+        found.into_sorted_vec()
+    }
+
+    fn search<D, F>(&self, k: usize, distance: D, include: F) -> Vec<(usize, f64)>
+    where
+        D: Fn(usize) -> f64,
+        F: Fn(usize) -> bool,
+    {
+        let Some(mut entry_point) = self.entry_point else {
+            return vec![];
+        };
+        for layer in (1..=self.top_layer).rev() {
+            entry_point = self.greedy_closest(entry_point, layer, &distance);
+        }
+
+        // when filtering, widen the search until as many candidates pass the filter as an
+        // unfiltered search would rank, so that filtering doesn't cost recall, and if the whole
+        // graph would need visiting anyway, just check everything
+        let size = self.neighbours.len();
+        let wanted = self.config.ef_search.max(k);
+        let mut ef = wanted;
+        loop {
+            let found: Vec<(usize, f64)> = self
+                .search_layer(&[entry_point], ef, 0, &distance)
+                .into_iter()
+                .filter(|c| include(c.position))
+                .map(|c| (c.position, c.distance))
+                .collect();
+            if found.len() >= wanted {
+                return found.into_iter().take(k).collect();
+            }
+            if ef >= size {
+                debug!("HNSW search exhausted, falling back to brute force");
+                return brute_force_search(size, k, distance, include);
+            }
+            ef = (ef * 4).min(size);
+        }
+    }
+}
+
+// The heuristic from the HNSW paper: a candidate is only linked if it is closer to the node than
+// to anything already linked. This keeps links pointing in different directions, which keeps
+// clustered data connected. Any remaining room is then filled with the closest of the rest.
+fn select_neighbours<D>(candidates: &[Candidate], max: usize, distance_between: &D) -> Vec<usize>
+where
+    D: Fn(usize, usize) -> f64,
+{
+    let mut selected: Vec<usize> = Vec::with_capacity(max);
+    let mut skipped: Vec<usize> = vec![];
+    for candidate in candidates {
+        if selected.len() >= max {
+            break;
+        }
+        let is_diverse = selected
+            .iter()
+            .all(|&s| candidate.distance < distance_between(candidate.position, s));
+        if is_diverse {
+            selected.push(candidate.position);
+        } else {
+            skipped.push(candidate.position);
+        }
+    }
+    for position in skipped {
+        if selected.len() >= max {
+            break;
+        }
+        selected.push(position);
+    }
+    selected
+}
+
+/// A small, fast, seedable source of pseudo-random numbers, for when the same input should always
+/// give the same output
+pub struct XorShift(u64);
+
+impl XorShift {
+    /// `seed` must not be zero
+    pub fn new(seed: u64) -> XorShift {
+        XorShift(seed)
+    }
+
+    pub fn next_f64(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        // top 53 bits, shifted into (0, 1]
+        ((self.0 >> 11) as f64 + 1.0) / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::OpenAIVector;
+
+    fn random_vectors(count: usize, dimensions: usize) -> Vec<OpenAIVector> {
+        let mut rng = XorShift(42);
+        (0..count)
+            .map(|_| {
+                let v = OpenAIVector::from_fn(dimensions, |_, _| rng.next_f64() - 0.5);
+                v.normalize()
+            })
+            .collect()
+    }
+
+    fn top_k(
+        index: &NearestNeighbourIndex,
+        vectors: &[OpenAIVector],
+        query: usize,
+        k: usize,
+    ) -> Vec<usize> {
+        index
+            .search(
+                k,
+                |i| vectors[query].metric_distance(&vectors[i]),
+                |i| i != query,
+            )
+            .into_iter()
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    #[test]
+    fn test_hnsw_matches_brute_force() {
+        let vectors = random_vectors(500, 16);
+        let distance_between = |a: usize, b: usize| vectors[a].metric_distance(&vectors[b]);
+        let brute_force =
+            NearestNeighbourIndex::build(IndexConfig::BruteForce, vectors.len(), distance_between);
+        let hnsw = NearestNeighbourIndex::build(
+            IndexConfig::Hnsw(HnswConfig {
+                ef_search: 200,
+                ..HnswConfig::default()
+            }),
+            vectors.len(),
+            distance_between,
+        );
+
+        for query in (0..vectors.len()).step_by(25) {
+            assert_eq!(
+                top_k(&brute_force, &vectors, query, 10),
+                top_k(&hnsw, &vectors, query, 10),
+                "query: {}",
+                query
+            );
+        }
+    }
+
+    #[test]
+    fn test_hnsw_respects_filter() {
+        let vectors = random_vectors(300, 8);
+        let hnsw = NearestNeighbourIndex::build(IndexConfig::default(), vectors.len(), |a, b| {
+            vectors[a].metric_distance(&vectors[b])
+        });
+
+        let found = hnsw.search(
+            5,
+            |i| vectors[0].metric_distance(&vectors[i]),
+            |i| i % 7 == 0,
+        );
+
+        assert_eq!(5, found.len());
+        assert!(found.iter().all(|(i, _)| i % 7 == 0));
+    }
+
+    #[test]
+    fn test_search_on_empty_index() {
+        let hnsw = NearestNeighbourIndex::build(IndexConfig::default(), 0, |_, _| 0.0);
+
+        assert!(hnsw.search(5, |_| 0.0, |_| true).is_empty());
+    }
+}
@@ -0,0 +1,110 @@
+use crate::embedding::EmbeddingProvider;
+use crate::model::OpenAIVector;
+
+pub const OFFLINE_EMBEDDING_MODEL: &str = "offline-hashed-v1";
+pub const OFFLINE_EMBEDDING_DIMENSIONS: usize = 1536;
+
+/// Deterministic embeddings made by hashing words and character trigrams into a fixed number
+/// of buckets. This knows nothing about meaning, but texts which share vocabulary end up close
+/// together, which is enough to develop and test against without an API key.
+#[derive(Debug, Clone)]
+pub struct OfflineEmbeddingProvider {
+    dimensions: usize,
+}
+
+impl Default for OfflineEmbeddingProvider {
+    fn default() -> Self {
+        OfflineEmbeddingProvider::new(OFFLINE_EMBEDDING_DIMENSIONS)
+    }
+}
+
+impl OfflineEmbeddingProvider {
+    pub fn new(dimensions: usize) -> OfflineEmbeddingProvider {
+        OfflineEmbeddingProvider { dimensions }
+    }
+
+    pub fn embed_sync(&self, input: &str) -> OpenAIVector {
+        let mut vector = OpenAIVector::zeros(self.dimensions);
+        for word in input
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .map(|w| w.to_lowercase())
+        {
+            self.add_feature(&mut vector, word.as_bytes(), 1.0);
+            let padded: Vec<char> = format!("^{}$", word).chars().collect();
+            for trigram in padded.windows(3) {
+                let trigram: String = trigram.iter().collect();
+                self.add_feature(&mut vector, trigram.as_bytes(), 0.5);
+            }
+        }
+        let norm = vector.norm();
+        if norm > 0.0 {
+            vector /= norm;
+        }
+        vector
+    }
+
+    fn add_feature(&self, vector: &mut OpenAIVector, feature: &[u8], weight: f64) {
+        let hash = fnv1a(feature);
+        let bucket = (hash % self.dimensions as u64) as usize;
+        let sign = if (hash >> 63) == 0 { 1.0 } else { -1.0 };
+        vector[bucket] += sign * weight;
+    }
+}
+
+impl EmbeddingProvider for OfflineEmbeddingProvider {
+    fn model(&self) -> &str {
+        OFFLINE_EMBEDDING_MODEL
+    }
+
+    async fn embed(&self, input: &str) -> Result<OpenAIVector, Box<dyn std::error::Error>> {
+        Ok(self.embed_sync(input))
+    }
+}
+
+// FNV-1a is used rather than `DefaultHasher`, as the latter may change between Rust releases
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_input_same_embedding() {
+        let provider = OfflineEmbeddingProvider::default();
+        assert_eq!(
+            provider.embed_sync("GNOME OS on phones"),
+            provider.embed_sync("GNOME OS on phones")
+        );
+    }
+
+    #[test]
+    fn test_embedding_is_normalised() {
+        let provider = OfflineEmbeddingProvider::default();
+        let embedding = provider.embed_sync("Rust in the Linux kernel");
+        assert_eq!(OFFLINE_EMBEDDING_DIMENSIONS, embedding.len());
+        assert!((embedding.norm() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_empty_input_is_zero() {
+        let provider = OfflineEmbeddingProvider::new(8);
+        assert_eq!(OpenAIVector::zeros(8), provider.embed_sync(" - "));
+    }
+
+    #[test]
+    fn test_shared_vocabulary_is_closer() {
+        let provider = OfflineEmbeddingProvider::default();
+        let query = provider.embed_sync("gnome desktop testing");
+        let similar = provider.embed_sync("End-to-end testing of the GNOME desktop with openQA");
+        let dissimilar = provider.embed_sync("Bioinformatics pipelines for genome assembly");
+        assert!(query.metric_distance(&similar) < query.metric_distance(&dissimilar));
+    }
+}
@@ -1,22 +1,240 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
 use openai_dive::v1::{
     api::Client,
     resources::embedding::{EmbeddingInput, EmbeddingParameters, EmbeddingResponse},
 };
+use reqwest::header::{HeaderMap, CONTENT_TYPE, RETRY_AFTER};
+use reqwest::StatusCode;
+use url::Url;
+
+use crate::embedding::EmbeddingProvider;
+use crate::error::EmbeddingRequestError;
+use crate::model::{OpenAIEmbedding, OpenAIVector};
+
+pub const OPENAI_EMBEDDING_MODEL: &str = "text-embedding-ada-002";
+
+#[derive(Debug)]
+pub struct OpenAIEmbeddingProvider {
+    client: Client,
+}
+
+impl OpenAIEmbeddingProvider {
+    pub fn new(
+        api_key: &str,
+        timeout: Option<Duration>,
+    ) -> Result<OpenAIEmbeddingProvider, Box<dyn std::error::Error>> {
+        Ok(OpenAIEmbeddingProvider {
+            client: client(api_key, timeout)?,
+        })
+    }
+}
+
+impl EmbeddingProvider for OpenAIEmbeddingProvider {
+    fn model(&self) -> &str {
+        OPENAI_EMBEDDING_MODEL
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn embed(&self, input: &str) -> Result<OpenAIVector, Box<dyn std::error::Error>> {
+        first(create_embeddings(&self.client, OPENAI_EMBEDDING_MODEL, &[input.to_string()]).await?)
+    }
+
+    #[tracing::instrument(skip_all, fields(inputs = inputs.len()))]
+    async fn embed_batch(
+        &self,
+        inputs: &[String],
+    ) -> Result<Vec<OpenAIVector>, Box<dyn std::error::Error>> {
+        create_embeddings(&self.client, OPENAI_EMBEDDING_MODEL, inputs).await
+    }
+}
+
+/// Talks to anything which implements the OpenAI embeddings API at `base_url`, such as a
+/// locally-running model server
+#[derive(Debug)]
+pub struct OpenAICompatibleEmbeddingProvider {
+    client: Client,
+    model: String,
+}
+
+impl OpenAICompatibleEmbeddingProvider {
+    pub fn new(
+        base_url: &Url,
+        model: &str,
+        api_key: &str,
+        timeout: Option<Duration>,
+    ) -> Result<OpenAICompatibleEmbeddingProvider, Box<dyn std::error::Error>> {
+        let mut client = client(api_key, timeout)?;
+        client.set_base_url(base_url.as_str().trim_end_matches('/'));
+        Ok(OpenAICompatibleEmbeddingProvider {
+            client,
+            model: model.to_string(),
+        })
+    }
+}
+
+impl EmbeddingProvider for OpenAICompatibleEmbeddingProvider {
+    fn model(&self) -> &str {
+        &self.model
+    }
 
-#[tracing::instrument(skip(client))]
-pub async fn get_embedding(
+    #[tracing::instrument(skip(self))]
+    async fn embed(&self, input: &str) -> Result<OpenAIVector, Box<dyn std::error::Error>> {
+        first(create_embeddings(&self.client, &self.model, &[input.to_string()]).await?)
+    }
+
+    #[tracing::instrument(skip_all, fields(inputs = inputs.len()))]
+    async fn embed_batch(
+        &self,
+        inputs: &[String],
+    ) -> Result<Vec<OpenAIVector>, Box<dyn std::error::Error>> {
+        create_embeddings(&self.client, &self.model, inputs).await
+    }
+}
+
+fn client(api_key: &str, timeout: Option<Duration>) -> Result<Client, Box<dyn std::error::Error>> {
+    let mut builder = reqwest::ClientBuilder::new();
+    if let Some(timeout) = timeout {
+        builder = builder.timeout(timeout);
+    }
+    Ok(Client {
+        api_key: api_key.to_string(),
+        http_client: builder.build()?,
+        ..Default::default()
+    })
+}
+
+/// Embeds all of `inputs` in one request. This talks to the endpoint directly, rather than
+/// through `client.embeddings()`, so that rate limiting and server errors can be told apart from
+/// other failures, and `Retry-After` is available to whoever retries.
+async fn create_embeddings(
     client: &Client,
-    input: &str,
-) -> Result<EmbeddingResponse, Box<dyn std::error::Error>> {
+    model: &str,
+    inputs: &[String],
+) -> Result<Vec<OpenAIVector>, Box<dyn std::error::Error>> {
     let parameters = EmbeddingParameters {
-        model: "text-embedding-ada-002".to_string(),
-        input: EmbeddingInput::String(input.to_string()),
+        model: model.to_string(),
+        input: EmbeddingInput::StringArray(inputs.to_vec()),
         encoding_format: None,
         user: None,
         dimensions: None,
     };
 
-    let response = client.embeddings().create(parameters).await.unwrap();
+    let response = client
+        .http_client
+        .post(format!("{}/embeddings", client.base_url))
+        .bearer_auth(&client.api_key)
+        .header(CONTENT_TYPE, "application/json")
+        .body(serde_json::to_vec(&parameters)?)
+        .send()
+        .await
+        .map_err(|e| EmbeddingRequestError::Retryable {
+            status: None,
+            retry_after: None,
+            message: e.to_string(),
+        })?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let retry_after = retry_after(response.headers(), Utc::now());
+        let message = response.text().await.unwrap_or_default();
+        return Err(
+            if status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error() {
+                EmbeddingRequestError::Retryable {
+                    status: Some(status.as_u16()),
+                    retry_after,
+                    message,
+                }
+            } else {
+                EmbeddingRequestError::Failed {
+                    status: status.as_u16(),
+                    message,
+                }
+            }
+            .into(),
+        );
+    }
+
+    let response: EmbeddingResponse = serde_json::from_slice(&response.bytes().await?)?;
+    let embeddings = OpenAIEmbedding::embeddings_from_response(&response)?;
+    if embeddings.len() != inputs.len() {
+        return Err(format!(
+            "asked for {} embeddings, but got {}",
+            inputs.len(),
+            embeddings.len()
+        )
+        .into());
+    }
+    Ok(embeddings)
+}
+
+fn first(embeddings: Vec<OpenAIVector>) -> Result<OpenAIVector, Box<dyn std::error::Error>> {
+    embeddings
+        .into_iter()
+        .next()
+        .ok_or_else(|| "no embedding in response".into())
+}
+
+/// How long the server asked us to wait, from either OpenAI's `retry-after-ms` or the standard
+/// `Retry-After`, which is either a number of seconds or a date
+fn retry_after(headers: &HeaderMap, now: DateTime<Utc>) -> Option<Duration> {
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+    if let Some(millis) = header("retry-after-ms").and_then(|value| value.trim().parse().ok()) {
+        return Some(Duration::from_millis(millis));
+    }
+    let value = header(RETRY_AFTER.as_str())?.trim();
+    if let Ok(seconds) = value.parse::<f64>() {
+        return Duration::try_from_secs_f64(seconds).ok();
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        (date.with_timezone(&Utc) - now)
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn headers(name: &'static str, value: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(name, HeaderValue::from_static(value));
+        headers
+    }
+
+    #[test]
+    fn test_retry_after_in_seconds_or_as_a_date() {
+        let now = DateTime::parse_from_rfc2822("Sat, 17 Oct 2026 07:28:00 GMT")
+            .unwrap()
+            .with_timezone(&Utc);
 
-    Ok(response)
+        assert_eq!(
+            Some(Duration::from_secs(20)),
+            retry_after(&headers("retry-after", "20"), now)
+        );
+        assert_eq!(
+            Some(Duration::from_millis(1500)),
+            retry_after(&headers("retry-after-ms", "1500"), now)
+        );
+        assert_eq!(
+            Some(Duration::from_secs(90)),
+            retry_after(
+                &headers("retry-after", "Sat, 17 Oct 2026 07:29:30 GMT"),
+                now
+            )
+        );
+        assert_eq!(
+            Some(Duration::ZERO),
+            retry_after(
+                &headers("retry-after", "Sat, 17 Oct 2026 07:27:00 GMT"),
+                now
+            )
+        );
+        assert_eq!(None, retry_after(&headers("retry-after", "soon"), now));
+        assert_eq!(None, retry_after(&HeaderMap::new(), now));
+    }
 }
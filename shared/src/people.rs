@@ -0,0 +1,187 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::Path;
+
+use crate::model::{Event, GlobalPersonId, PersonId};
+
+/// Presenters whose global identity is set by hand rather than matched, e.g. where a Pentabarf
+/// person id was reused for someone else, or where someone has changed their name
+pub type PeopleOverrides = HashMap<PersonId, GlobalPersonId>;
+
+/// Loads overrides from a JSON object of year-scoped person ids to global ids, e.g.
+/// `{"2025-2028": "rizki-kelimutu"}`
+pub fn load_overrides(path: &Path) -> io::Result<PeopleOverrides> {
+    let overrides: HashMap<String, GlobalPersonId> =
+        serde_json::from_reader(BufReader::new(File::open(path)?))?;
+    overrides
+        .into_iter()
+        .map(|(person_id, global_id)| {
+            let person_id = person_id
+                .parse()
+                .map_err(|e: String| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            Ok((person_id, global_id))
+        })
+        .collect()
+}
+
+/// Sets `global_id` on every presenter, returning how many distinct people there are.
+///
+/// Presenters are the same person if they share a normalised name or a Pentabarf person id,
+/// directly or through other presenters. Overridden presenters are only matched by their
+/// override. A person's id is the normalised form of the name they used most recently, so an
+/// override can also join someone to a matched person by using that person's id.
+pub fn resolve_people(events: &mut [Event], overrides: &PeopleOverrides) -> usize {
+    let mut presenters: Vec<(PersonId, &str)> = events
+        .iter()
+        .flat_map(|e| e.presenters.iter().map(|p| (p.id, p.name.as_str())))
+        .collect();
+    presenters.sort();
+    presenters.dedup_by_key(|(person_id, _)| *person_id);
+
+    let mut parents: Vec<usize> = (0..presenters.len()).collect();
+    let mut by_name: HashMap<GlobalPersonId, usize> = HashMap::new();
+    let mut by_pentabarf_id: HashMap<u32, usize> = HashMap::new();
+    for (i, (person_id, name)) in presenters.iter().enumerate() {
+        if overrides.contains_key(person_id) {
+            continue;
+        }
+        if let Some(name_id) = name_id(name) {
+            let same_name = *by_name.entry(name_id).or_insert(i);
+            union(&mut parents, same_name, i);
+        }
+        let same_pentabarf_id = *by_pentabarf_id
+            .entry(person_id.person_in_year())
+            .or_insert(i);
+        union(&mut parents, same_pentabarf_id, i);
+    }
+
+    // presenters are sorted by year, so the last one seen in each group is the most recent
+    let mut latest: HashMap<usize, usize> = HashMap::new();
+    for i in 0..presenters.len() {
+        latest.insert(find(&mut parents, i), i);
+    }
+    let mut global_ids: HashMap<PersonId, GlobalPersonId> = HashMap::new();
+    for (i, (person_id, _)) in presenters.iter().enumerate() {
+        let global_id = match overrides.get(person_id) {
+            Some(global_id) => global_id.clone(),
+            None => {
+                let (latest_person_id, latest_name) = presenters[latest[&find(&mut parents, i)]];
+                name_id(latest_name)
+                    .unwrap_or_else(|| GlobalPersonId::new(latest_person_id.to_string()))
+            }
+        };
+        global_ids.insert(*person_id, global_id);
+    }
+
+    for event in events.iter_mut() {
+        for presenter in event.presenters.iter_mut() {
+            presenter.global_id = global_ids.get(&presenter.id).cloned();
+        }
+    }
+    global_ids.values().collect::<HashSet<_>>().len()
+}
+
+/// a name with no letters or digits in it can't identify anyone
+fn name_id(name: &str) -> Option<GlobalPersonId> {
+    let global_id = GlobalPersonId::from_name(name);
+    if global_id.to_string().is_empty() {
+        None
+    } else {
+        Some(global_id)
+    }
+}
+
+fn find(parents: &mut [usize], i: usize) -> usize {
+    let mut root = i;
+    while parents[root] != root {
+        root = parents[root];
+    }
+    let mut current = i;
+    while parents[current] != root {
+        let next = parents[current];
+        parents[current] = root;
+        current = next;
+    }
+    root
+}
+
+fn union(parents: &mut [usize], a: usize, b: usize) {
+    let (a, b) = (find(parents, a), find(parents, b));
+    if a != b {
+        parents[b] = a;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{test_event, EventId, Person};
+
+    fn event_presented_by(year: u32, presenters: &[(u32, &str)]) -> Event {
+        Event {
+            room: "H.1302".to_string(),
+            track: "Rust".to_string(),
+            presenters: presenters
+                .iter()
+                .map(|(id, name)| Person {
+                    id: PersonId::new(year, *id),
+                    name: name.to_string(),
+                    global_id: None,
+                })
+                .collect(),
+            ..test_event(EventId::new(year, 1), "Title")
+        }
+    }
+
+    fn global_ids(events: &[Event]) -> Vec<String> {
+        events
+            .iter()
+            .flat_map(|e| &e.presenters)
+            .map(|p| p.global_id.as_ref().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_same_pentabarf_id_is_same_person_named_as_most_recently() {
+        let mut events = vec![
+            event_presented_by(2024, &[(1, "Aeva Black")]),
+            event_presented_by(2025, &[(1, "Æva Black")]),
+        ];
+
+        assert_eq!(1, resolve_people(&mut events, &PeopleOverrides::new()));
+        assert_eq!(vec!["æva-black", "æva-black"], global_ids(&events));
+    }
+
+    #[test]
+    fn test_same_name_is_same_person() {
+        let mut events = vec![
+            event_presented_by(2024, &[(1, "Karen Sandler"), (2, "Someone Else")]),
+            event_presented_by(2026, &[(3, "karen  sandler")]),
+        ];
+
+        assert_eq!(2, resolve_people(&mut events, &PeopleOverrides::new()));
+        assert_eq!(
+            vec!["karen-sandler", "someone-else", "karen-sandler"],
+            global_ids(&events)
+        );
+    }
+
+    #[test]
+    fn test_override_separates_reused_pentabarf_id() {
+        let mut events = vec![
+            event_presented_by(2024, &[(7, "Mitchell Baker")]),
+            event_presented_by(2025, &[(7, "Rizki Kelimutu (Kiki)")]),
+        ];
+        let overrides = PeopleOverrides::from([(
+            PersonId::new(2025, 7),
+            GlobalPersonId::new("rizki-kelimutu".to_string()),
+        )]);
+
+        assert_eq!(2, resolve_people(&mut events, &overrides));
+        assert_eq!(
+            vec!["mitchell-baker", "rizki-kelimutu"],
+            global_ids(&events)
+        );
+    }
+}
@@ -1,28 +1,91 @@
+use std::fmt::{Display, Formatter};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::QueryError;
+use crate::facets::Facets;
+use crate::filter::SearchFilter;
 use crate::model::{Event, EventId, SearchItem};
 
 pub const MAX_RELATED_EVENTS: u8 = 5;
 
+/// how many of the best matches for a search its facets are counted over; semantic search ranks
+/// every event, so there's no natural end to its matches
+pub const FACETED_RESULTS: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchMode {
+    /// match on the words in events, which is best for exact names
+    Lexical,
+    /// match on embeddings, which is best for fuzzier topics
+    Semantic,
+    /// both of the above, with rankings combined
+    #[default]
+    Hybrid,
+}
+
+impl SearchMode {
+    pub const ALL: [SearchMode; 3] = [
+        SearchMode::Hybrid,
+        SearchMode::Semantic,
+        SearchMode::Lexical,
+    ];
+}
+
+impl Display for SearchMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SearchMode::Lexical => write!(f, "lexical"),
+            SearchMode::Semantic => write!(f, "semantic"),
+            SearchMode::Hybrid => write!(f, "hybrid"),
+        }
+    }
+}
+
+/// What a search found
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchResults {
+    /// the best matches, no more than were asked for
+    pub items: Vec<SearchItem>,
+    /// counted over the best `FACETED_RESULTS` matches, not just `items`, so that the counts
+    /// aren't cut off at the page, and refinements further down are offered too
+    pub facets: Facets,
+}
+
 #[allow(async_fn_in_trait)]
 pub trait Queryable {
-    async fn load_all_events(&self) -> Result<Vec<Event>, Box<dyn std::error::Error>>;
+    async fn load_all_events(&self) -> Result<Vec<Event>, QueryError>;
 
-    async fn find_event_by_id(
-        &self,
-        event_id: EventId,
-    ) -> Result<Option<Event>, Box<dyn std::error::Error>>;
+    async fn find_event_by_id(&self, event_id: EventId) -> Result<Option<Event>, QueryError>;
 
     async fn find_related_events(
         &self,
-        title: &str,
+        event_id: EventId,
         limit: u8,
-        year_filter: Option<u32>,
-    ) -> Result<Vec<SearchItem>, Box<dyn std::error::Error>>;
+        filter: &SearchFilter,
+        mmr_lambda: f64,
+    ) -> Result<Vec<SearchItem>, QueryError>;
 
+    /// `mmr_lambda` trades relevance (1.0) against covering more of the schedule (lower), see
+    /// `rerank::maximal_marginal_relevance`; related events, if found, use the same value
     async fn search(
         &self,
         query: &str,
         limit: u8,
         find_related: bool,
-        year_filter: Option<u32>,
-    ) -> Result<Vec<SearchItem>, Box<dyn std::error::Error>>;
+        filter: &SearchFilter,
+        mode: SearchMode,
+        mmr_lambda: f64,
+    ) -> Result<SearchResults, QueryError>;
+
+    /// Events like all of the `seeds` (e.g. someone's bookmarks), other than the seeds
+    /// themselves and anything in `exclude`. Seeds which aren't known are ignored.
+    async fn recommend(
+        &self,
+        seeds: &[EventId],
+        exclude: &[EventId],
+        filter: &SearchFilter,
+        limit: u8,
+    ) -> Result<Vec<SearchItem>, QueryError>;
 }
@@ -0,0 +1,97 @@
+/// Balances relevance against diversity: results stay mostly in relevance order, but a result
+/// very like one already chosen gives way to a slightly less relevant one which isn't
+pub const DEFAULT_MMR_LAMBDA: f64 = 0.7;
+
+/// how many candidates to re-rank, relative to the number of results wanted
+pub const MMR_CANDIDATES_PER_RESULT: usize = 3;
+
+/// Maximal Marginal Relevance: pick `limit` of `candidates`, each given with its relevance to
+/// the query, from 0.0 to 1.0, one at a time. Each pick maximises
+/// `lambda * relevance - (1 - lambda) * similarity to the closest already picked`, where
+/// similarity is `1 - distance_between`, so on the same scale as relevance.
+/// So `lambda` of 1.0 is plain relevance order, and 0.0 ignores relevance after the first pick.
+pub fn maximal_marginal_relevance<D>(
+    candidates: &[(usize, f64)],
+    limit: usize,
+    lambda: f64,
+    distance_between: D,
+) -> Vec<(usize, f64)>
+where
+    D: Fn(usize, usize) -> f64,
+{
+    let mut remaining: Vec<(usize, f64)> = candidates.to_vec();
+    // the similarity of each remaining candidate to the closest already picked
+    let mut closest_picked = vec![f64::NEG_INFINITY; remaining.len()];
+    let mut picked = Vec::with_capacity(limit.min(remaining.len()));
+    while picked.len() < limit && !remaining.is_empty() {
+        let score = |index: usize| {
+            let (_, relevance) = remaining[index];
+            let similarity = if closest_picked[index].is_finite() {
+                closest_picked[index]
+            } else {
+                0.0
+            };
+            lambda * relevance - (1.0 - lambda) * similarity
+        };
+        // ties go to the earlier, i.e. more relevant, candidate
+        let best = (0..remaining.len())
+            .reduce(|best, index| {
+                if score(index) > score(best) {
+                    index
+                } else {
+                    best
+                }
+            })
+            .unwrap();
+        let chosen = remaining.remove(best);
+        closest_picked.remove(best);
+        for (index, (position, _)) in remaining.iter().enumerate() {
+            closest_picked[index] =
+                closest_picked[index].max(1.0 - distance_between(chosen.0, *position));
+        }
+        picked.push(chosen);
+    }
+    picked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // positions 0 and 1 are near duplicates, 2 is elsewhere
+    fn distance_between(a: usize, b: usize) -> f64 {
+        if a == b || (a < 2 && b < 2) {
+            0.01
+        } else {
+            1.0
+        }
+    }
+
+    const CANDIDATES: [(usize, f64); 3] = [(0, 0.9), (1, 0.89), (2, 0.7)];
+
+    #[test]
+    fn test_lambda_of_one_keeps_relevance_order() {
+        let picked = maximal_marginal_relevance(&CANDIDATES, 3, 1.0, distance_between);
+
+        assert_eq!(CANDIDATES.to_vec(), picked);
+    }
+
+    #[test]
+    fn test_near_duplicate_gives_way() {
+        let picked =
+            maximal_marginal_relevance(&CANDIDATES, 2, DEFAULT_MMR_LAMBDA, distance_between);
+
+        assert_eq!(
+            vec![0, 2],
+            picked.iter().map(|(i, _)| *i).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_limit_beyond_candidates() {
+        let picked =
+            maximal_marginal_relevance(&CANDIDATES, 10, DEFAULT_MMR_LAMBDA, distance_between);
+
+        assert_eq!(3, picked.len());
+    }
+}
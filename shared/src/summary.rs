@@ -1,8 +1,12 @@
 use std::collections::{BTreeMap, HashSet};
 
-use chrono::Duration;
+use chrono::{Duration, NaiveDate};
 
-use crate::{model::PersonId, queryable::Queryable};
+use crate::{
+    error::QueryError,
+    model::{Event, GlobalPersonId, Person, PersonId},
+    queryable::Queryable,
+};
 
 #[derive(Debug)]
 pub struct Summary {
@@ -14,6 +18,92 @@ pub struct Summary {
     pub video_duration: Duration,
     pub slides: usize,
     pub links: usize,
+    pub events_with_slides: usize,
+    /// of all events, as scheduled
+    pub duration: Duration,
+    /// presenters who also presented in the previous year
+    pub returning_people: usize,
+    /// tracks which also ran in the previous year
+    pub returning_tracks: usize,
+    pub by_track: BTreeMap<String, Breakdown>,
+    pub by_day: BTreeMap<NaiveDate, Breakdown>,
+    /// since the previous year, if there is one
+    pub change: Option<YearChange>,
+}
+
+impl Summary {
+    pub fn video_share(&self) -> f64 {
+        share(self.videos, self.events)
+    }
+
+    pub fn slides_share(&self) -> f64 {
+        share(self.events_with_slides, self.events)
+    }
+
+    pub fn average_duration(&self) -> Duration {
+        average(self.duration, self.events)
+    }
+
+    /// Tracks with the most events first
+    pub fn largest_tracks(&self) -> Vec<(&String, &Breakdown)> {
+        let mut tracks: Vec<(&String, &Breakdown)> = self.by_track.iter().collect();
+        tracks.sort_by_key(|(_, breakdown)| std::cmp::Reverse(breakdown.events));
+        tracks
+    }
+}
+
+/// Counts for a part of a year, e.g. one track or one day
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Breakdown {
+    pub events: usize,
+    pub videos: usize,
+    pub events_with_slides: usize,
+    /// of all events, as scheduled
+    pub duration: Duration,
+}
+
+impl Breakdown {
+    fn add(&mut self, event: &Event) {
+        self.events += 1;
+        if event.mp4_video_link().is_some() {
+            self.videos += 1;
+        }
+        if !event.slides.is_empty() {
+            self.events_with_slides += 1;
+        }
+        self.duration += Duration::minutes(event.duration.into());
+    }
+
+    pub fn video_share(&self) -> f64 {
+        share(self.videos, self.events)
+    }
+
+    pub fn slides_share(&self) -> f64 {
+        share(self.events_with_slides, self.events)
+    }
+
+    pub fn average_duration(&self) -> Duration {
+        average(self.duration, self.events)
+    }
+}
+
+/// How a year differs from the previous one, as this year's count minus that year's
+#[derive(Debug, Clone, PartialEq)]
+pub struct YearChange {
+    pub previous_year: u32,
+    pub events: i64,
+    pub people: i64,
+    pub rooms: i64,
+    pub tracks: i64,
+    pub videos: i64,
+    pub slides: i64,
+    pub links: i64,
+    /// `events` as a share of the previous year's events, e.g. 0.1 for 10% more
+    pub events_growth: f64,
+    /// tracks which weren't in the previous year
+    pub new_tracks: Vec<String>,
+    /// tracks in the previous year which aren't in this one
+    pub ended_tracks: Vec<String>,
 }
 
 #[derive(Debug)]
@@ -21,74 +111,145 @@ pub struct DataSummary {
     pub by_year: BTreeMap<u32, Summary>,
 }
 
-pub async fn load_summary<Q: Queryable>(
-    queryable: &Q,
-) -> Result<DataSummary, Box<dyn std::error::Error>> {
-    let events = queryable.load_all_events().await?;
+fn share(part: usize, whole: usize) -> f64 {
+    if whole == 0 {
+        0.0
+    } else {
+        part as f64 / whole as f64
+    }
+}
 
-    let mut events_by_year: BTreeMap<u32, usize> = BTreeMap::new();
-    let mut people_by_year: BTreeMap<u32, HashSet<PersonId>> = BTreeMap::new();
-    let mut rooms_by_year: BTreeMap<u32, HashSet<String>> = BTreeMap::new();
-    let mut tracks_by_year: BTreeMap<u32, HashSet<String>> = BTreeMap::new();
-    let mut videos_by_year: BTreeMap<u32, usize> = BTreeMap::new();
-    let mut video_duration_by_year: BTreeMap<u32, Duration> = BTreeMap::new();
-    let mut slides_by_year: BTreeMap<u32, usize> = BTreeMap::new();
-    let mut links_by_year: BTreeMap<u32, usize> = BTreeMap::new();
+fn average(total: Duration, count: usize) -> Duration {
+    if count == 0 {
+        Duration::zero()
+    } else {
+        total / count as i32
+    }
+}
 
-    for event in events {
-        let year = event.year;
-        *events_by_year.entry(year).or_insert(0) += 1;
+fn difference(now: usize, before: usize) -> i64 {
+    now as i64 - before as i64
+}
 
-        rooms_by_year
-            .entry(year)
-            .or_default()
-            .insert(event.room.clone());
+/// The same presenter in different years, even if they weren't resolved on import
+fn global_person_id(person: &Person) -> GlobalPersonId {
+    person
+        .global_id
+        .clone()
+        .unwrap_or_else(|| GlobalPersonId::from_name(&person.name))
+}
 
-        tracks_by_year
-            .entry(year)
-            .or_default()
-            .insert(event.track.clone());
+pub async fn load_summary<Q: Queryable>(queryable: &Q) -> Result<DataSummary, QueryError> {
+    let mut events_by_year: BTreeMap<u32, Vec<Event>> = BTreeMap::new();
+    for event in queryable.load_all_events().await? {
+        events_by_year.entry(event.year).or_default().push(event);
+    }
 
-        for person in &event.presenters {
-            people_by_year.entry(year).or_default().insert(person.id);
+    let mut by_year = BTreeMap::new();
+    let mut previous: Option<(u32, HashSet<GlobalPersonId>)> = None;
+    for (year, events) in &events_by_year {
+        let mut summary = summarise(events);
+        let presenters: HashSet<GlobalPersonId> = events
+            .iter()
+            .flat_map(|event| event.presenters.iter().map(global_person_id))
+            .collect();
+        if let Some((previous_year, previous_presenters)) = &previous {
+            let before: &Summary = &by_year[previous_year];
+            summary.returning_people = presenters.intersection(previous_presenters).count();
+            summary.returning_tracks = summary
+                .by_track
+                .keys()
+                .filter(|track| before.by_track.contains_key(*track))
+                .count();
+            summary.change = Some(change_between(*previous_year, before, &summary));
         }
+        by_year.insert(*year, summary);
+        previous = Some((*year, presenters));
+    }
 
+    Ok(DataSummary { by_year })
+}
+
+/// Everything about one year's `events` which doesn't depend on other years
+fn summarise(events: &[Event]) -> Summary {
+    let mut people: HashSet<PersonId> = HashSet::new();
+    let mut rooms: HashSet<&str> = HashSet::new();
+    let mut overall = Breakdown::default();
+    let mut video_duration = Duration::zero();
+    let mut slides = 0;
+    let mut links = 0;
+    let mut by_track: BTreeMap<String, Breakdown> = BTreeMap::new();
+    let mut by_day: BTreeMap<NaiveDate, Breakdown> = BTreeMap::new();
+
+    for event in events {
+        rooms.insert(&event.room);
+        for person in &event.presenters {
+            people.insert(person.id);
+        }
         if event.mp4_video_link().is_some() {
-            *videos_by_year.entry(year).or_insert(0) += 1;
-            *video_duration_by_year.entry(year).or_insert(Duration::zero()) +=
-                Duration::minutes(event.duration.into());
+            video_duration += Duration::minutes(event.duration.into());
         }
-
-        *slides_by_year.entry(year).or_insert(0) += event.slides.len();
-        *links_by_year.entry(year).or_insert(0) += event.links.len();
+        slides += event.slides.len();
+        links += event.links.len();
+        overall.add(event);
+        by_track.entry(event.track.clone()).or_default().add(event);
+        by_day.entry(event.date).or_default().add(event);
     }
 
-    let mut by_year = BTreeMap::new();
-    for (year, events) in &events_by_year {
-        by_year.insert(
-            *year,
-            Summary {
-                events: *events,
-                people: people_by_year.get(year).map_or(0, |s| s.len()),
-                rooms: rooms_by_year.get(year).map_or(0, |s| s.len()),
-                tracks: tracks_by_year.get(year).map_or(0, |s| s.len()),
-                videos: *videos_by_year.get(year).unwrap_or(&0),
-                video_duration: *video_duration_by_year
-                    .get(year)
-                    .unwrap_or(&Duration::zero()),
-                slides: *slides_by_year.get(year).unwrap_or(&0),
-                links: *links_by_year.get(year).unwrap_or(&0),
-            },
-        );
+    Summary {
+        events: overall.events,
+        people: people.len(),
+        rooms: rooms.len(),
+        tracks: by_track.len(),
+        videos: overall.videos,
+        video_duration,
+        slides,
+        links,
+        events_with_slides: overall.events_with_slides,
+        duration: overall.duration,
+        returning_people: 0,
+        returning_tracks: 0,
+        by_track,
+        by_day,
+        change: None,
     }
+}
 
-    Ok(DataSummary { by_year })
+fn change_between(previous_year: u32, before: &Summary, now: &Summary) -> YearChange {
+    YearChange {
+        previous_year,
+        events: difference(now.events, before.events),
+        people: difference(now.people, before.people),
+        rooms: difference(now.rooms, before.rooms),
+        tracks: difference(now.tracks, before.tracks),
+        videos: difference(now.videos, before.videos),
+        slides: difference(now.slides, before.slides),
+        links: difference(now.links, before.links),
+        events_growth: if before.events == 0 {
+            0.0
+        } else {
+            difference(now.events, before.events) as f64 / before.events as f64
+        },
+        new_tracks: now
+            .by_track
+            .keys()
+            .filter(|track| !before.by_track.contains_key(*track))
+            .cloned()
+            .collect(),
+        ended_tracks: before
+            .by_track
+            .keys()
+            .filter(|track| !now.by_track.contains_key(*track))
+            .cloned()
+            .collect(),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::model::{Event, EventId, Link, Person, PersonId};
+    use crate::filter::SearchFilter;
+    use crate::model::{test_event, Event, EventId, Link, Person, PersonId};
     use chrono::NaiveDate;
     use url::Url;
 
@@ -97,23 +258,21 @@ mod tests {
     }
 
     impl crate::queryable::Queryable for TestQueryable {
-        async fn load_all_events(&self) -> Result<Vec<Event>, Box<dyn std::error::Error>> {
+        async fn load_all_events(&self) -> Result<Vec<Event>, QueryError> {
             Ok(self.events.clone())
         }
 
-        async fn find_event_by_id(
-            &self,
-            _: EventId,
-        ) -> Result<Option<Event>, Box<dyn std::error::Error>> {
+        async fn find_event_by_id(&self, _: EventId) -> Result<Option<Event>, QueryError> {
             unimplemented!()
         }
 
         async fn find_related_events(
             &self,
-            _: &str,
+            _: EventId,
             _: u8,
-            _: Option<u32>,
-        ) -> Result<Vec<crate::model::SearchItem>, Box<dyn std::error::Error>> {
+            _: &SearchFilter,
+            _: f64,
+        ) -> Result<Vec<crate::model::SearchItem>, QueryError> {
             unimplemented!()
         }
 
@@ -122,8 +281,20 @@ mod tests {
             _: &str,
             _: u8,
             _: bool,
-            _: Option<u32>,
-        ) -> Result<Vec<crate::model::SearchItem>, Box<dyn std::error::Error>> {
+            _: &SearchFilter,
+            _: crate::queryable::SearchMode,
+            _: f64,
+        ) -> Result<crate::queryable::SearchResults, QueryError> {
+            unimplemented!()
+        }
+
+        async fn recommend(
+            &self,
+            _: &[EventId],
+            _: &[EventId],
+            _: &SearchFilter,
+            _: u8,
+        ) -> Result<Vec<crate::model::SearchItem>, QueryError> {
             unimplemented!()
         }
     }
@@ -137,27 +308,20 @@ mod tests {
         links: Vec<Link>,
     ) -> Event {
         Event {
-            id: EventId::new(year, 1),
-            guid: "guid".to_string(),
-            year,
             date: NaiveDate::from_ymd_opt(year as i32, 1, 1).unwrap(),
-            start: chrono::NaiveTime::from_hms_opt(10, 0, 0).unwrap(),
-            duration: 30,
             room: room.to_string(),
             track: track.to_string(),
-            title: "Title".to_string(),
-            slug: "slug".to_string(),
-            url: "https://example.com".parse().unwrap(),
-            r#abstract: "Abstract".to_string(),
             slides,
             presenters: presenters
                 .into_iter()
                 .map(|id| Person {
                     id,
                     name: "Name".to_string(),
+                    global_id: None,
                 })
                 .collect(),
             links,
+            ..test_event(EventId::new(year, 1), "Title")
         }
     }
 
@@ -191,8 +355,22 @@ mod tests {
     async fn test_single_year_summary() {
         let queryable = TestQueryable {
             events: vec![
-                make_event(2024, "Room1", "Track1", vec![PersonId::new(2024, 1)], vec![], vec![]),
-                make_event(2024, "Room2", "Track1", vec![PersonId::new(2024, 2)], vec![], vec![]),
+                make_event(
+                    2024,
+                    "Room1",
+                    "Track1",
+                    vec![PersonId::new(2024, 1)],
+                    vec![],
+                    vec![],
+                ),
+                make_event(
+                    2024,
+                    "Room2",
+                    "Track1",
+                    vec![PersonId::new(2024, 2)],
+                    vec![],
+                    vec![],
+                ),
             ],
         };
         let summary = load_summary(&queryable).await.unwrap();
@@ -209,8 +387,22 @@ mod tests {
     async fn test_multi_year_summary() {
         let queryable = TestQueryable {
             events: vec![
-                make_event(2024, "Room1", "Track1", vec![PersonId::new(2024, 1)], vec![], vec![]),
-                make_event(2025, "Room1", "Track2", vec![PersonId::new(2025, 1)], vec![], vec![]),
+                make_event(
+                    2024,
+                    "Room1",
+                    "Track1",
+                    vec![PersonId::new(2024, 1)],
+                    vec![],
+                    vec![],
+                ),
+                make_event(
+                    2025,
+                    "Room1",
+                    "Track2",
+                    vec![PersonId::new(2025, 1)],
+                    vec![],
+                    vec![],
+                ),
             ],
         };
         let summary = load_summary(&queryable).await.unwrap();
@@ -250,7 +442,7 @@ mod tests {
         assert_eq!(year_2024.videos, 1); // Only first event has video
         assert_eq!(year_2024.video_duration, Duration::minutes(30)); // 30 min event duration
         assert_eq!(year_2024.slides, 3); // 2 + 1
-        assert_eq!(year_2024.links, 4);  // 3 + 1
+        assert_eq!(year_2024.links, 4); // 3 + 1
     }
 
     #[tokio::test]
@@ -266,4 +458,124 @@ mod tests {
         assert_eq!(year_2024.slides, 0);
         assert_eq!(year_2024.links, 0);
     }
+
+    #[tokio::test]
+    async fn test_breakdowns_by_track_and_day() {
+        let mut sunday = make_event(2024, "Room1", "Track2", vec![], vec![make_slide()], vec![]);
+        sunday.date = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap();
+        sunday.duration = 50;
+        let queryable = TestQueryable {
+            events: vec![
+                make_event(
+                    2024,
+                    "Room1",
+                    "Track1",
+                    vec![],
+                    vec![],
+                    vec![make_video_link()],
+                ),
+                make_event(2024, "Room1", "Track1", vec![], vec![], vec![]),
+                sunday,
+            ],
+        };
+        let summary = load_summary(&queryable).await.unwrap();
+
+        let year_2024 = summary.by_year.get(&2024).unwrap();
+        assert_eq!(1.0 / 3.0, year_2024.video_share());
+        assert_eq!(1.0 / 3.0, year_2024.slides_share());
+        assert_eq!(Duration::minutes(110) / 3, year_2024.average_duration());
+        let track_1 = &year_2024.by_track["Track1"];
+        assert_eq!(
+            (2, 1, 0),
+            (track_1.events, track_1.videos, track_1.events_with_slides)
+        );
+        assert_eq!(0.5, track_1.video_share());
+        assert_eq!("Track1", year_2024.largest_tracks()[0].0);
+        let days: Vec<usize> = year_2024.by_day.values().map(|day| day.events).collect();
+        assert_eq!(vec![2, 1], days);
+        assert!(year_2024.change.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_returning_presenters_and_tracks_and_change_from_previous_year() {
+        let presented_by = |mut event: Event, names: &[&str]| {
+            for (presenter, name) in event.presenters.iter_mut().zip(names) {
+                presenter.name = name.to_string();
+            }
+            event
+        };
+        let queryable = TestQueryable {
+            events: vec![
+                presented_by(
+                    make_event(
+                        2024,
+                        "Room1",
+                        "Track1",
+                        vec![PersonId::new(2024, 1)],
+                        vec![],
+                        vec![],
+                    ),
+                    &["Ada Lovelace"],
+                ),
+                presented_by(
+                    make_event(
+                        2024,
+                        "Room1",
+                        "Track2",
+                        vec![PersonId::new(2024, 2)],
+                        vec![],
+                        vec![],
+                    ),
+                    &["Grace Hopper"],
+                ),
+                presented_by(
+                    make_event(
+                        2025,
+                        "Room1",
+                        "Track1",
+                        vec![PersonId::new(2025, 7)],
+                        vec![],
+                        vec![],
+                    ),
+                    &["Ada Lovelace"],
+                ),
+                presented_by(
+                    make_event(
+                        2025,
+                        "Room1",
+                        "Track3",
+                        vec![PersonId::new(2025, 8)],
+                        vec![],
+                        vec![],
+                    ),
+                    &["Alan Turing"],
+                ),
+                presented_by(
+                    make_event(
+                        2025,
+                        "Room2",
+                        "Track3",
+                        vec![PersonId::new(2025, 9)],
+                        vec![],
+                        vec![],
+                    ),
+                    &["Edsger Dijkstra"],
+                ),
+            ],
+        };
+        let summary = load_summary(&queryable).await.unwrap();
+
+        let year_2025 = summary.by_year.get(&2025).unwrap();
+        assert_eq!(1, year_2025.returning_people);
+        assert_eq!(1, year_2025.returning_tracks);
+        let change = year_2025.change.as_ref().unwrap();
+        assert_eq!(2024, change.previous_year);
+        assert_eq!(
+            (1, 1, 1, 0),
+            (change.events, change.people, change.rooms, change.tracks)
+        );
+        assert_eq!(0.5, change.events_growth);
+        assert_eq!(vec!["Track3"], change.new_tracks);
+        assert_eq!(vec!["Track2"], change.ended_tracks);
+    }
 }
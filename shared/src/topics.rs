@@ -0,0 +1,176 @@
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::error::QueryError;
+use crate::model::EventId;
+
+pub const TOPICS_FILE: &str = "topics.json";
+
+/// Named after the event closest to the centre of the topic, e.g. `2026-8816` for a topic of
+/// 2026, or `all-2026-8816` for one across all years, rather than after where it ranks, so that
+/// clustering again keeps the ids, and so the URLs, of topics which are much the same
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, PartialOrd, Eq, Ord, Hash)]
+pub struct TopicId(String);
+
+impl TopicId {
+    pub fn new(year: Option<u32>, central_event: EventId) -> TopicId {
+        match year {
+            Some(_) => TopicId(central_event.to_string()),
+            None => TopicId(format!("all-{}", central_event)),
+        }
+    }
+
+    pub fn parse(id: &str) -> TopicId {
+        TopicId(id.to_string())
+    }
+}
+
+impl Display for TopicId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct TopicMember {
+    pub event_id: EventId,
+    /// from the centre of the topic
+    pub distance: f64,
+}
+
+/// A cluster of events with similar embeddings
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct Topic {
+    pub id: TopicId,
+    /// the year the events were clustered within, or None if they were clustered across all years
+    pub year: Option<u32>,
+    /// made from the most distinctive terms
+    pub name: String,
+    /// most distinctive first
+    pub terms: Vec<String>,
+    /// closest to the centre first
+    pub members: Vec<TopicMember>,
+}
+
+impl Topic {
+    pub fn members_in_year(&self, year: u32) -> impl Iterator<Item = &TopicMember> {
+        self.members
+            .iter()
+            .filter(move |member| member.event_id.year() == year)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TopicIndex {
+    topics: Vec<Topic>,
+}
+
+impl TopicIndex {
+    pub fn new(topics: Vec<Topic>) -> TopicIndex {
+        TopicIndex { topics }
+    }
+
+    pub fn empty_index() -> TopicIndex {
+        TopicIndex::default()
+    }
+
+    /// Topics are optional, as they are made by a separate clustering step
+    pub fn load(model_dir: &Path) -> Result<TopicIndex, QueryError> {
+        let path = model_dir.join(TOPICS_FILE);
+        if !path.exists() {
+            info!("No topics at {:?}", path);
+            return Ok(TopicIndex::empty_index());
+        }
+        let reader =
+            BufReader::new(File::open(&path).map_err(|e| {
+                QueryError::IndexCorrupt(format!("failed to open {:?}: {}", path, e))
+            })?);
+        let topics: Vec<Topic> = serde_json::from_reader(reader)
+            .map_err(|e| QueryError::IndexCorrupt(format!("failed to parse {:?}: {}", path, e)))?;
+        info!("Loaded {} topics from {:?}", topics.len(), path);
+        Ok(TopicIndex { topics })
+    }
+
+    pub fn save(&self, model_dir: &Path) -> std::io::Result<()> {
+        let mut writer = BufWriter::new(File::create(model_dir.join(TOPICS_FILE))?);
+        serde_json::to_writer_pretty(&mut writer, &self.topics)?;
+        writer.flush()
+    }
+
+    pub fn find(&self, id: &TopicId) -> Option<&Topic> {
+        self.topics.iter().find(|topic| topic.id == *id)
+    }
+
+    /// Topics clustered within `year`, largest first
+    pub fn for_year(&self, year: u32) -> Vec<&Topic> {
+        self.topics
+            .iter()
+            .filter(|topic| topic.year == Some(year))
+            .collect()
+    }
+
+    /// Topics clustered across all years which have events in `year`, largest in that year first
+    pub fn across_years_in(&self, year: u32) -> Vec<&Topic> {
+        let mut topics: Vec<&Topic> = self
+            .topics
+            .iter()
+            .filter(|topic| topic.year.is_none() && topic.members_in_year(year).next().is_some())
+            .collect();
+        topics.sort_by_key(|topic| std::cmp::Reverse(topic.members_in_year(year).count()));
+        topics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn topic(year: Option<u32>, event_ids: &[EventId]) -> Topic {
+        Topic {
+            id: TopicId::new(year, event_ids[0]),
+            year,
+            name: "name".to_string(),
+            terms: vec![],
+            members: event_ids
+                .iter()
+                .map(|event_id| TopicMember {
+                    event_id: *event_id,
+                    distance: 0.0,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_topics_for_a_year() {
+        let index = TopicIndex::new(vec![
+            topic(Some(2025), &[EventId::new(2025, 1)]),
+            topic(Some(2026), &[EventId::new(2026, 1)]),
+            topic(None, &[EventId::new(2025, 2)]),
+            topic(
+                None,
+                &[
+                    EventId::new(2025, 3),
+                    EventId::new(2026, 2),
+                    EventId::new(2026, 3),
+                ],
+            ),
+        ]);
+
+        let ids = |topics: Vec<&Topic>| -> Vec<String> {
+            topics.iter().map(|topic| topic.id.to_string()).collect()
+        };
+        assert_eq!(vec!["2026-1"], ids(index.for_year(2026)));
+        assert_eq!(vec!["all-2025-3"], ids(index.across_years_in(2026)));
+        assert_eq!(
+            vec!["all-2025-2", "all-2025-3"],
+            ids(index.across_years_in(2025))
+        );
+        assert!(index.find(&TopicId::parse("2025-1")).is_some());
+    }
+}
@@ -1,23 +1,291 @@
 use std::path::Path;
 
-use shared::{inmemory_openai::InMemoryOpenAIQueryable, queryable::Queryable};
-use test_shared::{EVENT_ID_2025, EVENT_ID_2026};
+use shared::{
+    embedding::EmbeddingProvider,
+    error::EmbeddingRequestError,
+    evaluation::{evaluate, load_golden_queries, DEFAULT_CUTOFF},
+    filter::SearchFilter,
+    inmemory_openai::InMemoryOpenAIQueryable,
+    manifest::ManifestPolicy,
+    model::{EventId, SearchItem},
+    nearest::{HnswConfig, IndexConfig},
+    offline::OfflineEmbeddingProvider,
+    queryable::{Queryable, SearchMode},
+    rerank::DEFAULT_MMR_LAMBDA,
+};
+use test_shared::{
+    embedding_stub::{EmbeddingStub, STUB_RETRY_AFTER},
+    EVENT_ID_2025, EVENT_ID_2025_CONTENT_SAMPLE, EVENT_ID_2026, EVENT_ID_2026_CONTENT_SAMPLE,
+    GOLDEN_QUERIES_PATH, MODEL_DIR,
+};
+
+/// the model dir `just import_schedules index_next` builds, for tests which need real data
+const FULL_MODEL_DIR: &str = "./data/model";
+
+async fn connect(index_config: IndexConfig) -> InMemoryOpenAIQueryable<OfflineEmbeddingProvider> {
+    connect_to(MODEL_DIR, index_config, ManifestPolicy::Strict).await
+}
+
+async fn connect_to(
+    model_dir: &str,
+    index_config: IndexConfig,
+    manifest_policy: ManifestPolicy,
+) -> InMemoryOpenAIQueryable<OfflineEmbeddingProvider> {
+    InMemoryOpenAIQueryable::connect(
+        Path::new(model_dir),
+        OfflineEmbeddingProvider::default(),
+        index_config,
+        manifest_policy,
+    )
+    .await
+    .unwrap()
+}
 
 #[tokio::test]
 async fn test_can_find_expected_content() {
-    let model_dir = Path::new("./data/model");
-    let openai_api_key = "dummy";
-    let queryable = InMemoryOpenAIQueryable::connect(model_dir, openai_api_key)
-        .await
-        .unwrap();
+    let queryable = connect(IndexConfig::BruteForce).await;
 
     let expected_event_ids_found = vec![EVENT_ID_2025, EVENT_ID_2026];
     let mut actual_event_ids_found = vec![];
     for event_id in expected_event_ids_found.iter() {
-        if let Some(_) = queryable.find_event_by_id(event_id.clone()).await.unwrap() {
-            actual_event_ids_found.push(event_id.clone());
+        if queryable
+            .find_event_by_id(*event_id)
+            .await
+            .unwrap()
+            .is_some()
+        {
+            actual_event_ids_found.push(*event_id);
         }
     }
 
     assert_eq!(expected_event_ids_found, actual_event_ids_found);
 }
+
+fn event_ids(items: &[SearchItem]) -> Vec<String> {
+    items.iter().map(|i| i.event.id.to_string()).collect()
+}
+
+fn hnsw_config() -> IndexConfig {
+    IndexConfig::Hnsw(HnswConfig {
+        ef_search: 200,
+        ..HnswConfig::default()
+    })
+}
+
+/// compare plain nearest neighbours, without re-ranking, of each of `events`
+async fn assert_same_related_events<Q: Queryable>(
+    brute_force: &Q,
+    hnsw: &Q,
+    events: &[EventId],
+    filter: &SearchFilter,
+) {
+    for event_id in events {
+        let expected = brute_force
+            .find_related_events(*event_id, 5, filter, 1.0)
+            .await
+            .unwrap();
+        let actual = hnsw
+            .find_related_events(*event_id, 5, filter, 1.0)
+            .await
+            .unwrap();
+        assert_eq!(
+            event_ids(&expected),
+            event_ids(&actual),
+            "related to {}",
+            event_id
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_hnsw_has_same_top_k_as_brute_force() {
+    let brute_force = connect(IndexConfig::BruteForce).await;
+    let hnsw = connect(hnsw_config()).await;
+
+    let queries = [EVENT_ID_2025_CONTENT_SAMPLE, EVENT_ID_2026_CONTENT_SAMPLE];
+    for (query, year_filter) in queries.iter().flat_map(|q| [(q, None), (q, Some(2025))]) {
+        let filter = SearchFilter::for_year(year_filter);
+        // compare plain nearest neighbours, without re-ranking
+        let expected = brute_force
+            .search(query, 10, false, &filter, SearchMode::Semantic, 1.0)
+            .await
+            .unwrap()
+            .items;
+        let actual = hnsw
+            .search(query, 10, false, &filter, SearchMode::Semantic, 1.0)
+            .await
+            .unwrap()
+            .items;
+        assert_eq!(event_ids(&expected), event_ids(&actual), "search");
+
+        let found: Vec<EventId> = expected.iter().map(|item| item.event.id).collect();
+        assert_same_related_events(&brute_force, &hnsw, &found, &filter).await;
+    }
+}
+
+/// As above, but at full scale, and with whichever embeddings `./data/model` was built with, so
+/// comparing related events rather than searches, which would need that provider.
+/// Run with `cargo test -p shared --test integration_tests -- --ignored`
+#[tokio::test]
+#[ignore = "needs ./data/model, built with `just import_schedules index_next`"]
+async fn test_hnsw_has_same_top_k_as_brute_force_on_full_model() {
+    let brute_force = connect_to(
+        FULL_MODEL_DIR,
+        IndexConfig::BruteForce,
+        ManifestPolicy::Warn,
+    )
+    .await;
+    let hnsw = connect_to(FULL_MODEL_DIR, hnsw_config(), ManifestPolicy::Warn).await;
+
+    let sample: Vec<EventId> = brute_force
+        .load_all_events()
+        .await
+        .unwrap()
+        .iter()
+        .step_by(50)
+        .map(|e| e.id)
+        .collect();
+    for year_filter in [None, Some(2025)] {
+        let filter = SearchFilter::for_year(year_filter);
+        assert_same_related_events(&brute_force, &hnsw, &sample, &filter).await;
+    }
+}
+
+#[tokio::test]
+async fn test_exact_title_ranks_first_in_lexical_and_hybrid_search() {
+    let queryable = connect(IndexConfig::BruteForce).await;
+    let event = queryable
+        .find_event_by_id(EVENT_ID_2026)
+        .await
+        .unwrap()
+        .unwrap();
+
+    for mode in [SearchMode::Lexical, SearchMode::Hybrid] {
+        let found = queryable
+            .search(
+                &event.title,
+                5,
+                false,
+                &SearchFilter::default(),
+                mode,
+                DEFAULT_MMR_LAMBDA,
+            )
+            .await
+            .unwrap()
+            .items;
+        assert_eq!(event.id, found[0].event.id, "{mode}");
+    }
+}
+
+#[tokio::test]
+async fn test_lexical_search_finds_most_golden_query_events() {
+    let queryable = connect(IndexConfig::BruteForce).await;
+    let golden = load_golden_queries(Path::new(GOLDEN_QUERIES_PATH)).unwrap();
+
+    let evaluation = evaluate(
+        &queryable,
+        &golden,
+        DEFAULT_CUTOFF,
+        SearchMode::Lexical,
+        DEFAULT_MMR_LAMBDA,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(golden.len(), evaluation.queries.len());
+    assert!(
+        evaluation.mean.recall >= 0.8,
+        "recall@{} of {:?}",
+        DEFAULT_CUTOFF,
+        evaluation.mean
+    );
+}
+
+async fn search_ids<Q: Queryable>(queryable: &Q, query: &str, mode: SearchMode) -> Vec<String> {
+    let found = queryable
+        .search(
+            query,
+            5,
+            false,
+            &SearchFilter::default(),
+            mode,
+            DEFAULT_MMR_LAMBDA,
+        )
+        .await
+        .unwrap()
+        .items;
+    event_ids(&found)
+}
+
+#[tokio::test]
+async fn test_search_through_openai_client_matches_offline_embeddings() {
+    let stub = EmbeddingStub::start().await;
+    let through_client = InMemoryOpenAIQueryable::connect(
+        Path::new(MODEL_DIR),
+        stub.provider_config().build().unwrap(),
+        IndexConfig::BruteForce,
+        ManifestPolicy::Strict,
+    )
+    .await
+    .unwrap();
+    let offline = connect(IndexConfig::BruteForce).await;
+
+    for mode in [SearchMode::Semantic, SearchMode::Hybrid] {
+        let found = search_ids(&through_client, EVENT_ID_2026_CONTENT_SAMPLE, mode).await;
+
+        assert_eq!(EVENT_ID_2026.to_string(), found[0], "{mode}");
+        assert_eq!(
+            search_ids(&offline, EVENT_ID_2026_CONTENT_SAMPLE, mode).await,
+            found,
+            "{mode}"
+        );
+    }
+    assert_eq!(2, stub.requests());
+}
+
+#[tokio::test]
+async fn test_batch_embedding_through_openai_client_is_one_request() {
+    let stub = EmbeddingStub::start().await;
+    let provider = stub.provider_config().build().unwrap();
+    let inputs = vec![
+        "Rust in the kernel".to_string(),
+        "Postgres replication".to_string(),
+        "Rust in the kernel".to_string(),
+    ];
+
+    let embeddings = provider.embed_batch(&inputs).await.unwrap();
+
+    let offline = OfflineEmbeddingProvider::default();
+    assert_eq!(inputs.len(), embeddings.len());
+    for (input, embedding) in inputs.iter().zip(&embeddings) {
+        // allowing for the last digit being lost on the way through JSON
+        assert!(
+            (offline.embed_sync(input) - embedding).norm() < 1e-9,
+            "{input}"
+        );
+    }
+    assert_eq!(1, stub.requests());
+}
+
+#[tokio::test]
+async fn test_rate_limited_embedding_can_be_retried_after_the_time_asked_for() {
+    let stub = EmbeddingStub::start().await;
+    let provider = stub.provider_config().build().unwrap();
+    stub.rate_limit_next(1);
+
+    let error = provider.embed("Rust in the kernel").await.unwrap_err();
+
+    match error.downcast_ref::<EmbeddingRequestError>() {
+        Some(
+            e @ EmbeddingRequestError::Retryable {
+                retry_after: Some(retry_after),
+                ..
+            },
+        ) => {
+            assert!(e.is_rate_limited());
+            assert_eq!(STUB_RETRY_AFTER, retry_after.as_secs().to_string());
+        }
+        _ => panic!("not retryable: {:?}", error),
+    }
+    assert!(provider.embed("Rust in the kernel").await.is_ok());
+}
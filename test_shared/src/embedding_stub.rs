@@ -0,0 +1,146 @@
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::http::header::RETRY_AFTER;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Json, Router};
+use openai_dive::v1::resources::embedding::{
+    Embedding, EmbeddingInput, EmbeddingOutput, EmbeddingParameters, EmbeddingResponse,
+};
+use shared::embedding::{EmbeddingProviderConfig, EmbeddingProviderKind};
+use shared::offline::{OfflineEmbeddingProvider, OFFLINE_EMBEDDING_MODEL};
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+use url::Url;
+
+/// A stand-in for the OpenAI `/v1/embeddings` endpoint, so that tests can go through the same
+/// HTTP client as a real embedding provider without a key or network access. Embeddings are
+/// those of `OfflineEmbeddingProvider`, so they match a model dir built with it. Stops when
+/// dropped.
+pub struct EmbeddingStub {
+    address: SocketAddr,
+    requests: Arc<AtomicUsize>,
+    rate_limited: Arc<AtomicUsize>,
+    server: JoinHandle<()>,
+}
+
+/// what the stub asks rate limited clients to wait, in seconds
+pub const STUB_RETRY_AFTER: &str = "2";
+
+#[derive(Clone)]
+struct StubState {
+    provider: OfflineEmbeddingProvider,
+    requests: Arc<AtomicUsize>,
+    rate_limited: Arc<AtomicUsize>,
+}
+
+impl EmbeddingStub {
+    /// Serve on a free local port, from the current tokio runtime
+    pub async fn start() -> EmbeddingStub {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("failed to bind embedding stub");
+        let address = listener
+            .local_addr()
+            .expect("embedding stub has no address");
+        let requests = Arc::new(AtomicUsize::new(0));
+        let rate_limited = Arc::new(AtomicUsize::new(0));
+        let app = Router::new()
+            .route("/v1/embeddings", post(embeddings))
+            .with_state(StubState {
+                provider: OfflineEmbeddingProvider::default(),
+                requests: requests.clone(),
+                rate_limited: rate_limited.clone(),
+            });
+        let server = tokio::spawn(async move {
+            axum::serve(listener, app)
+                .await
+                .expect("embedding stub failed");
+        });
+        EmbeddingStub {
+            address,
+            requests,
+            rate_limited,
+            server,
+        }
+    }
+
+    /// e.g. `http://127.0.0.1:54321/v1`, for `--embedding-base-url`
+    pub fn base_url(&self) -> Url {
+        Url::parse(&format!("http://{}/v1", self.address)).unwrap()
+    }
+
+    /// how many embedding requests have been made so far
+    pub fn requests(&self) -> usize {
+        self.requests.load(Ordering::SeqCst)
+    }
+
+    /// Answer the next `count` requests with 429 Too Many Requests, and `Retry-After`
+    pub fn rate_limit_next(&self, count: usize) {
+        self.rate_limited.store(count, Ordering::SeqCst);
+    }
+
+    /// An `openai-compatible` provider which uses this stub, with the offline model's name so
+    /// that it passes a model dir's manifest check
+    pub fn provider_config(&self) -> EmbeddingProviderConfig {
+        EmbeddingProviderConfig {
+            kind: EmbeddingProviderKind::OpenAICompatible,
+            base_url: Some(self.base_url()),
+            model: Some(OFFLINE_EMBEDDING_MODEL.to_string()),
+            timeout: None,
+        }
+    }
+}
+
+impl Drop for EmbeddingStub {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+async fn embeddings(
+    State(state): State<StubState>,
+    Json(parameters): Json<EmbeddingParameters>,
+) -> Result<Json<EmbeddingResponse>, Response> {
+    state.requests.fetch_add(1, Ordering::SeqCst);
+    let rate_limited = state
+        .rate_limited
+        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |count| {
+            count.checked_sub(1)
+        })
+        .is_ok();
+    if rate_limited {
+        return Err((
+            StatusCode::TOO_MANY_REQUESTS,
+            [(RETRY_AFTER, STUB_RETRY_AFTER)],
+            "rate limited",
+        )
+            .into_response());
+    }
+    let inputs = match parameters.input {
+        EmbeddingInput::String(input) => vec![input],
+        EmbeddingInput::StringArray(inputs) => inputs,
+        _ => return Err((StatusCode::BAD_REQUEST, "only text input is supported").into_response()),
+    };
+    let data = inputs
+        .iter()
+        .enumerate()
+        .map(|(index, input)| Embedding {
+            index: index as u32,
+            embedding: EmbeddingOutput::Float(
+                state.provider.embed_sync(input).iter().copied().collect(),
+            ),
+            object: "embedding".to_string(),
+        })
+        .collect();
+    Ok(Json(EmbeddingResponse {
+        object: "list".to_string(),
+        data,
+        model: parameters.model,
+        usage: None,
+    }))
+}
@@ -1,3 +1,5 @@
+pub mod embedding_stub;
+
 use const_format::formatcp;
 use shared::model::EventId;
 
@@ -24,3 +26,12 @@ pub const EVENT_ID_2026_CANONICAL_PATH: &str = formatcp!(
 );
 pub const EVENT_ID_2026_CONTENT_SAMPLE: &str =
     "In this talk, I'm going to present recent efforts to run GNOME OS on phones";
+
+/// queries, and the events a good search should find for them, for measuring search quality
+pub const GOLDEN_QUERIES_PATH: &str =
+    concat!(env!("CARGO_MANIFEST_DIR"), "/data/golden_queries.json");
+
+/// a small model area built with the offline embedding provider from a subset of the events
+/// (those above, the golden queries' and the main tracks'), so tests don't depend on
+/// whichever provider built ./shared/data/model
+pub const MODEL_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/data/model");
@@ -0,0 +1,30 @@
+use axum::{
+    extract::State,
+    http::{header::AUTHORIZATION, HeaderMap, StatusCode},
+};
+use tracing::{info, warn};
+
+use crate::state::AppState;
+
+/// Reload the model dir and content in the background, e.g. after a schedule re-import. Needs
+/// `Authorization: Bearer <ADMIN_TOKEN>`, and is refused for everyone if no token is configured.
+#[tracing::instrument(skip_all)]
+pub async fn reload(State(state): State<AppState>, headers: HeaderMap) -> (StatusCode, String) {
+    let token = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    if !token.is_some_and(|token| state.is_admin(token)) {
+        warn!("refused reload");
+        return (StatusCode::UNAUTHORIZED, "Unauthorized\n".to_string());
+    }
+    if state.reload_in_background() {
+        info!("reload started");
+        (StatusCode::ACCEPTED, "Reload started\n".to_string())
+    } else {
+        (
+            StatusCode::CONFLICT,
+            "A reload is already running\n".to_string(),
+        )
+    }
+}
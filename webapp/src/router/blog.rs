@@ -24,6 +24,7 @@ struct BlogPostTemplate<'a> {
 
 #[tracing::instrument(skip(state))]
 pub async fn blog_list(State(state): State<AppState>) -> Html<String> {
+    let state = state.snapshot();
     let page = BlogListTemplate {
         current_fosdem: state.current_fosdem.clone(),
         posts: state.blog_index.all_posts(),
@@ -33,6 +34,7 @@ pub async fn blog_list(State(state): State<AppState>) -> Html<String> {
 
 #[tracing::instrument(skip(state))]
 pub async fn blog_post(Path(date): Path<String>, State(state): State<AppState>) -> Response {
+    let state = state.snapshot();
     match state.blog_index.find_by_slug(&date) {
         Some(post) => {
             let page = BlogPostTemplate {
@@ -47,6 +49,7 @@ pub async fn blog_post(Path(date): Path<String>, State(state): State<AppState>)
 
 #[tracing::instrument(skip(state))]
 pub async fn rss_feed(Host(host): Host, State(state): State<AppState>) -> Response {
+    let state = state.snapshot();
     let base_url = base_url_from_host(&host);
 
     let mut channel = rss::ChannelBuilder::default()
@@ -3,6 +3,7 @@ use axum::{extract::State, response::Html};
 
 use shared::{model::Event, queryable::Queryable};
 
+use crate::router::error::ErrorPage;
 use crate::state::AppState;
 
 #[derive(Template, Debug)]
@@ -14,8 +15,13 @@ struct BookmarksTemplate {
 }
 
 #[tracing::instrument(skip(state))]
-pub async fn bookmarks(State(state): State<AppState>) -> axum::response::Result<Html<String>> {
-    let mut events = state.queryable.load_all_events().await.unwrap();
+pub async fn bookmarks(State(state): State<AppState>) -> Result<Html<String>, ErrorPage> {
+    let state = state.snapshot();
+    let mut events = state
+        .queryable
+        .load_all_events()
+        .await
+        .map_err(|e| ErrorPage::from_query_error(&state, e))?;
     events.sort_by_key(|e| e.starting_time());
     let page = BookmarksTemplate {
         events,
@@ -0,0 +1,79 @@
+use askama::Template;
+use axum::{
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+use shared::error::QueryError;
+use tracing::{error, warn};
+
+use crate::state::Snapshot;
+
+#[derive(Template, Debug)]
+#[template(path = "error.html")]
+struct ErrorTemplate {
+    status: StatusCode,
+    message: String,
+    current_fosdem: shared::model::CurrentFosdem,
+}
+
+/// A failed request, shown as an error page with a status to match what went wrong
+#[derive(Debug)]
+pub struct ErrorPage {
+    status: StatusCode,
+    message: String,
+    current_fosdem: shared::model::CurrentFosdem,
+}
+
+impl ErrorPage {
+    pub fn new(state: &Snapshot, status: StatusCode, message: impl Into<String>) -> ErrorPage {
+        ErrorPage {
+            status,
+            message: message.into(),
+            current_fosdem: state.current_fosdem.clone(),
+        }
+    }
+
+    /// Something went wrong on our side, which is logged where it happened, but not shown
+    pub fn internal(state: &Snapshot) -> ErrorPage {
+        ErrorPage::new(
+            state,
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Something went wrong",
+        )
+    }
+
+    pub fn from_query_error(state: &Snapshot, query_error: QueryError) -> ErrorPage {
+        let (status, message) = match &query_error {
+            QueryError::NotFound(_) => (StatusCode::NOT_FOUND, query_error.to_string()),
+            QueryError::InvalidInput(_) => (StatusCode::BAD_REQUEST, query_error.to_string()),
+            QueryError::EmbeddingUnavailable(_) => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "Search is unavailable at the moment, please try again later".to_string(),
+            ),
+            QueryError::IndexCorrupt(_) | QueryError::Io(_) => {
+                error!("{}", query_error);
+                return ErrorPage::internal(state);
+            }
+        };
+        if status.is_server_error() {
+            error!("{}", query_error);
+        } else {
+            warn!("{}", query_error);
+        }
+        ErrorPage::new(state, status, message)
+    }
+}
+
+impl IntoResponse for ErrorPage {
+    fn into_response(self) -> Response {
+        let page = ErrorTemplate {
+            status: self.status,
+            message: self.message,
+            current_fosdem: self.current_fosdem,
+        };
+        match page.render() {
+            Ok(html) => (self.status, Html(html)).into_response(),
+            Err(_) => self.status.into_response(),
+        }
+    }
+}
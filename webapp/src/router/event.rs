@@ -1,16 +1,18 @@
+use crate::filters;
+use crate::router::error::ErrorPage;
+use crate::state::AppState;
 use askama::Template;
 use axum::{
     extract::{Path, State},
     response::Html,
 };
-use tracing::warn;
-
-use crate::filters;
-use crate::state::AppState;
 use serde::Deserialize;
+use shared::error::QueryError;
+use shared::filter::SearchFilter;
+use shared::model;
 use shared::model::{Event, SearchItem};
 use shared::queryable::Queryable;
-use shared::{inmemory_openai::InMemoryOpenAIQueryable, model};
+use shared::rerank::DEFAULT_MMR_LAMBDA;
 use validator::Validate;
 
 #[derive(Deserialize, Validate, Debug)]
@@ -33,7 +35,7 @@ struct EventTemplate {
 pub async fn event_2025(
     State(state): State<AppState>,
     Path(event_in_year_id): Path<u32>,
-) -> axum::response::Result<Html<String>> {
+) -> Result<Html<String>, ErrorPage> {
     event(State(state), Path((2025, event_in_year_id))).await
 }
 
@@ -41,36 +43,26 @@ pub async fn event_2025(
 pub async fn event(
     State(state): State<AppState>,
     Path((year, event_in_year_id)): Path<(u32, u32)>,
-) -> axum::response::Result<Html<String>> {
-    // TODO: this is all a bit contorted, for a couple of reasons:
-    // - InMemoryOpenAIQueryable should really natively support finding related events, as opposed to
-    // us having to find by event.title in `find_related_events`
-    // - When we are doing the two calls we can't have a nested await as `dyn StdError` isn't
-    // `Send`, which Rust thinks it needs to be on the second call
-    // Best thing is to move more of the responsibility into `InMemoryOpenAIQueryable` out of here
+) -> Result<Html<String>, ErrorPage> {
+    let state = state.snapshot();
     let event_id = model::EventId::new(year, event_in_year_id);
-    let possible_event: Option<Event> =
-        (state.queryable.find_event_by_id(event_id).await).unwrap_or_default();
-    if let Some(event) = possible_event {
-        let current_event = None;
-        let related = find_related_events(&state.queryable, &event).await;
-        let page = EventTemplate {
-            event,
-            related,
-            current_event,
-            current_fosdem: state.current_fosdem.clone(),
-        };
-        let html = page.render().unwrap();
-        Ok(Html(html))
-    } else {
-        warn!("Could not find event: {}", event_id);
-        Err("Could not find event".into())
-    }
-}
-
-async fn find_related_events(
-    queryable: &InMemoryOpenAIQueryable,
-    event: &Event,
-) -> Option<Vec<SearchItem>> {
-    (queryable.find_related_events(&event.title, 10, None).await).ok()
+    let event = state
+        .queryable
+        .find_event_by_id(event_id)
+        .await
+        .and_then(|event| event.ok_or(QueryError::NotFound(event_id)))
+        .map_err(|e| ErrorPage::from_query_error(&state, e))?;
+    let related = state
+        .queryable
+        .find_related_events(event.id, 10, &SearchFilter::default(), DEFAULT_MMR_LAMBDA)
+        .await
+        .ok();
+    let page = EventTemplate {
+        event,
+        related,
+        current_event: None,
+        current_fosdem: state.current_fosdem.clone(),
+    };
+    let html = page.render().unwrap();
+    Ok(Html(html))
 }
@@ -1,5 +1,6 @@
 use askama::Template;
 use axum::{extract::State, response::Html};
+use shared::manifest::Manifest;
 use shared::summary::{load_summary, DataSummary};
 use tracing::error;
 
@@ -14,11 +15,13 @@ struct IndexTemplate<'a> {
     default_year: Option<u32>,
     recent_posts: Vec<&'a ::blog::Post>,
     summary: DataSummary,
+    manifest: Option<Manifest>,
 }
 
 #[tracing::instrument(skip(state))]
 pub async fn index(State(state): State<AppState>) -> Html<String> {
-    let summary = load_summary(state.queryable.as_ref()).await.unwrap_or_else(|e| {
+    let state = state.snapshot();
+    let summary = load_summary(&state.queryable).await.unwrap_or_else(|e| {
         error!("Failed to load summary: {}", e);
         DataSummary {
             by_year: std::collections::BTreeMap::new(),
@@ -29,6 +32,7 @@ pub async fn index(State(state): State<AppState>) -> Html<String> {
         default_year: None,
         recent_posts: state.blog_index.recent_posts(RECENT_POSTS_COUNT),
         summary,
+        manifest: state.queryable.manifest().cloned(),
     };
     let html = page.render().unwrap();
     Html(html)
@@ -0,0 +1,142 @@
+use std::collections::{BTreeSet, HashMap};
+
+use askama::Template;
+use axum::{
+    extract::{Query, State},
+    response::Html,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use shared::model::{Event, EventId};
+use shared::queryable::Queryable;
+
+use crate::router::error::ErrorPage;
+use crate::router::search::empty_string_as_none;
+use crate::state::{AppState, Snapshot};
+
+/// the map is drawn in a square of this size, in SVG user units, inside a margin
+const MAP_SIZE: f64 = 1000.0;
+const MAP_MARGIN: f64 = 10.0;
+
+#[derive(Deserialize, Debug)]
+pub struct MapParams {
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    year: Option<u32>,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    track: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct MapEntry {
+    event_id: String,
+    /// between 0.0 and 1.0, as are all coordinates
+    x: f64,
+    y: f64,
+    year: u32,
+    track: String,
+    title: String,
+    url: String,
+}
+
+impl MapEntry {
+    fn cx(&self) -> f64 {
+        MAP_MARGIN + self.x * (MAP_SIZE - 2.0 * MAP_MARGIN)
+    }
+
+    fn cy(&self) -> f64 {
+        MAP_MARGIN + self.y * (MAP_SIZE - 2.0 * MAP_MARGIN)
+    }
+
+    /// a colour per track, so that tracks can be told apart
+    fn colour(&self) -> String {
+        let hue = self
+            .track
+            .bytes()
+            .fold(0u32, |hash, b| hash.wrapping_mul(31).wrapping_add(b as u32))
+            % 360;
+        format!("hsl({}, 70%, 45%)", hue)
+    }
+}
+
+/// The events on the map matching `params`, and every track which can be picked in the year
+async fn map_entries(
+    state: &Snapshot,
+    params: &MapParams,
+) -> Result<(Vec<MapEntry>, Vec<String>), ErrorPage> {
+    let events: HashMap<EventId, Event> = state
+        .queryable
+        .load_all_events()
+        .await
+        .map_err(|e| ErrorPage::from_query_error(state, e))?
+        .into_iter()
+        .filter(|event| params.year.is_none_or(|year| event.year == year))
+        .map(|event| (event.id, event))
+        .collect();
+    let tracks: BTreeSet<String> = events.values().map(|e| e.track.clone()).collect();
+    let entries = state
+        .event_map
+        .points()
+        .iter()
+        .filter_map(|point| {
+            let event = events.get(&point.event_id)?;
+            if params
+                .track
+                .as_ref()
+                .is_some_and(|track| *track != event.track)
+            {
+                return None;
+            }
+            Some(MapEntry {
+                event_id: event.id.to_string(),
+                x: point.x,
+                y: point.y,
+                year: event.year,
+                track: event.track.clone(),
+                title: event.title.clone(),
+                url: format!("/{}/event/{}/", event.id.year(), event.id.event_in_year()),
+            })
+        })
+        .collect();
+    Ok((entries, tracks.into_iter().collect()))
+}
+
+/// Where each event is on the map, e.g. `/map.json?year=2026&track=Rust`
+#[tracing::instrument(skip(state))]
+pub async fn map_json(
+    State(state): State<AppState>,
+    Query(params): Query<MapParams>,
+) -> Result<Json<Vec<MapEntry>>, ErrorPage> {
+    let state = state.snapshot();
+    let (entries, _) = map_entries(&state, &params).await?;
+    Ok(Json(entries))
+}
+
+#[derive(Template, Debug)]
+#[template(path = "map.html")]
+struct MapTemplate {
+    year: Option<u32>,
+    track: Option<String>,
+    tracks: Vec<String>,
+    entries: Vec<MapEntry>,
+    map_size: f64,
+    current_fosdem: shared::model::CurrentFosdem,
+}
+
+#[tracing::instrument(skip(state))]
+pub async fn map(
+    State(state): State<AppState>,
+    Query(params): Query<MapParams>,
+) -> Result<Html<String>, ErrorPage> {
+    let state = state.snapshot();
+    let (entries, tracks) = map_entries(&state, &params).await?;
+    let page = MapTemplate {
+        year: params.year,
+        track: params.track,
+        tracks,
+        entries,
+        map_size: MAP_SIZE,
+        current_fosdem: state.current_fosdem.clone(),
+    };
+    let html = page.render().unwrap();
+    Ok(Html(html))
+}
@@ -0,0 +1,69 @@
+use crate::router::error::ErrorPage;
+use crate::state::AppState;
+use askama::Template;
+use axum::extract::Path;
+use axum::http::StatusCode;
+use axum::{extract::State, response::Html};
+use shared::model::{Event, GlobalPersonId};
+use shared::queryable::Queryable;
+
+#[derive(Template, Debug)]
+#[template(path = "person.html")]
+struct PersonTemplate {
+    name: String,
+    /// most recent year first
+    events_by_year: Vec<(u32, Vec<Event>)>,
+    current_event: Option<Event>, // TODO: remove this
+    current_fosdem: shared::model::CurrentFosdem,
+}
+
+#[tracing::instrument(skip(state))]
+pub async fn person(
+    State(state): State<AppState>,
+    Path(person_id): Path<String>,
+) -> Result<Html<String>, ErrorPage> {
+    let state = state.snapshot();
+    let person_id = GlobalPersonId::new(person_id);
+    let all_events = state
+        .queryable
+        .load_all_events()
+        .await
+        .map_err(|e| ErrorPage::from_query_error(&state, e))?;
+    let mut events: Vec<Event> = all_events
+        .into_iter()
+        .filter(|e| {
+            e.presenters
+                .iter()
+                .any(|p| p.global_id.as_ref() == Some(&person_id))
+        })
+        .collect();
+    events.sort_by_key(|e| (std::cmp::Reverse(e.year), e.starting_time()));
+    // the name they used most recently
+    let Some(name) = events.first().and_then(|e| {
+        e.presenters
+            .iter()
+            .find(|p| p.global_id.as_ref() == Some(&person_id))
+            .map(|p| p.name.clone())
+    }) else {
+        return Err(ErrorPage::new(
+            &state,
+            StatusCode::NOT_FOUND,
+            format!("No events presented by {}", person_id),
+        ));
+    };
+    let mut events_by_year: Vec<(u32, Vec<Event>)> = vec![];
+    for event in events {
+        match events_by_year.last_mut() {
+            Some((year, year_events)) if *year == event.year => year_events.push(event),
+            _ => events_by_year.push((event.year, vec![event])),
+        }
+    }
+    let page = PersonTemplate {
+        name,
+        events_by_year,
+        current_event: None,
+        current_fosdem: state.current_fosdem.clone(),
+    };
+    let html = page.render().unwrap();
+    Ok(Html(html))
+}
@@ -0,0 +1,82 @@
+use askama::Template;
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::Html,
+};
+use serde::Deserialize;
+use shared::filter::SearchFilter;
+use shared::model::{Event, EventId, SearchItem};
+use shared::queryable::Queryable;
+use tracing::info;
+
+use crate::filters;
+use crate::router::error::ErrorPage;
+use crate::router::search::empty_string_as_none;
+use crate::state::AppState;
+
+const RECOMMENDATIONS_LIMIT: u8 = 20;
+
+/// `ids` and `exclude` are lists of event ids separated by spaces or commas, such as the text
+/// from exporting bookmarks, e.g. `/recommendations?ids=2026-7327+2025-6520`
+#[derive(Deserialize, Debug)]
+pub struct RecommendationParams {
+    #[serde(default)]
+    ids: String,
+    #[serde(default)]
+    exclude: String,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    year: Option<u32>,
+}
+
+fn parse_event_ids(text: &str) -> Result<Vec<EventId>, String> {
+    text.split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse())
+        .collect()
+}
+
+#[derive(Template, Debug)]
+#[template(path = "recommendations.html")]
+struct RecommendationsTemplate {
+    ids: String,
+    year: Option<u32>,
+    items: Vec<SearchItem>,
+    current_event: Option<Event>, // TODO: remove this
+    current_fosdem: shared::model::CurrentFosdem,
+}
+
+#[tracing::instrument(skip(state))]
+pub async fn recommendations(
+    State(state): State<AppState>,
+    Query(params): Query<RecommendationParams>,
+) -> Result<Html<String>, ErrorPage> {
+    let state = state.snapshot();
+    info!("recommendation params: {:?}", params);
+    let bad_request = |e: String| ErrorPage::new(&state, StatusCode::BAD_REQUEST, e);
+    let seeds = parse_event_ids(&params.ids).map_err(bad_request)?;
+    let exclude = parse_event_ids(&params.exclude).map_err(bad_request)?;
+    let items = if seeds.is_empty() {
+        vec![]
+    } else {
+        state
+            .queryable
+            .recommend(
+                &seeds,
+                &exclude,
+                &SearchFilter::for_year(params.year),
+                RECOMMENDATIONS_LIMIT,
+            )
+            .await
+            .map_err(|e| ErrorPage::from_query_error(&state, e))?
+    };
+    let page = RecommendationsTemplate {
+        ids: params.ids.clone(),
+        year: params.year,
+        items,
+        current_event: None,
+        current_fosdem: state.current_fosdem.clone(),
+    };
+    let html = page.render().unwrap();
+    Ok(Html(html))
+}
@@ -1,4 +1,5 @@
 use crate::filters;
+use crate::router::error::ErrorPage;
 use crate::state::AppState;
 use askama::Template;
 use axum::extract::Path;
@@ -23,8 +24,13 @@ pub struct RoomIdParam(String);
 pub async fn room(
     State(state): State<AppState>,
     Path(RoomIdParam(room_id)): Path<RoomIdParam>,
-) -> axum::response::Result<Html<String>> {
-    let all_events = state.queryable.load_all_events().await.unwrap();
+) -> Result<Html<String>, ErrorPage> {
+    let state = state.snapshot();
+    let all_events = state
+        .queryable
+        .load_all_events()
+        .await
+        .map_err(|e| ErrorPage::from_query_error(&state, e))?;
     let mut events: Vec<Event> = all_events
         .into_iter()
         .filter(|e| e.room == room_id && e.year == state.current_fosdem.year)
@@ -1,34 +1,86 @@
 use std::{fmt, str::FromStr};
 
 use askama::Template;
-use axum::{
-    extract::{Query, State},
-    response::Html,
-};
-use axum_valid::Valid;
-
+use axum::{extract::State, http::StatusCode, response::Html};
+use axum_extra::extract::Query;
+use chrono::{NaiveTime, Weekday};
 use serde::{de, Deserialize, Deserializer};
-use shared::model::{Event, SearchItem};
+use shared::facets::{FacetCount, Facets};
+use shared::filter::SearchFilter;
+use shared::model::{Event, EventType, SearchItem};
 use tracing::info;
-use validator::Validate;
+use url::form_urlencoded;
+use validator::{Validate, ValidationError};
 
 use crate::filters;
+use crate::router::error::ErrorPage;
 use crate::state::AppState;
-use shared::queryable::Queryable;
+use shared::queryable::{Queryable, SearchMode, SearchResults};
+use shared::rerank::DEFAULT_MMR_LAMBDA;
+
+/// how many of the most common values of each facet are offered as refinements
+const MAX_REFINEMENTS_PER_FACET: usize = 10;
 
-#[derive(Deserialize, Validate, Debug)]
+/// List params (`year`, `day`, `track`, `room`, `presenter`, `type`, `language`) can be
+/// repeated, as names can contain commas, e.g. `/search?q=firmware&track=Rust&track=Go`
+#[derive(Deserialize, Validate, Debug, Clone)]
 pub struct SearchParams {
     #[validate(length(min = 2, max = 100))]
     q: String,
     #[validate(range(min = 1, max = 20))]
     limit: u8,
-    #[validate(range(min = 2024, max = 2026))]
+    #[validate(custom(function = "validate_years"))]
+    #[serde(default, rename = "year", deserialize_with = "empty_strings_skipped")]
+    years: Vec<u32>,
+    #[serde(default)]
+    mode: SearchMode,
+    /// how much to favour relevance over covering more tracks and years, from 0.0 to 1.0
+    #[validate(range(min = 0.0, max = 1.0))]
     #[serde(default, deserialize_with = "empty_string_as_none")]
-    year: Option<u32>,
+    lambda: Option<f64>,
+    #[serde(default, rename = "day", deserialize_with = "empty_strings_skipped")]
+    days: Vec<Weekday>,
+    #[serde(default, rename = "track", deserialize_with = "empty_strings_skipped")]
+    tracks: Vec<String>,
+    #[serde(default, rename = "room", deserialize_with = "empty_strings_skipped")]
+    rooms: Vec<String>,
+    #[serde(
+        default,
+        rename = "presenter",
+        deserialize_with = "empty_strings_skipped"
+    )]
+    presenters: Vec<String>,
+    /// e.g. `keynote`, `lightningtalk` or `bof`
+    #[serde(default, rename = "type", deserialize_with = "empty_strings_skipped")]
+    types: Vec<EventType>,
+    #[serde(
+        default,
+        rename = "language",
+        deserialize_with = "empty_strings_skipped"
+    )]
+    languages: Vec<String>,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    has_video: Option<bool>,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    has_slides: Option<bool>,
+    /// a time of day, like `14:00`
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    starts_after: Option<NaiveTime>,
+    /// a time of day, like `14:00`
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    ends_before: Option<NaiveTime>,
+}
+
+fn validate_years(years: &[u32]) -> Result<(), ValidationError> {
+    if years.iter().all(|year| (2024..=2026).contains(year)) {
+        Ok(())
+    } else {
+        Err(ValidationError::new("range"))
+    }
 }
 
 /// Serde deserialization decorator to map empty Strings to None,
-fn empty_string_as_none<'de, D, T>(de: D) -> Result<Option<T>, D::Error>
+pub(crate) fn empty_string_as_none<'de, D, T>(de: D) -> Result<Option<T>, D::Error>
 where
     D: Deserializer<'de>,
     T: FromStr,
@@ -41,12 +93,184 @@ where
     }
 }
 
+/// Serde deserialization decorator to parse repeated params, ignoring any which are empty
+pub(crate) fn empty_strings_skipped<'de, D, T>(de: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    Vec::<String>::deserialize(de)?
+        .iter()
+        .filter(|s| !s.is_empty())
+        .map(|s| FromStr::from_str(s).map_err(de::Error::custom))
+        .collect()
+}
+
+impl SearchParams {
+    fn filter(&self) -> SearchFilter {
+        SearchFilter {
+            years: self.years.clone(),
+            days: self.days.clone(),
+            tracks: self.tracks.clone(),
+            rooms: self.rooms.clone(),
+            presenters: self.presenters.clone(),
+            types: self.types.clone(),
+            languages: self.languages.clone(),
+            has_video: self.has_video,
+            has_slides: self.has_slides,
+            starts_after: self.starts_after,
+            ends_before: self.ends_before,
+        }
+    }
+
+    fn query_pairs(&self) -> Vec<(&'static str, String)> {
+        let mut pairs = vec![
+            ("q", self.q.clone()),
+            ("limit", self.limit.to_string()),
+            ("mode", self.mode.to_string()),
+        ];
+        pairs.extend(self.lambda.map(|l| ("lambda", l.to_string())));
+        pairs.extend(self.years.iter().map(|y| ("year", y.to_string())));
+        pairs.extend(self.days.iter().map(|d| ("day", d.to_string())));
+        pairs.extend(self.tracks.iter().map(|t| ("track", t.clone())));
+        pairs.extend(self.rooms.iter().map(|r| ("room", r.clone())));
+        pairs.extend(self.presenters.iter().map(|p| ("presenter", p.clone())));
+        pairs.extend(self.types.iter().map(|t| ("type", t.to_string())));
+        pairs.extend(self.languages.iter().map(|l| ("language", l.clone())));
+        pairs.extend(self.has_video.map(|v| ("has_video", v.to_string())));
+        pairs.extend(self.has_slides.map(|s| ("has_slides", s.to_string())));
+        pairs.extend(
+            self.starts_after
+                .map(|t| ("starts_after", t.format("%H:%M").to_string())),
+        );
+        pairs.extend(
+            self.ends_before
+                .map(|t| ("ends_before", t.format("%H:%M").to_string())),
+        );
+        pairs
+    }
+
+    /// A link to this search, with `key=value` added if it's not present, or removed if it is
+    fn toggled_href(&self, key: &'static str, value: &str) -> String {
+        let mut pairs = self.query_pairs();
+        let before = pairs.len();
+        pairs.retain(|(k, v)| !(*k == key && v == value));
+        if pairs.len() == before {
+            pairs.push((key, value.to_string()));
+        }
+        let query = form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(pairs)
+            .finish();
+        format!("/search?{}", query)
+    }
+
+    fn refinement<T: fmt::Display>(
+        &self,
+        key: &'static str,
+        facet: &FacetCount<T>,
+        active: bool,
+    ) -> Refinement {
+        let value = facet.value.to_string();
+        Refinement {
+            href: self.toggled_href(key, &value),
+            label: value,
+            count: facet.count,
+            active,
+        }
+    }
+
+    fn refinement_groups(&self, facets: &Facets) -> Vec<RefinementGroup> {
+        let mut groups = vec![
+            RefinementGroup {
+                name: "Year",
+                refinements: facets
+                    .years
+                    .iter()
+                    .map(|f| self.refinement("year", f, self.years.contains(&f.value)))
+                    .collect(),
+            },
+            RefinementGroup {
+                name: "Day",
+                refinements: facets
+                    .days
+                    .iter()
+                    .map(|f| self.refinement("day", f, self.days.contains(&f.value)))
+                    .collect(),
+            },
+            RefinementGroup {
+                name: "Type",
+                refinements: facets
+                    .types
+                    .iter()
+                    .map(|f| {
+                        let mut refinement =
+                            self.refinement("type", f, self.types.contains(&f.value));
+                        refinement.label = f.value.label().to_string();
+                        refinement
+                    })
+                    .collect(),
+            },
+        ];
+        for (name, key, counts, selected) in [
+            ("Track", "track", &facets.tracks, &self.tracks),
+            ("Room", "room", &facets.rooms, &self.rooms),
+            (
+                "Presenter",
+                "presenter",
+                &facets.presenters,
+                &self.presenters,
+            ),
+            ("Language", "language", &facets.languages, &self.languages),
+        ] {
+            groups.push(RefinementGroup {
+                name,
+                refinements: counts
+                    .iter()
+                    .take(MAX_REFINEMENTS_PER_FACET)
+                    .map(|f| self.refinement(key, f, selected.contains(&f.value)))
+                    .collect(),
+            });
+        }
+        for (name, key, count, selected) in [
+            ("Video", "has_video", facets.with_video, self.has_video),
+            ("Slides", "has_slides", facets.with_slides, self.has_slides),
+        ] {
+            let facet = FacetCount { value: true, count };
+            let mut refinement = self.refinement(key, &facet, selected == Some(true));
+            refinement.label = format!("with {}", name.to_lowercase());
+            groups.push(RefinementGroup {
+                name,
+                refinements: if count > 0 { vec![refinement] } else { vec![] },
+            });
+        }
+        groups.retain(|g| !g.refinements.is_empty());
+        groups
+    }
+}
+
+#[derive(Debug)]
+struct RefinementGroup {
+    name: &'static str,
+    refinements: Vec<Refinement>,
+}
+
+#[derive(Debug)]
+struct Refinement {
+    label: String,
+    count: usize,
+    href: String,
+    active: bool,
+}
+
 #[derive(Template, Debug)]
 #[template(path = "search.html")]
 struct SearchTemplate {
     query: String,
     year: Option<u32>,
+    mode: SearchMode,
     items: Vec<SearchItem>,
+    refinement_groups: Vec<RefinementGroup>,
     has_videos: bool,
     current_event: Option<Event>, // TODO: remove this
     current_fosdem: shared::model::CurrentFosdem,
@@ -55,20 +279,41 @@ struct SearchTemplate {
 #[tracing::instrument(skip(state))]
 pub async fn search(
     State(state): State<AppState>,
-    Valid(Query(params)): Valid<Query<SearchParams>>,
-) -> axum::response::Result<Html<String>> {
+    Query(params): Query<SearchParams>,
+) -> Result<Html<String>, ErrorPage> {
+    let state = state.snapshot();
     info!("search params: {:?}", params);
+    if let Err(e) = params.validate() {
+        return Err(ErrorPage::new(
+            &state,
+            StatusCode::BAD_REQUEST,
+            e.to_string(),
+        ));
+    }
     match state
         .queryable
-        .search(&params.q, params.limit, true, params.year)
+        .search(
+            &params.q,
+            params.limit,
+            true,
+            &params.filter(),
+            params.mode,
+            params.lambda.unwrap_or(DEFAULT_MMR_LAMBDA),
+        )
         .await
     {
-        Ok(items) => {
+        Ok(SearchResults { items, facets }) => {
             let has_videos = items.iter().any(|item| item.event.has_video());
+            let refinement_groups = params.refinement_groups(&facets);
             let page = SearchTemplate {
+                year: match params.years.as_slice() {
+                    [year] => Some(*year),
+                    _ => None,
+                },
                 query: params.q,
-                year: params.year,
+                mode: params.mode,
                 items,
+                refinement_groups,
                 has_videos,
                 current_event: None,
                 current_fosdem: state.current_fosdem.clone(),
@@ -76,6 +321,6 @@ pub async fn search(
             let html = page.render().unwrap();
             Ok(Html(html))
         }
-        Err(_) => Err("search failed".into()),
+        Err(e) => Err(ErrorPage::from_query_error(&state, e)),
     }
 }
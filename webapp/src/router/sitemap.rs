@@ -12,6 +12,7 @@ use crate::state::AppState;
 
 #[tracing::instrument(skip(state))]
 pub async fn sitemap(Host(host): Host, State(state): State<AppState>) -> Response {
+    let state = state.snapshot();
     let base_url = if let Ok(url) = base_url_from_host(&host) {
         url
     } else {
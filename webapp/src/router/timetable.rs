@@ -3,24 +3,84 @@ use axum::{
     extract::{Path, State},
     response::{Html, Redirect},
 };
+use axum_extra::extract::Query;
+use serde::Deserialize;
+use tracing::error;
+use url::form_urlencoded;
 
 use planning::Timetable;
+use shared::filter::SearchFilter;
+use shared::model::EventType;
 use shared::queryable::Queryable;
 
 use crate::filters;
+use crate::router::error::ErrorPage;
+use crate::router::search::empty_strings_skipped;
 use crate::state::AppState;
 
 /// Redirect /next/ to the current year's timetable
 #[tracing::instrument(skip(state))]
 pub async fn next_redirect(State(state): State<AppState>) -> Redirect {
+    let state = state.snapshot();
     let year = state.current_fosdem.year;
     Redirect::temporary(&format!("/{}/timetable/", year))
 }
 
+/// Both can be repeated, e.g. `/2026/timetable/?type=keynote&type=maintrack`
+#[derive(Deserialize, Debug, Default)]
+pub struct TimetableParams {
+    #[serde(default, rename = "type", deserialize_with = "empty_strings_skipped")]
+    types: Vec<EventType>,
+    #[serde(
+        default,
+        rename = "language",
+        deserialize_with = "empty_strings_skipped"
+    )]
+    languages: Vec<String>,
+}
+
+impl TimetableParams {
+    /// A link to `year`'s timetable showing only `value` for `key`, or any value if there's none,
+    /// and keeping whatever the other filters are
+    fn href_with(&self, year: u32, key: &'static str, value: Option<String>) -> String {
+        let mut pairs: Vec<(&str, String)> = self
+            .types
+            .iter()
+            .map(|t| ("type", t.to_string()))
+            .chain(self.languages.iter().map(|l| ("language", l.clone())))
+            .filter(|(k, _)| *k != key)
+            .collect();
+        pairs.extend(value.map(|v| (key, v)));
+        if pairs.is_empty() {
+            return format!("/{}/timetable/", year);
+        }
+        let query = form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(pairs)
+            .finish();
+        format!("/{}/timetable/?{}", year, query)
+    }
+}
+
+#[derive(Debug)]
+struct Choice {
+    label: String,
+    href: String,
+    active: bool,
+}
+
+/// The choices for one filter, which are only worth showing if there's more than one
+#[derive(Debug)]
+struct Choices {
+    all_href: String,
+    choices: Vec<Choice>,
+}
+
 #[derive(Template, Debug)]
 #[template(path = "timetables.html")]
 struct TimetablesTemplate {
     timetables: Vec<Timetable>,
+    /// by type, then by language
+    filters: Vec<Choices>,
     current_fosdem: shared::model::CurrentFosdem,
 }
 
@@ -28,21 +88,84 @@ struct TimetablesTemplate {
 pub async fn timetable(
     State(state): State<AppState>,
     Path(year): Path<u32>,
-) -> axum::response::Result<Html<String>> {
-    let all_events = state.queryable.load_all_events().await.unwrap();
+    Query(params): Query<TimetableParams>,
+) -> Result<Html<String>, ErrorPage> {
+    let state = state.snapshot();
+    let all_events = state
+        .queryable
+        .load_all_events()
+        .await
+        .map_err(|e| ErrorPage::from_query_error(&state, e))?;
 
-    // Filter events for the requested year
+    // Filter events for the requested year, and any types or languages asked for
+    let filter = SearchFilter {
+        years: vec![year],
+        types: params.types.clone(),
+        languages: params.languages.clone(),
+        ..SearchFilter::default()
+    };
+    let mut types_in_year: Vec<EventType> = all_events
+        .iter()
+        .filter(|e| e.year == year)
+        .map(|e| e.event_type)
+        .collect();
+    types_in_year.sort();
+    types_in_year.dedup();
+    let mut languages_in_year: Vec<String> = all_events
+        .iter()
+        .filter(|e| e.year == year)
+        .filter_map(|e| e.language.clone())
+        .collect();
+    languages_in_year.sort();
+    languages_in_year.dedup();
     let events_for_year: Vec<_> = all_events
         .into_iter()
-        .filter(|e| e.year == year)
+        .filter(|e| filter.matches(e))
         .collect();
 
     // Allocate events into timetables
-    let timetables = planning::allocate(&events_for_year)
-        .map_err(|_| axum::http::StatusCode::INTERNAL_SERVER_ERROR)?;
+    let timetables = planning::allocate(&events_for_year).map_err(|e| {
+        error!("Could not make timetables for {}: {:?}", year, e);
+        ErrorPage::internal(&state)
+    })?;
+
+    let type_choices = Choices {
+        all_href: params.href_with(year, "type", None),
+        choices: if types_in_year.len() > 1 {
+            types_in_year
+                .into_iter()
+                .map(|event_type| Choice {
+                    label: event_type.label().to_string(),
+                    href: params.href_with(year, "type", Some(event_type.to_string())),
+                    active: params.types.contains(&event_type),
+                })
+                .collect()
+        } else {
+            vec![]
+        },
+    };
+    let language_choices = Choices {
+        all_href: params.href_with(year, "language", None),
+        choices: if languages_in_year.len() > 1 {
+            languages_in_year
+                .into_iter()
+                .map(|language| Choice {
+                    active: params
+                        .languages
+                        .iter()
+                        .any(|l| l.eq_ignore_ascii_case(&language)),
+                    href: params.href_with(year, "language", Some(language.clone())),
+                    label: language,
+                })
+                .collect()
+        } else {
+            vec![]
+        },
+    };
 
     let page = TimetablesTemplate {
         timetables,
+        filters: vec![type_choices, language_choices],
         current_fosdem: state.current_fosdem.clone(),
     };
     let html = page.render().unwrap();
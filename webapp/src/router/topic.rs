@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+
+use crate::router::error::ErrorPage;
+use crate::state::AppState;
+use askama::Template;
+use axum::extract::Path;
+use axum::http::StatusCode;
+use axum::{extract::State, response::Html};
+use shared::model::{Event, EventId};
+use shared::queryable::Queryable;
+use shared::topics::{Topic, TopicId};
+
+/// how many of the events closest to the centre of a topic to show in the list of topics
+const EXAMPLE_EVENTS_PER_TOPIC: usize = 3;
+
+#[derive(Debug)]
+struct TopicSummary {
+    id: TopicId,
+    name: String,
+    /// how many events the topic has in the year being shown
+    event_count: usize,
+    examples: Vec<Event>,
+}
+
+#[derive(Template, Debug)]
+#[template(path = "topics.html")]
+struct TopicsTemplate {
+    year: u32,
+    topics: Vec<TopicSummary>,
+    topics_across_years: Vec<TopicSummary>,
+    current_fosdem: shared::model::CurrentFosdem,
+}
+
+#[derive(Template, Debug)]
+#[template(path = "topic.html")]
+struct TopicTemplate {
+    topic: Topic,
+    /// closest to the centre first
+    events: Vec<Event>,
+    current_event: Option<Event>, // TODO: remove this
+    current_fosdem: shared::model::CurrentFosdem,
+}
+
+fn summary(topic: &Topic, year: u32, events: &HashMap<EventId, Event>) -> TopicSummary {
+    TopicSummary {
+        id: topic.id.clone(),
+        name: topic.name.clone(),
+        event_count: topic.members_in_year(year).count(),
+        examples: topic
+            .members_in_year(year)
+            .filter_map(|member| events.get(&member.event_id).cloned())
+            .take(EXAMPLE_EVENTS_PER_TOPIC)
+            .collect(),
+    }
+}
+
+#[tracing::instrument(skip(state))]
+pub async fn topics(
+    State(state): State<AppState>,
+    Path(year): Path<u32>,
+) -> Result<Html<String>, ErrorPage> {
+    let state = state.snapshot();
+    let events: HashMap<EventId, Event> = state
+        .queryable
+        .load_all_events()
+        .await
+        .map_err(|e| ErrorPage::from_query_error(&state, e))?
+        .into_iter()
+        .map(|event| (event.id, event))
+        .collect();
+    let page = TopicsTemplate {
+        year,
+        topics: state
+            .topic_index
+            .for_year(year)
+            .into_iter()
+            .map(|topic| summary(topic, year, &events))
+            .collect(),
+        topics_across_years: state
+            .topic_index
+            .across_years_in(year)
+            .into_iter()
+            .map(|topic| summary(topic, year, &events))
+            .collect(),
+        current_fosdem: state.current_fosdem.clone(),
+    };
+    let html = page.render().unwrap();
+    Ok(Html(html))
+}
+
+#[tracing::instrument(skip(state))]
+pub async fn topic(
+    State(state): State<AppState>,
+    Path(topic_id): Path<String>,
+) -> Result<Html<String>, ErrorPage> {
+    let state = state.snapshot();
+    let Some(topic) = state.topic_index.find(&TopicId::parse(&topic_id)).cloned() else {
+        return Err(ErrorPage::new(
+            &state,
+            StatusCode::NOT_FOUND,
+            format!("No topic {}", topic_id),
+        ));
+    };
+    let mut events: HashMap<EventId, Event> = state
+        .queryable
+        .load_all_events()
+        .await
+        .map_err(|e| ErrorPage::from_query_error(&state, e))?
+        .into_iter()
+        .map(|event| (event.id, event))
+        .collect();
+    let events = topic
+        .members
+        .iter()
+        .filter_map(|member| events.remove(&member.event_id))
+        .collect();
+    let page = TopicTemplate {
+        topic,
+        events,
+        current_event: None,
+        current_fosdem: state.current_fosdem.clone(),
+    };
+    let html = page.render().unwrap();
+    Ok(Html(html))
+}
@@ -6,9 +6,11 @@ use axum::{
 };
 
 use serde::Deserialize;
+use shared::error::QueryError;
 use shared::model::{self, Event};
 use validator::Validate;
 
+use crate::router::error::ErrorPage;
 use crate::state::AppState;
 use shared::queryable::Queryable;
 
@@ -30,22 +32,21 @@ struct EventVideoTemplate {
 pub async fn event_video(
     State(state): State<AppState>,
     Path((year, event_in_year_id)): Path<(u32, u32)>,
-) -> axum::response::Result<Html<String>> {
-    match state
+) -> Result<Html<String>, ErrorPage> {
+    let state = state.snapshot();
+    let event_id = model::EventId::new(year, event_in_year_id);
+    let event = state
         .queryable
-        .find_event_by_id(model::EventId::new(year, event_in_year_id))
+        .find_event_by_id(event_id)
         .await
-    {
-        Ok(Some(event)) => {
-            let page = EventVideoTemplate {
-                event,
-                current_fosdem: state.current_fosdem.clone(),
-            };
-            let html = page.render().unwrap();
-            Ok(Html(html))
-        }
-        _ => Err("failed".into()),
-    }
+        .and_then(|event| event.ok_or(QueryError::NotFound(event_id)))
+        .map_err(|e| ErrorPage::from_query_error(&state, e))?;
+    let page = EventVideoTemplate {
+        event,
+        current_fosdem: state.current_fosdem.clone(),
+    };
+    let html = page.render().unwrap();
+    Ok(Html(html))
 }
 
 #[tracing::instrument(skip(state))]
@@ -53,6 +54,7 @@ pub async fn event_video_webvtt(
     State(state): State<AppState>,
     Path((year, event_in_year_id)): Path<(u32, u32)>,
 ) -> impl IntoResponse {
+    let state = state.snapshot();
     match state
         .video_index
         .webvtt_for_event_id(model::EventId::new(year, event_in_year_id))
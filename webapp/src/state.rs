@@ -1,13 +1,194 @@
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 
+use arc_swap::ArcSwap;
 use blog::BlogIndex;
-use content::video_index::VideoIndex;
-use shared::{inmemory_openai::InMemoryOpenAIQueryable, model::CurrentFosdem};
+use chrono::{DateTime, Utc};
+use content::{slide_content::load_slide_content, video_index::VideoIndex};
+use shared::{
+    embedding::EmbeddingProviderConfig, inmemory_openai::InMemoryOpenAIQueryable,
+    manifest::ManifestPolicy, map::EventMap, model::CurrentFosdem, nearest::IndexConfig,
+    queryable::Queryable, topics::TopicIndex,
+};
+use tracing::{error, info};
+
+/// Everything loaded from the model dir and content dirs. A reload builds a whole new snapshot,
+/// and then swaps it in, so a request sees either all old or all new content.
+#[derive(Debug)]
+pub struct Snapshot {
+    pub queryable: InMemoryOpenAIQueryable,
+    pub video_index: VideoIndex,
+    pub current_fosdem: CurrentFosdem,
+    pub blog_index: BlogIndex,
+    pub topic_index: TopicIndex,
+    pub event_map: EventMap,
+}
+
+/// Where a `Snapshot` is loaded from
+#[derive(Debug, Clone)]
+pub struct SnapshotLoader {
+    pub model_dir: PathBuf,
+    pub embedding: EmbeddingProviderConfig,
+    pub index_config: IndexConfig,
+    pub manifest_policy: ManifestPolicy,
+    pub video_content_dir: Option<PathBuf>,
+    pub slide_content_dir: Option<PathBuf>,
+    pub blog_content_dir: PathBuf,
+    pub current_year: u32,
+    pub selectable_years: Vec<u32>,
+}
+
+impl SnapshotLoader {
+    pub async fn load(
+        &self,
+        updated_at: DateTime<Utc>,
+    ) -> Result<Snapshot, Box<dyn std::error::Error>> {
+        let mut queryable = InMemoryOpenAIQueryable::connect(
+            &self.model_dir,
+            self.embedding.build()?,
+            self.index_config,
+            self.manifest_policy,
+        )
+        .await?;
+        let video_index = if let Some(base_path) = &self.video_content_dir {
+            VideoIndex::from_content_area(base_path)?
+        } else {
+            VideoIndex::empty_index()
+        };
+        queryable.add_lexical_text(video_index.transcripts());
+        if let Some(base_path) = &self.slide_content_dir {
+            let events = queryable.load_all_events().await?;
+            queryable.add_lexical_text(load_slide_content(base_path, &events)?);
+        }
+        Ok(Snapshot {
+            queryable,
+            video_index,
+            current_fosdem: CurrentFosdem {
+                year: self.current_year,
+                selectable_years: self.selectable_years.clone(),
+                updated_at,
+            },
+            blog_index: BlogIndex::load_from_dir(&self.blog_content_dir)?,
+            topic_index: TopicIndex::load(&self.model_dir)?,
+            event_map: EventMap::load(&self.model_dir)?,
+        })
+    }
+
+    /// When any file in the model dir was last changed, as a cheap way to notice a re-import
+    fn model_dir_modified(&self) -> Option<SystemTime> {
+        std::fs::read_dir(&self.model_dir)
+            .ok()?
+            .filter_map(|entry| entry.ok()?.metadata().ok()?.modified().ok())
+            .max()
+    }
+}
 
 #[derive(Clone, Debug)]
 pub struct AppState {
-    pub queryable: Arc<InMemoryOpenAIQueryable>,
-    pub video_index: Arc<VideoIndex>,
-    pub current_fosdem: CurrentFosdem,
-    pub blog_index: Arc<BlogIndex>,
+    snapshot: Arc<ArcSwap<Snapshot>>,
+    loader: Arc<SnapshotLoader>,
+    reloading: Arc<AtomicBool>,
+    /// the bearer token needed to trigger a reload; reloading over HTTP is off without one
+    admin_token: Option<Arc<str>>,
+}
+
+impl AppState {
+    pub async fn load(
+        loader: SnapshotLoader,
+        admin_token: Option<String>,
+    ) -> Result<AppState, Box<dyn std::error::Error>> {
+        let snapshot = loader.load(Utc::now()).await?;
+        Ok(AppState {
+            snapshot: Arc::new(ArcSwap::from_pointee(snapshot)),
+            loader: Arc::new(loader),
+            reloading: Arc::new(AtomicBool::new(false)),
+            admin_token: admin_token.map(Arc::from),
+        })
+    }
+
+    /// The current snapshot. Hold on to it for the whole of a request, so that a reload part
+    /// way through doesn't mix old and new content.
+    pub fn snapshot(&self) -> Arc<Snapshot> {
+        self.snapshot.load_full()
+    }
+
+    pub fn is_admin(&self, token: &str) -> bool {
+        self.admin_token
+            .as_ref()
+            .is_some_and(|admin_token| constant_time_eq(admin_token.as_bytes(), token.as_bytes()))
+    }
+
+    /// Start rebuilding the snapshot in the background, swapping it in if it loads, and keeping
+    /// the current one if it doesn't. Returns false if a reload is already under way.
+    pub fn reload_in_background(&self) -> bool {
+        if self.reloading.swap(true, Ordering::SeqCst) {
+            return false;
+        }
+        let state = self.clone();
+        // building the index is CPU-bound, so keep it off the threads serving requests
+        tokio::task::spawn_blocking(move || {
+            // cleared however the reload ends, including if loading panics
+            let _reloading = ReloadingGuard(state.reloading.clone());
+            let handle = tokio::runtime::Handle::current();
+            info!("Reloading from {:?}", state.loader.model_dir);
+            match handle.block_on(state.loader.load(Utc::now())) {
+                Ok(snapshot) => {
+                    state.snapshot.store(Arc::new(snapshot));
+                    info!("Reloaded");
+                }
+                Err(e) => error!("Reload failed, keeping current content: {}", e),
+            }
+        });
+        true
+    }
+
+    /// Check the model dir every `interval`, and reload when anything in it changes
+    pub fn watch_model_dir(&self, interval: Duration) {
+        let state = self.clone();
+        tokio::spawn(async move {
+            let mut last_modified = state.loader.model_dir_modified();
+            let mut ticks = tokio::time::interval(interval);
+            loop {
+                ticks.tick().await;
+                let modified = state.loader.model_dir_modified();
+                if modified != last_modified && state.reload_in_background() {
+                    info!("{:?} changed", state.loader.model_dir);
+                    last_modified = modified;
+                }
+            }
+        });
+    }
+}
+
+/// Marks a reload as no longer under way when dropped
+struct ReloadingGuard(Arc<AtomicBool>);
+
+impl Drop for ReloadingGuard {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reloading_is_cleared_when_a_reload_panics() {
+        let reloading = Arc::new(AtomicBool::new(true));
+
+        let result = std::panic::catch_unwind(|| {
+            let _reloading = ReloadingGuard(reloading.clone());
+            panic!("loading failed");
+        });
+
+        assert!(result.is_err());
+        assert!(!reloading.load(Ordering::SeqCst));
+    }
 }
@@ -1,15 +1,66 @@
+use axum::{http::StatusCode, routing::get};
 use reqwest::blocking::{Client, Response};
 use reqwest::Result;
+use shared::manifest::ManifestPolicy;
 use shared::model::EventId;
+use shared::nearest::IndexConfig;
 use std::env;
+use std::path::PathBuf;
+use std::sync::{mpsc, OnceLock};
 use test_shared::{
-    EVENT_ID_2025, EVENT_ID_2025_BACKWARDS_COMPATIBLE_PATH, EVENT_ID_2025_CANONICAL_PATH,
-    EVENT_ID_2025_CONTENT_SAMPLE, EVENT_ID_2026, EVENT_ID_2026_CANONICAL_PATH,
-    EVENT_ID_2026_CONTENT_SAMPLE, SEARCH_TERM,
+    embedding_stub::EmbeddingStub, EVENT_ID_2025, EVENT_ID_2025_BACKWARDS_COMPATIBLE_PATH,
+    EVENT_ID_2025_CANONICAL_PATH, EVENT_ID_2025_CONTENT_SAMPLE, EVENT_ID_2026,
+    EVENT_ID_2026_CANONICAL_PATH, EVENT_ID_2026_CONTENT_SAMPLE, MODEL_DIR, SEARCH_TERM,
 };
+use webapp::router::router;
+use webapp::state::{AppState, SnapshotLoader};
 
+/// `TEST_BASE_URL`, e.g. `http://localhost:8000`, or else a server started within the tests
 fn get_base_url() -> String {
-    env::var("TEST_BASE_URL").unwrap_or_else(|_| "http://localhost:8000".to_string())
+    env::var("TEST_BASE_URL").unwrap_or_else(|_| in_process_base_url().to_string())
+}
+
+fn in_process_base_url() -> &'static str {
+    static BASE_URL: OnceLock<String> = OnceLock::new();
+    BASE_URL.get_or_init(start_in_process)
+}
+
+/// Serve the test model dir, as `just webapp` would, but getting query embeddings from an
+/// `EmbeddingStub`, so that no OpenAI key is needed
+fn start_in_process() -> String {
+    let (started, base_url) = mpsc::channel();
+    std::thread::spawn(move || {
+        let runtime = tokio::runtime::Runtime::new().expect("Failed to create runtime");
+        runtime.block_on(async {
+            let stub = EmbeddingStub::start().await;
+            let workspace = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("..");
+            let loader = SnapshotLoader {
+                model_dir: PathBuf::from(MODEL_DIR),
+                embedding: stub.provider_config(),
+                index_config: IndexConfig::BruteForce,
+                manifest_policy: ManifestPolicy::Strict,
+                video_content_dir: None,
+                slide_content_dir: None,
+                blog_content_dir: workspace.join("blog/content/posts"),
+                current_year: 2026,
+                selectable_years: vec![2024, 2025, 2026],
+            };
+            let state = AppState::load(loader, None)
+                .await
+                .expect("Failed to load model");
+            let app = router(state)
+                .await
+                .route("/health", get(|| async { StatusCode::NO_CONTENT }));
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+                .await
+                .expect("Failed to bind");
+            // as localhost, which the sitemap allows as a host
+            let port = listener.local_addr().unwrap().port();
+            started.send(format!("http://localhost:{}", port)).unwrap();
+            axum::serve(listener, app).await.expect("Server failed");
+        });
+    });
+    base_url.recv().expect("Server failed to start")
 }
 
 fn client() -> Client {
@@ -57,6 +108,16 @@ fn test_homepage_contains_expected_content() {
     );
 }
 
+#[test]
+fn test_homepage_summarises_each_year_by_day_and_track() {
+    let response = exists_at_path("/").expect("exists");
+
+    let body = response.text().expect("Failed to read body");
+    assert!(body.contains("id=\"summary-2026\""), "2026 breakdown");
+    assert!(body.contains("Compared to 2025"), "change since 2025");
+    assert!(body.contains("returning)"), "returning presenters");
+}
+
 fn event_id_as_anchor_text(event_id: EventId) -> String {
     format!(
         "<a name=\"{}-{}\"></a>",
@@ -88,6 +149,13 @@ fn test_search_for_any_year_with_year_param_as_empty_string() {
     assert_any_year_search(&format!("/search?q={SEARCH_TERM}&limit=20&year="));
 }
 
+#[test]
+fn test_search_for_any_year_in_each_mode() {
+    for mode in ["lexical", "semantic", "hybrid"] {
+        assert_any_year_search(&format!("/search?q={SEARCH_TERM}&limit=20&mode={mode}"));
+    }
+}
+
 #[test]
 fn test_search_for_2025_only() {
     let response = client()
@@ -106,6 +174,55 @@ fn test_search_for_2025_only() {
     assert!(!body.contains(&event_id_as_anchor_text(EVENT_ID_2026)));
 }
 
+fn search_body(path_and_query: &str) -> String {
+    let response = client()
+        .get(format!("{}{}", get_base_url(), path_and_query))
+        .send()
+        .expect("Failed to send request");
+
+    assert_eq!(response.status(), 200);
+
+    response.text().expect("Failed to read body")
+}
+
+#[test]
+fn test_search_with_slides_only() {
+    let body = search_body(&format!("/search?q={SEARCH_TERM}&limit=20&has_slides=true"));
+    assert!(body.contains(&event_id_as_anchor_text(EVENT_ID_2025)));
+    assert!(!body.contains(&event_id_as_anchor_text(EVENT_ID_2026)));
+}
+
+#[test]
+fn test_search_in_repeated_tracks() {
+    let body = search_body(&format!(
+        "/search?q={SEARCH_TERM}&limit=20&track=FOSS+on+Mobile&track=Lightning+Talks"
+    ));
+    assert!(body.contains(&event_id_as_anchor_text(EVENT_ID_2025)));
+    assert!(body.contains(&event_id_as_anchor_text(EVENT_ID_2026)));
+
+    let body = search_body(&format!(
+        "/search?q={SEARCH_TERM}&limit=20&track=FOSS+on+Mobile&day=Sat"
+    ));
+    assert!(!body.contains(&event_id_as_anchor_text(EVENT_ID_2025)));
+    assert!(body.contains(&event_id_as_anchor_text(EVENT_ID_2026)));
+}
+
+#[test]
+fn test_search_by_event_type() {
+    let body = search_body(&format!(
+        "/search?q={SEARCH_TERM}&limit=20&type=lightningtalk"
+    ));
+    assert!(body.contains(&event_id_as_anchor_text(EVENT_ID_2025)));
+    assert!(!body.contains(&event_id_as_anchor_text(EVENT_ID_2026)));
+}
+
+#[test]
+fn test_search_offers_refinements() {
+    let body = search_body(&format!("/search?q={SEARCH_TERM}&limit=20"));
+    assert!(body.contains("id=\"refinements\""));
+    assert!(body.contains("has_video=true"));
+}
+
 fn assert_2025_content(response: Response) {
     let body = response.text().expect("Failed to read body");
     assert!(body.contains(EVENT_ID_2025_CONTENT_SAMPLE));
@@ -182,6 +299,27 @@ fn test_timetable_2026_exists() {
     assert_generic_timetable_content(&body);
 }
 
+#[test]
+fn test_timetable_by_event_type() {
+    let response = exists_at_path("/2026/timetable/?type=maintrack").expect("exists");
+    let body = response.text().expect("Failed to read body");
+    assert_generic_timetable_content(&body);
+    assert!(body.contains("Main track"));
+    assert!(!body.contains(&format!(
+        "/{}/event/{}/",
+        EVENT_ID_2026.year(),
+        EVENT_ID_2026.event_in_year()
+    )));
+}
+
+#[test]
+fn test_timetable_type_choices_keep_the_language() {
+    let response = exists_at_path("/2026/timetable/?type=maintrack&language=en").expect("exists");
+    let body = response.text().expect("Failed to read body");
+    assert!(body.contains("href=\"/2026/timetable/?language=en&#38;type=devroom\""));
+    assert!(body.contains("href=\"/2026/timetable/?language=en\""));
+}
+
 #[test]
 fn test_blog_list_exists() {
     let response = exists_at_path("/blog/").expect("exists");
@@ -238,3 +376,148 @@ fn test_next_redirects_to_current_year_timetable() {
         .expect("Missing Location header");
     assert_eq!(location, "/2026/timetable/");
 }
+
+#[test]
+fn test_unknown_event_is_not_found() {
+    let response = client()
+        .get(format!("{}/2025/event/1/", get_base_url()))
+        .send()
+        .expect("Failed to send request");
+
+    assert_eq!(response.status(), 404);
+    let body = response.text().expect("Failed to read body");
+    assert!(body.contains("Event not found: 2025-1"));
+}
+
+#[test]
+fn test_invalid_search_is_bad_request() {
+    let response = client()
+        .get(format!("{}/search?q=x&limit=20", get_base_url()))
+        .send()
+        .expect("Failed to send request");
+
+    assert_eq!(response.status(), 400);
+}
+
+#[test]
+fn test_search_with_lambda_out_of_range_is_bad_request() {
+    let response = client()
+        .get(format!("{}/search?q=rust&limit=5&lambda=2", get_base_url()))
+        .send()
+        .expect("Failed to send request");
+
+    assert_eq!(response.status(), 400);
+}
+
+#[test]
+fn test_reload_needs_admin_token() {
+    for authorization in [None, Some("Bearer not-the-admin-token")] {
+        let mut request = client().post(format!("{}/admin/reload", get_base_url()));
+        if let Some(authorization) = authorization {
+            request = request.header("Authorization", authorization);
+        }
+        let response = request.send().expect("Failed to send request");
+
+        assert_eq!(response.status(), 401);
+    }
+}
+
+#[test]
+fn test_recommendations_exclude_seeds() {
+    let seeds = format!("{} {}", EVENT_ID_2025, EVENT_ID_2026);
+    let url = format!(
+        "{}/recommendations?ids={}",
+        get_base_url(),
+        url::form_urlencoded::byte_serialize(seeds.as_bytes()).collect::<String>()
+    );
+    let response = client().get(url).send().expect("Failed to send request");
+
+    assert_eq!(response.status(), 200);
+    let body = response.text().expect("Failed to read body");
+    assert!(body.contains("data-event-id"));
+    for seed in [EVENT_ID_2025, EVENT_ID_2026] {
+        assert!(!body.contains(&format!("data-event-id=\"{}\"", seed)));
+    }
+}
+
+#[test]
+fn test_recommendations_with_bad_id_is_bad_request() {
+    let response = client()
+        .get(format!("{}/recommendations?ids=not-an-id", get_base_url()))
+        .send()
+        .expect("Failed to send request");
+
+    assert_eq!(response.status(), 400);
+}
+
+#[test]
+fn test_presenters_link_to_their_person_page() {
+    let event_page = exists_at_path(EVENT_ID_2026_CANONICAL_PATH)
+        .expect("exists")
+        .text()
+        .expect("Failed to read body");
+    let start = event_page.find("href=\"/person/").expect("person link") + "href=\"".len();
+    let end = start + event_page[start..].find('"').unwrap();
+
+    let person_page = exists_at_path(&event_page[start..end])
+        .expect("exists")
+        .text()
+        .expect("Failed to read body");
+    assert!(person_page.contains(&format!("data-event-id=\"{}\"", EVENT_ID_2026)));
+}
+
+#[test]
+fn test_unknown_person_is_not_found() {
+    let response = client()
+        .get(format!("{}/person/no-such-person/", get_base_url()))
+        .send()
+        .expect("Failed to send request");
+
+    assert_eq!(response.status(), 404);
+}
+
+#[test]
+fn test_topics_link_to_topic_pages() {
+    let topics_page = exists_at_path("/2026/topics/")
+        .expect("exists")
+        .text()
+        .expect("Failed to read body");
+    let start = topics_page.find("href=\"/topic/").expect("topic link") + "href=\"".len();
+    let end = start + topics_page[start..].find('"').unwrap();
+
+    exists_at_path(&topics_page[start..end]).expect("exists");
+}
+
+#[test]
+fn test_unknown_topic_is_not_found() {
+    let response = client()
+        .get(format!("{}/topic/no-such-topic/", get_base_url()))
+        .send()
+        .expect("Failed to send request");
+
+    assert_eq!(response.status(), 404);
+}
+
+#[test]
+fn test_map_json_is_filtered_by_year() {
+    let body = exists_at_path("/map.json?year=2026")
+        .expect("exists")
+        .text()
+        .expect("Failed to read body");
+    let entries: Vec<serde_json::Value> = serde_json::from_str(&body).expect("json");
+
+    assert!(entries.iter().all(|entry| entry["year"] == 2026));
+    assert!(entries
+        .iter()
+        .any(|entry| entry["event_id"] == EVENT_ID_2026.to_string()));
+}
+
+#[test]
+fn test_map_links_to_events() {
+    let body = exists_at_path("/map?year=2026")
+        .expect("exists")
+        .text()
+        .expect("Failed to read body");
+
+    assert!(body.contains(&format!("href=\"{}\"", EVENT_ID_2026_CANONICAL_PATH)));
+}